@@ -0,0 +1,34 @@
+//! Generates the 16 bit decode table from the instruction descriptions
+//!
+//! The descriptions are compiled into the build script directly from the crate sources, and the complete table is
+//! written out as an array expression which the processor includes as a `static`.
+
+#![allow(dead_code, unused_imports, unused_macros)]
+
+#[macro_use]
+#[path = "src/decode.rs"]
+mod decode;
+
+#[path = "src/instructions/mod.rs"]
+mod instructions;
+
+use std::fmt::Write;
+use instructions::InstrThumb16;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/decode.rs");
+    println!("cargo:rerun-if-changed=src/instructions");
+
+    let table = InstrThumb16::generate_decode_table();
+
+    let mut source = String::from("[\n");
+    for (encoded, decoded) in table.iter().enumerate() {
+        writeln!(source, "    InstrThumb16::{:?}, // {:#06X}", decoded, encoded).unwrap();
+    }
+    source.push_str("]\n");
+
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    let path = std::path::Path::new(&out_dir).join("decode_table.rs");
+    std::fs::write(path, source).expect("failed to write the decode table");
+}
@@ -1,9 +1,6 @@
-#[macro_use]
-use std::ops::{ Add, RangeInclusive };
-use std::any::{ TypeId, Any };
-use std::rc::Rc;
-use std::cell::RefCell;
-use log::{ info, debug, trace };
+use std::ops::Add;
+use std::any::Any;
+use log::{ debug, trace };
 use crate::instructions::InstrThumb16;
 
 
@@ -98,51 +95,253 @@ use crate::instructions::InstrThumb16;
 
 
 
-/// What do we need to capture to fully describe an instruction?
-///     * Name, long name, and optional description
-///     * Family, thumb or thumb2
-///     * Arity, discovered implicitly
-///     * Invariant, the value which defines the instruction as being itself
-///     * The algebraic variant of the instruction
-///     * A description of each operand
-///     * Individual encodings
+// What do we need to capture to fully describe an instruction?
+//     * Name, long name, and optional description
+//     * Family, thumb or thumb2
+//     * Arity, discovered implicitly
+//     * Invariant, the value which defines the instruction as being itself
+//     * The algebraic variant of the instruction
+//     * A description of each operand
+//     * Individual encodings
+// 
+// What do we need to capture to fully describe an operand?
+//     * Name, optional long name, and optional description
+//     * The bit width of the operand
+//     * The number of bits shifted from the right to the left of each operand
+//     * The language representation of each operand, including whether it is signed or unsigned
+//     * Whether the operand is a composite of bit sub-slices of the instruction
+// 
+// How do we want to represent that captured data?
+//     * Verbose, immutable, structured data
+// 
+// How can we simplify the definitions of operands?
+//     * Templates for commonly used operands?
+// 
+
+/// Creates the pair of functions which write a decoded operand value into a field of an instruction variant, and read
+/// it back out again when encoding
 /// 
-/// What do we need to capture to fully describe an operand?
-///     * Name, optional long name, and optional description
-///     * The bit width of the operand
-///     * The number of bits shifted from the right to the left of each operand
-///     * The language representation of each operand, including whether it is signed or unsigned
-///     * Whether the operand is a composite of bit sub-slices of the instruction
-/// 
-/// How do we want to represent that captured data?
-///     * Verbose, immutable, structured data
-/// 
-/// How can we simplify the definitions of operands?
-///     * Templates for commonly used operands?
-/// 
-
+/// A list of fields may be given instead of a single field, in which case the value is written into each of them and
+/// read back from the first. This is used by encodings where a single operand names a register which is both a source
+/// and the destination.
 #[allow(unused_macros)]
 macro_rules! map_operand {
-    ($instr:path, $op:ident, $repr:ident) => {
+    ($instr:path, [$first:ident $(, $op:ident)*], $repr:ident) => {
         #[allow(unused_variables)]
         {
-            Box::new(|_s, mut _i, _o| {
-                //trace!("_i: {:?}", _i);
-                //trace!("    Operand {{ name: {:?}, repr: {:?} }}", _s.name, _s.repr);
+            let map: $crate::decode::OperatorMapFn = Box::new(|_s, _i, _o| {
                 match _i {
-                    $instr{ ref mut $op, .. } => {
+                    $instr{ ref mut $first, $(ref mut $op,)* .. } => {
                         let _temp_downcasted = *_o.downcast_ref::<i64>().expect("invalid signed operand intermediary downcast");
-                        *$op = _temp_downcasted as $repr;
+                        *$first = _temp_downcasted as $repr;
+                        $(*$op = _temp_downcasted as $repr;)*
                     },
                     m => {
-                        panic!(format!("invalid instruction operand field map: {:?}", m));
+                        panic!("invalid instruction operand field map: {:?}", m);
                     }
                 }
-            })
+            });
+
+            let read: $crate::decode::OperatorReadFn = Box::new(|_i| {
+                match _i {
+                    $instr{ $first, .. } => Some(*$first as i64),
+                    _ => None
+                }
+            });
+
+            (map, read)
+        }
+    };
+
+    ($instr:path, $op:ident, $repr:ident) => {
+        map_operand!($instr, [$op], $repr)
+    };
+}
+
+/// Maps the Rust type of an instruction field onto its operand representation
+#[allow(unused_macros)]
+macro_rules! operand_repr {
+    (i8) => { $crate::decode::OperandRepr::SignedByte };
+    (i16) => { $crate::decode::OperandRepr::SignedShort };
+    (i32) => { $crate::decode::OperandRepr::SignedWord };
+    (u8) => { $crate::decode::OperandRepr::UnsignedByte };
+    (u16) => { $crate::decode::OperandRepr::UnsignedShort };
+    (u32) => { $crate::decode::OperandRepr::UnsignedWord };
+}
+
+/// Defines the complete set of 16 bit instruction descriptions
+/// 
+/// Takes a comma separated list of `instruction!` definitions and evaluates to a `Vec<InstrDesc>`.
+#[allow(unused_macros)]
+macro_rules! define_instructions {
+    ($($instruction:expr),* $(,)?) => {
+        vec![$($instruction),*]
+    };
+}
+
+/// Defines a single instruction and each of its encodings
+/// 
+/// ```text
+/// instruction! {
+///     name: AddImm,
+///     encoding: [
+///         base: 0x3000,
+///         operand: [imm, 8 << 0],
+///         operand: [rdn | rd, 3 << 8]
+///     ]
+/// }
+/// ```
+/// 
+/// `name` is both the name of the instruction and of its `InstrThumb16` variant, and every encoding names each field of
+/// that variant exactly once. An operand is written `[field, slices]`:
+/// 
+///  - `W << S` is a slice of `W` bits shifted `S` bits from the right, several slices are joined with `:` most
+///    significant first and `zeros N` inserts constant zero bits, as in `i:imm5:'0'` which is `1 << 9 : 5 << 3 : zeros 1`
+///  - `, scale N` shifts the extended value `N` bits to the left
+///  - `, as T` sets the type of the field, which is `u8` unless given, and a signed type sign extends the value
+///  - `field | alias` writes the same value into further fields, for registers which are both a source and destination
+/// 
+///  - `, read`, `, write` or `, readwrite` marks the field as a register number accessed by the instruction, and
+///    `, list read` or `, list write` as a register list
+/// 
+/// A field which this encoding does not hold is written `[field, unused]`, or `[field, unused = value]` where the field
+/// is implied by the encoding, such as the SP of `add sp, #imm`, which may also be given an access. Encodings are named
+/// `E1`, `E2` and so on in the order they are given.
+/// 
+/// The semantics shared by all of the encodings follow the name, each is optional but they must appear in this order:
+/// 
+///  - `class: Branch`, one of the `InstrClass` variants, `DataProcessing` unless given
+///  - `flags: NZCV`, the APSR flags written
+///  - `cycles: 1` or `cycles: 1 + N`, the nominal Cortex-M3 cycle count, plus one per register of a register list,
+///    writes to the PC add a pipeline refill automatically
+///  - `reads: [sp]` and `writes: [sp, pc]`, registers accessed without being named by an operand
+#[allow(unused_macros)]
+macro_rules! instruction {
+    (
+        name: $name:ident
+        $(, class: $class:ident)?
+        $(, flags: $flags:ident)?
+        $(, cycles: $cycles:literal $(+ $per_register:ident)?)?
+        $(, reads: [$($read:ident),*])?
+        $(, writes: [$($write:ident),*])?
+        $(, encoding: [$($encoding:tt)*])* $(,)?
+    ) => {
+        $crate::decode::InstrDesc::new()
+            .name(stringify!($name))
+            $(.class($crate::decode::InstrClass::$class))?
+            $(.flags($crate::decode::Flags::parse(stringify!($flags))))?
+            $(.cycles($cycles, instruction_per_register!($($per_register)?)))?
+            $($(.implicit(register_number!($read), $crate::decode::Access::Read))*)?
+            $($(.implicit(register_number!($write), $crate::decode::Access::Write))*)?
+            $(.encoding(instruction_encoding!($name, $($encoding)*)))*
+            .build()
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! instruction_per_register {
+    (N) => { true };
+    () => { false };
+}
+
+/// The number of a register named in an instruction definition
+#[allow(unused_macros)]
+macro_rules! register_number {
+    (sp) => { 13 };
+    (lr) => { 14 };
+    (pc) => { 15 };
+}
+
+/// The access named by an operand of an instruction definition
+#[allow(unused_macros)]
+macro_rules! operand_access {
+    (read) => { $crate::decode::Access::Read };
+    (write) => { $crate::decode::Access::Write };
+    (readwrite) => { $crate::decode::Access::ReadWrite };
+}
+
+/// Expands a single `encoding: [...]` of an `instruction!` definition into an `Encoding`
+#[allow(unused_macros)]
+macro_rules! instruction_encoding {
+    ($name:ident, base: $base:expr $(, operand: [$($operand:tt)*])* $(,)?) => {
+        {
+            let encoding = $crate::decode::Encoding::new()
+                .invariant($base)
+                .ctor(instruction_ctor!($name, [] $([$($operand)*])*));
+
+            $(let encoding = instruction_operand!(encoding, $name, [$($operand)*]);)*
+            encoding.build()
         }
     };
 }
 
+/// Builds the variant constructor of an encoding, with each field set to its default or its implied value
+#[allow(unused_macros)]
+macro_rules! instruction_ctor {
+    ($name:ident, [$($field:ident: $value:expr,)*]) => {
+        Box::new(|| $crate::instructions::InstrThumb16::$name { $($field: $value),* })
+    };
+
+    ($name:ident, [$($acc:tt)*] [$field:ident, unused = $value:expr $(, $access:ident)?] $($rest:tt)*) => {
+        instruction_ctor!($name, [$($acc)* $field: $value,] $($rest)*)
+    };
+
+    ($name:ident, [$($acc:tt)*] [$field:ident $(| $alias:ident)*, $($spec:tt)*] $($rest:tt)*) => {
+        instruction_ctor!($name, [$($acc)* $field: Default::default(), $($alias: Default::default(),)*] $($rest)*)
+    };
+}
+
+/// Adds a single operand of an encoding to an `EncodingBuilder`, unused operands add nothing
+#[allow(unused_macros)]
+macro_rules! instruction_operand {
+    ($encoding:expr, $name:ident, [$field:ident, unused = $value:expr, $access:ident]) => {
+        $encoding.implicit($value, operand_access!($access))
+    };
+
+    ($encoding:expr, $name:ident, [$field:ident, unused $(= $value:expr)?]) => {
+        $encoding
+    };
+
+    ($encoding:expr, $name:ident, [$field:ident $(| $alias:ident)*, $($spec:tt)*]) => {
+        instruction_operand!(@spec $encoding, $name, [$field $(, $alias)*], $crate::decode::Operand::new().name(stringify!($field)), u8; $($spec)*)
+    };
+
+    (@spec $encoding:expr, $name:ident, $fields:tt, $operand:expr, $repr:ident; $width:literal << $shift:literal $($rest:tt)*) => {
+        instruction_operand!(@spec $encoding, $name, $fields, $operand.slice($width, $shift), $repr; $($rest)*)
+    };
+
+    (@spec $encoding:expr, $name:ident, $fields:tt, $operand:expr, $repr:ident; zeros $count:literal $($rest:tt)*) => {
+        instruction_operand!(@spec $encoding, $name, $fields, $operand.zeros($count), $repr; $($rest)*)
+    };
+
+    (@spec $encoding:expr, $name:ident, $fields:tt, $operand:expr, $repr:ident; : $($rest:tt)*) => {
+        instruction_operand!(@spec $encoding, $name, $fields, $operand, $repr; $($rest)*)
+    };
+
+    (@spec $encoding:expr, $name:ident, $fields:tt, $operand:expr, $repr:ident; , scale $scale:literal $($rest:tt)*) => {
+        instruction_operand!(@spec $encoding, $name, $fields, $operand.scale($scale), $repr; $($rest)*)
+    };
+
+    (@spec $encoding:expr, $name:ident, $fields:tt, $operand:expr, $repr:ident; , list $access:ident $($rest:tt)*) => {
+        instruction_operand!(@spec $encoding, $name, $fields, $operand.kind($crate::decode::OperandKind::RegisterList(operand_access!($access))), $repr; $($rest)*)
+    };
+
+    (@spec $encoding:expr, $name:ident, $fields:tt, $operand:expr, $old:ident; , as $repr:ident $($rest:tt)*) => {
+        instruction_operand!(@spec $encoding, $name, $fields, $operand, $repr; $($rest)*)
+    };
+
+    (@spec $encoding:expr, $name:ident, $fields:tt, $operand:expr, $repr:ident; , $access:ident $($rest:tt)*) => {
+        instruction_operand!(@spec $encoding, $name, $fields, $operand.kind($crate::decode::OperandKind::Register(operand_access!($access))), $repr; $($rest)*)
+    };
+
+    (@spec $encoding:expr, $name:ident, [$($field:ident),*], $operand:expr, $repr:ident;) => {
+        $encoding.operand($operand
+            .repr(operand_repr!($repr))
+            .map(map_operand!($crate::instructions::InstrThumb16::$name, [$($field),*], $repr))
+            .build())
+    };
+}
+
 //#[allow(unused_macros)]
 //macro_rules! map_operand {
 //    ($instr:path, $op:ident, $repr:ident) => {
@@ -215,7 +414,7 @@ mod test {
                 .name("E1")
                 .desc("Performs a conditional branch")
                 .invariant(0xD000)
-                .ctor(Box::new(|| InstrThumb16::BranchE1{ cond: 0u8, imm: 0i8 }))
+                .ctor(Box::new(|| InstrThumb16::BranchE1{ cond: 0u8, imm: 0i16 }))
                 .operand(Operand::new()
                     .name("cond")
                     .width(4)
@@ -226,8 +425,9 @@ mod test {
                 .operand(Operand::new()
                     .name("imm")
                     .width(8)
-                    .repr(OperandRepr::SignedByte)
-                    .map(map_operand!(InstrThumb16::BranchE1, imm, i8))
+                    .scale(1)
+                    .repr(OperandRepr::SignedShort)
+                    .map(map_operand!(InstrThumb16::BranchE1, imm, i16))
                     .build())
                 .build())
             .encoding(Encoding::new()
@@ -238,6 +438,7 @@ mod test {
                 .operand(Operand::new()
                     .name("imm")
                     .width(11)
+                    .scale(1)
                     .repr(OperandRepr::SignedShort)
                     .map(map_operand!(InstrThumb16::BranchE2, imm, i16))
                     .build())
@@ -286,6 +487,129 @@ mod test {
             enc.generate_decode_table();
         }
     }
+
+    #[test]
+    fn instruction_macro() {
+        let descriptions = define_instructions! {
+            instruction! {
+                name: Test,
+                encoding: [
+                    base: 0xF000,
+                    operand: [a | b, 2 << 6],
+                    operand: [c, 1 << 3 : zeros 1, scale 1]
+                ],
+                encoding: [
+                    base: 0xE000,
+                    operand: [a, 4 << 0],
+                    operand: [b, unused = 7],
+                    operand: [c, unused]
+                ]
+            }
+        };
+
+        assert_eq!(descriptions.len(), 1);
+        let encodings = descriptions[0].encodings();
+        assert_eq!(encodings.iter().map(|e| e.name()).collect::<Vec<_>>(), vec!["E1", "E2"]);
+        assert_eq!(encodings[0].arity(), 2);
+        assert_eq!(encodings[1].arity(), 1);
+
+        let table = encodings[0].generate_decode_table();
+        assert_eq!(table.len(), 8);
+        assert!(table.iter().any(|p| p.encoded() == 0xF0C8 && p.decoded() == InstrThumb16::Test { a: 3, b: 3, c: 4 }));
+
+        let table = encodings[1].generate_decode_table();
+        assert!(table.iter().any(|p| p.encoded() == 0xE005 && p.decoded() == InstrThumb16::Test { a: 5, b: 7, c: 0 }));
+        assert_eq!(encodings[1].encode(&InstrThumb16::Test { a: 5, b: 7, c: 0 }), Some(0xE005));
+        assert_eq!(encodings[1].encode(&InstrThumb16::Test { a: 5, b: 0, c: 0 }), None);
+    }
+
+    #[test]
+    fn composite_operands() {
+        // cbz offset, i:imm5:'0'
+        let cbz = Operand::new().name("imm").slice(1, 9).slice(5, 3).zeros(1).build();
+        assert_eq!(cbz.mask(), 0x02F8);
+        assert_eq!(cbz.permutations(), 64);
+        assert_eq!(cbz.extract(0xB1F8), 62);
+        assert_eq!(cbz.extract(0xBB00), 64);
+        assert_eq!(cbz.encode(126), Some(0x02F8));
+        assert_eq!(cbz.encode(63), None);
+        assert_eq!(cbz.encode(128), None);
+
+        // push register list, M:'000000':register_list
+        let push = Operand::new().name("list").slice(1, 8).zeros(6).slice(8, 0).repr(OperandRepr::UnsignedShort).build();
+        assert_eq!(push.deposit(0x1F0), 0x01F0);
+        assert_eq!(push.extract(0xB5F0), 0x40F0);
+        assert_eq!(push.encode(0x40F0), Some(0x01F0));
+        assert_eq!(push.encode(0x2000), None);
+
+        // conditional branch offset, SignExtend(imm8:'0')
+        let branch = Operand::new().name("imm").width(8).scale(1).repr(OperandRepr::SignedShort).build();
+        assert_eq!(branch.extract(0xD0FE), -4);
+        assert_eq!(branch.extract(0xD07F), 254);
+        assert_eq!(branch.encode(-256), Some(0x0080));
+        assert_eq!(branch.encode(256), None);
+        assert_eq!(branch.encode(-3), None);
+    }
+
+    #[test]
+    fn validation_reports_defects() {
+        let broken = InstrDesc::new()
+            .name("Broken")
+            .encoding(Encoding::new()
+                .name("E1")
+                .invariant(0xF0C0)
+                .ctor(Box::new(|| InstrThumb16::Test{ a: 0u8, b: 0, c: 0u8} ))
+                .operand(Operand::new().name("a").width(4).shift(6).map(map_operand!(InstrThumb16::Test, a, u8)).build())
+                .operand(Operand::new().name("b").width(2).shift(8).map(map_operand!(InstrThumb16::Test, b, u8)).build())
+                .operand(Operand::new().name("c").width(4).shift(14).map(map_operand!(InstrThumb16::Test, c, u8)).build())
+                .build())
+            .build();
+
+        let errors = validate(&[broken]);
+        let expected = [
+            EncodingError::OperandOverlap { parent: "Broken".into(), encoding: "E1".into(), first: "a".into(), second: "b".into(), bits: 0x0300 },
+            EncodingError::InvariantOverlap { parent: "Broken".into(), encoding: "E1".into(), operand: "a".into(), bits: 0x00C0 },
+            EncodingError::OperandOverflow { parent: "Broken".into(), encoding: "E1".into(), operand: "c".into() },
+        ];
+
+        for error in expected.iter() {
+            assert!(errors.contains(error), "expected {} in {:?}", error, errors);
+        }
+
+        // Two encodings over the same halfwords, neither more specific than the other
+        let twice = InstrDesc::new()
+            .name("Twice")
+            .encoding(Encoding::new()
+                .name("E1")
+                .invariant(0x1000)
+                .ctor(Box::new(|| InstrThumb16::Test{ a: 0u8, b: 0, c: 0u8} ))
+                .operand(Operand::new().name("a").width(8).map(map_operand!(InstrThumb16::Test, a, u8)).build())
+                .build())
+            .encoding(Encoding::new()
+                .name("E2")
+                .invariant(0x1000)
+                .ctor(Box::new(|| InstrThumb16::Test{ a: 0u8, b: 0, c: 0u8} ))
+                .operand(Operand::new().name("a").width(4).shift(4).map(map_operand!(InstrThumb16::Test, a, u8)).build())
+                .operand(Operand::new().name("b").width(4).map(map_operand!(InstrThumb16::Test, b, u8)).build())
+                .build())
+            .encoding(Encoding::new()
+                .name("E3")
+                .invariant(0x10FF)
+                .ctor(Box::new(|| InstrThumb16::Test{ a: 0u8, b: 0, c: 0u8} ))
+                .build())
+            .build();
+
+        let errors = validate(&[twice]);
+        let conflicts: Vec<&EncodingError> = errors.iter().filter(|e| matches!(e, EncodingError::Conflict { .. })).collect();
+        assert_eq!(conflicts, vec![&EncodingError::Conflict {
+            first: ("Twice".into(), "E1".into()),
+            second: ("Twice".into(), "E2".into()),
+            example: 0x1000,
+        }]);
+
+        assert!(errors.contains(&EncodingError::Unclaimed { low: 0x0000, high: 0x0FFF }));
+        assert!(errors.contains(&EncodingError::Unclaimed { low: 0x1100, high: 0xFFFF }));
+    }
 }
 
 
@@ -294,9 +618,11 @@ mod test {
 pub struct InstrDesc {
     name: String,
     desc: String,
+    semantics: Semantics,
     encodings: Vec<Encoding>,
 }
 
+#[allow(clippy::new_ret_no_self)]
 impl InstrDesc {
     pub fn new() -> InstrDescBuilder {
         trace!("New instruction description");
@@ -306,10 +632,27 @@ impl InstrDesc {
             inner: InstrDesc {
                 name: Default::default(),
                 desc: Default::default(),
+                semantics: Default::default(),
                 encodings: Vec::new()
             }
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn encodings(&self) -> &[Encoding] {
+        &self.encodings
+    }
+
+    pub fn semantics(&self) -> &Semantics {
+        &self.semantics
+    }
+
+    pub fn into_encodings(self) -> Vec<Encoding> {
+        self.encodings
+    }
 }
 
 #[derive(Debug)]
@@ -328,7 +671,33 @@ impl InstrDescBuilder {
         self.inner.desc = String::from(desc);
         self
     }
-    
+
+    pub fn class(mut self, class: InstrClass) -> Self {
+        self.inner.semantics.class = class;
+        self
+    }
+
+    /// Sets the APSR flags written by the instruction, which for the 16 bit data processing instructions is only the
+    /// case outside of an IT block
+    pub fn flags(mut self, flags: Flags) -> Self {
+        self.inner.semantics.flags = flags;
+        self
+    }
+
+    /// Sets the nominal Cortex-M3 cycle count, optionally with one more cycle per register in a register list
+    pub fn cycles(mut self, base: u32, per_register: bool) -> Self {
+        self.inner.semantics.cycles = base;
+        self.inner.semantics.per_register = per_register;
+        self
+    }
+
+    /// Adds a register which every encoding accesses without naming it as an operand
+    pub fn implicit(mut self, register: u8, access: Access) -> Self {
+        self.inner.semantics.add_implicit(register, access);
+        self
+    }
+
+    /// Semantics must be given before the encodings they apply to
     pub fn encoding(mut self, encoding: Encoding) -> Self {
         trace!("Adding instruction encoding");
 
@@ -340,6 +709,17 @@ impl InstrDescBuilder {
             panic!("Instructions must be named before encodings can be added");
         }
 
+        // Unnamed encodings are numbered in the order they are added
+        if temp.name.is_empty() {
+            temp.name = format!("E{}", self.inner.encodings.len() + 1);
+        }
+
+        // Encodings share the semantics of their instruction, plus any registers implied by the encoding itself
+        let (reads, writes) = (temp.semantics.reads, temp.semantics.writes);
+        temp.semantics = self.inner.semantics;
+        temp.semantics.reads |= reads;
+        temp.semantics.writes |= writes;
+
         self.inner.encodings.push(temp);
         self
     }
@@ -358,10 +738,12 @@ pub struct Encoding {
     name: String,
     desc: String,
     invariant: usize,
+    semantics: Semantics,
     operands: Vec<Operand>,
     ctor: Option<VariantCtorFn>
 }
 
+#[allow(clippy::new_ret_no_self)]
 impl Encoding {
     pub fn new() -> EncodingBuilder {
         trace!("New encoding...");
@@ -372,24 +754,114 @@ impl Encoding {
                 name: Default::default(),
                 desc: Default::default(),
                 invariant: Default::default(),
+                semantics: Default::default(),
                 operands: Vec::new(),
                 ctor: None
             }
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent(&self) -> &str {
+        &self.parent
+    }
+
     pub fn arity(&self) -> usize {
         self.operands.len()
     }
 
-    pub fn generate_decode_table(&self) {
-        let rdb = RecursiveDecoderBuilder::new(&self);
-        let dct = rdb.build_decode_table();
+    pub fn semantics(&self) -> &Semantics {
+        &self.semantics
+    }
+
+    /// The bits of the halfword which are not claimed by any operand, and so must match the invariant exactly
+    pub fn invariant_mask(&self) -> u16 {
+        let operand_bits = self.operands.iter().fold(0, |acc, op| acc | op.mask());
+        !operand_bits
+    }
+
+    pub fn generate_decode_table(&self) -> DecodeTable {
+        let rdb = RecursiveDecoderBuilder::new(self);
+        rdb.build_decode_table()
+    }
+
+    /// Encodes an instruction with this encoding, the inverse of a decode table lookup
+    /// 
+    /// Each operand value is read back out of the instruction and placed into the invariant. The operands are then
+    /// mapped onto a freshly constructed variant in the same way the decode table is built; if that does not reproduce
+    /// the instruction exactly, because an operand is out of range or a value implied by this encoding differs, the
+    /// instruction cannot be expressed with this encoding and `None` is returned.
+    pub fn encode(&self, instr: &InstrThumb16) -> Option<u16> {
+        let ctor = self.ctor.as_ref().expect("no variant ctor");
+        let mut state = (*ctor)();
+
+        if std::mem::discriminant(&state) != std::mem::discriminant(instr) {
+            return None;
+        }
+
+        let mut encoded = self.invariant;
+        for operand in self.operands.iter() {
+            let read = operand.read.as_ref().expect("no operand read function");
+            let value = (*read)(instr)?;
+
+            let bits = operand.encode(value)?;
+
+            let field_map = operand.map.as_ref().expect("no operand map function");
+            (*field_map)(operand, &mut state, &value);
+            encoded |= bits as usize;
+        }
 
-        for item in dct {
-            println!("{:?}", item);
+        if state == *instr {
+            Some(encoded as u16)
+        } else {
+            None
         }
-        //let decode_table = self.gdt_recursive(&self.operands, None, 0);
+    }
+
+    /// Describes an instruction of this encoding, resolving the registers named by its operands
+    pub fn info(&self, instr: &InstrThumb16) -> Option<InstrInfo> {
+        let ctor = self.ctor.as_ref().expect("no variant ctor");
+        if std::mem::discriminant(&(*ctor)()) != std::mem::discriminant(instr) {
+            return None;
+        }
+
+        let (mut reads, mut writes) = (self.semantics.reads, self.semantics.writes);
+        for operand in self.operands.iter() {
+            let read = operand.read.as_ref().expect("no operand read function");
+            let value = (*read)(instr)? as u16;
+
+            let (registers, access) = match operand.kind {
+                OperandKind::Immediate => continue,
+                OperandKind::Register(access) => (1 << (value & 0xF), access),
+                OperandKind::RegisterList(access) => (value, access),
+            };
+
+            if access != Access::Write {
+                reads |= registers;
+            }
+
+            if access != Access::Read {
+                writes |= registers;
+            }
+        }
+
+        let mut cycles = self.semantics.cycles;
+        if self.semantics.per_register {
+            let list = self.operands.iter()
+                .filter(|op| matches!(op.kind, OperandKind::RegisterList(_)))
+                .filter_map(|op| (*op.read.as_ref()?)(instr))
+                .fold(0, |acc, list| acc | list as u16);
+            cycles += list.count_ones();
+        }
+
+        if writes & (1 << 15) != 0 {
+            cycles += PIPELINE_REFILL;
+        }
+
+        Some(InstrInfo { class: self.semantics.class, flags: self.semantics.flags, reads, writes, cycles })
     }
 }
 
@@ -405,44 +877,39 @@ impl InstructionCodecPair {
             encoded, decoded
         }
     }
+
+    pub fn encoded(&self) -> u16 {
+        self.encoded
+    }
+
+    pub fn decoded(&self) -> InstrThumb16 {
+        self.decoded
+    }
 }
 
 pub type DecodeTable = Vec<InstructionCodecPair>;
 
 struct RecursiveDecoderBuilder<'a> {
     encoding: &'a Encoding,
-
-    /// The resulting decode table
-    dectab: RefCell<DecodeTable>,
-
-    // this holds the current state of the instruction we are processing
-    //state: Rc<RefCell<InstrThumb16>>
 }
 
 impl<'a> RecursiveDecoderBuilder<'a> {
     pub fn new(encoding: &'a Encoding) -> RecursiveDecoderBuilder<'a> {
         RecursiveDecoderBuilder {
-            encoding: encoding,
-            dectab: RefCell::new(Vec::new()),
+            encoding
         }
     }
 
     pub fn build_decode_table(self) -> DecodeTable {
         let variant_constructor = self.encoding.ctor.as_ref().expect("no variant ctor");
-        let variant = (*variant_constructor)();
+        let mut state = (*variant_constructor)();
 
         let mut table = Vec::new();
-        let mut state = variant;
-        self.build_decode_table_recursive(0, &mut state, &mut table);
-
-        for (i, item) in table.iter().enumerate() {
-            //println!("{:04}: {:?}", i + 1, item);
-        }
-
-        self.dectab.into_inner()
+        self.build_decode_table_recursive(0, self.encoding.invariant, &mut state, &mut table);
+        table
     }
     
-    fn build_decode_table_recursive(&self, idx: usize, state: &mut InstrThumb16, output: &mut Vec<InstrThumb16>) {
+    fn build_decode_table_recursive(&self, idx: usize, encoded: usize, state: &mut InstrThumb16, output: &mut DecodeTable) {
         trace!("Building decode table from instruction operand set (recursion level: {})", idx);
         
         // Map all permutations of a given instruction into a list of decoded instructions
@@ -451,21 +918,20 @@ impl<'a> RecursiveDecoderBuilder<'a> {
         if let Some(operand) = self.encoding.operands.get(idx) {
             let field_map = operand.map.as_ref().unwrap();
 
-            // Calculate the permutation range of the operand
-            let (low, high) = operand.permutations();
-
-            // For each permutation
-            for p in RangeInclusive::new(low, high) {
+            // For each permutation of the bits the operand occupies
+            for p in 0..operand.permutations() {
+                let bits = operand.deposit(p);
 
-                // Apply the field mapping function to update the value of the state
-                (*field_map)(&operand, state, &p);
+                // Apply the field mapping function to update the value of the state with the architectural value
+                let value = operand.extract(bits);
+                (*field_map)(operand, state, &value);
                 
-                // try and go one level deeper
-                self.build_decode_table_recursive(idx.add(1), state, output);                
+                // try and go one level deeper, carrying the bits this operand contributes to the encoding
+                self.build_decode_table_recursive(idx.add(1), encoded | bits as usize, state, output);
             }
         } else {
             // Terminal, copy our current state into the decode table as a legal permutation
-            output.push(state.clone());
+            output.push(InstructionCodecPair::new(encoded as u16, *state));
         }
         
         // if we are out of the recursion stack and about to return
@@ -480,6 +946,139 @@ impl<'a> RecursiveDecoderBuilder<'a> {
     }
 }
 
+/// A defect found while validating a set of instruction descriptions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodingError {
+    /// An operand claims bits beyond the top of the halfword
+    OperandOverflow { parent: String, encoding: String, operand: String },
+
+    /// Two operands of the same encoding claim the same bits
+    OperandOverlap { parent: String, encoding: String, first: String, second: String, bits: u16 },
+
+    /// An operand claims bits which are also set in the invariant
+    InvariantOverlap { parent: String, encoding: String, operand: String, bits: u16 },
+
+    /// Two encodings claim a common halfword and neither is a strict specialization of the other
+    Conflict { first: (String, String), second: (String, String), example: u16 },
+
+    /// A contiguous range of halfwords which no encoding claims
+    Unclaimed { low: u16, high: u16 },
+}
+
+impl std::fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EncodingError::OperandOverflow { parent, encoding, operand } => {
+                write!(f, "{} {}: operand {:?} extends beyond bit 15", parent, encoding, operand)
+            },
+            EncodingError::OperandOverlap { parent, encoding, first, second, bits } => {
+                write!(f, "{} {}: operands {:?} and {:?} overlap at {:#06X}", parent, encoding, first, second, bits)
+            },
+            EncodingError::InvariantOverlap { parent, encoding, operand, bits } => {
+                write!(f, "{} {}: operand {:?} overlaps the invariant at {:#06X}", parent, encoding, operand, bits)
+            },
+            EncodingError::Conflict { first, second, example } => {
+                write!(f, "{} {} conflicts with {} {}, both claim {:#06X}", first.0, first.1, second.0, second.1, example)
+            },
+            EncodingError::Unclaimed { low, high } => {
+                write!(f, "unclaimed halfwords {:#06X}..={:#06X}", low, high)
+            },
+        }
+    }
+}
+
+/// Checks a set of instruction descriptions for malformed encodings, ambiguous decodes, and gaps in the 16 bit space
+/// 
+/// Two encodings may claim the same halfword only when one is a strict specialization of the other, that is when its
+/// invariant bits are a superset of the other's and agree with them. The decode table resolves those cases in favour
+/// of the more specific encoding; anything else would depend on definition order and is reported as a conflict.
+pub fn validate(descriptions: &[InstrDesc]) -> Vec<EncodingError> {
+    let mut errors = Vec::new();
+    let encodings: Vec<&Encoding> = descriptions.iter().flat_map(|d| d.encodings.iter()).collect();
+
+    for enc in encodings.iter() {
+        for (i, op) in enc.operands.iter().enumerate() {
+            if op.slices.iter().any(|s| match *s { BitSlice::Field { width, shift } => width + shift > 16, _ => false }) {
+                errors.push(EncodingError::OperandOverflow {
+                    parent: enc.parent.clone(), encoding: enc.name.clone(), operand: op.name.clone()
+                });
+            }
+
+            let bits = op.mask() & (enc.invariant as u16);
+            if bits != 0 {
+                errors.push(EncodingError::InvariantOverlap {
+                    parent: enc.parent.clone(), encoding: enc.name.clone(), operand: op.name.clone(), bits
+                });
+            }
+
+            for (j, slice) in op.slices.iter().enumerate() {
+                let bits = op.slices.iter().skip(j + 1).fold(0, |acc, s| acc | s.mask()) & slice.mask();
+                if bits != 0 {
+                    errors.push(EncodingError::OperandOverlap {
+                        parent: enc.parent.clone(), encoding: enc.name.clone(), first: op.name.clone(), second: op.name.clone(), bits
+                    });
+                }
+            }
+
+            for other in enc.operands.iter().skip(i + 1) {
+                let bits = op.mask() & other.mask();
+                if bits != 0 {
+                    errors.push(EncodingError::OperandOverlap {
+                        parent: enc.parent.clone(), encoding: enc.name.clone(), first: op.name.clone(), second: other.name.clone(), bits
+                    });
+                }
+            }
+        }
+    }
+
+    for (i, a) in encodings.iter().enumerate() {
+        let (a_mask, a_value) = (a.invariant_mask(), a.invariant as u16 & a.invariant_mask());
+
+        for b in encodings.iter().skip(i + 1) {
+            let (b_mask, b_value) = (b.invariant_mask(), b.invariant as u16 & b.invariant_mask());
+
+            // Encodings are disjoint if they disagree on any bit which both hold invariant
+            if (a_value ^ b_value) & a_mask & b_mask != 0 {
+                continue;
+            }
+
+            let a_specializes = a_mask != b_mask && a_mask & b_mask == b_mask;
+            let b_specializes = a_mask != b_mask && a_mask & b_mask == a_mask;
+            if !a_specializes && !b_specializes {
+                errors.push(EncodingError::Conflict {
+                    first: (a.parent.clone(), a.name.clone()),
+                    second: (b.parent.clone(), b.name.clone()),
+                    example: a_value | b_value,
+                });
+            }
+        }
+    }
+
+    let mut claimed = vec![false; (u16::MAX as usize) + 1];
+    for enc in encodings.iter() {
+        let (mask, value) = (enc.invariant_mask(), enc.invariant as u16 & enc.invariant_mask());
+        for (halfword, claim) in claimed.iter_mut().enumerate() {
+            if (halfword as u16) & mask == value {
+                *claim = true;
+            }
+        }
+    }
+
+    let mut start = None;
+    for (halfword, claim) in claimed.iter().chain(std::iter::once(&true)).enumerate() {
+        match (start, claim) {
+            (None, false) => start = Some(halfword),
+            (Some(low), true) => {
+                errors.push(EncodingError::Unclaimed { low: low as u16, high: (halfword - 1) as u16 });
+                start = None;
+            },
+            _ => (),
+        }
+    }
+
+    errors
+}
+
 #[derive(Debug)]
 pub struct EncodingBuilder {
     inner: Encoding
@@ -501,7 +1100,7 @@ impl EncodingBuilder {
     }
 
     pub fn operand(mut self, op: Operand) -> Self {
-        trace!("Adding encoding operand {:?}, width {:?}, shifted {:?} bits to the left", op.name, op.width, op.shift);
+        trace!("Adding encoding operand {:?}, occupying {:#06X}", op.name, op.mask());
 
         self.inner.operands.push(op);
         self
@@ -514,6 +1113,14 @@ impl EncodingBuilder {
         self
     }
     
+    /// Adds a register which this encoding accesses without naming it as an operand
+    pub fn implicit(mut self, register: u8, access: Access) -> Self {
+        trace!("Adding implicit register r{} to encoding, {:?}", register, access);
+
+        self.inner.semantics.add_implicit(register, access);
+        self
+    }
+
     pub fn ctor(mut self, ctor_func: VariantCtorFn) -> Self {
         trace!("Setting encoding variant constructor method");
 
@@ -528,53 +1135,184 @@ impl EncodingBuilder {
     }
 }
 
+/// A run of bits contributing to an operand value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitSlice {
+    /// `width` bits of the halfword, shifted `shift` bits from the right
+    Field { width: usize, shift: usize },
+
+    /// Constant zero bits which are not present in the halfword, written as '0' in the reference manual pseudocode
+    Zeros(usize),
+}
+
+impl BitSlice {
+    pub fn width(&self) -> usize {
+        match *self {
+            BitSlice::Field { width, .. } => width,
+            BitSlice::Zeros(count) => count,
+        }
+    }
+
+    /// The bits of the halfword occupied by this slice
+    pub fn mask(&self) -> u16 {
+        match *self {
+            BitSlice::Field { width, shift } => (((1usize << width) - 1) << shift) as u16,
+            BitSlice::Zeros(_) => 0,
+        }
+    }
+}
+
+/// An operand of an encoding
+/// 
+/// Most operands are a single contiguous field of the halfword, but some are the concatenation of several slices, for
+/// example the branch offset of CBZ which is `i:imm5:'0'`. The slices are concatenated most significant first, the
+/// result is sign extended if the operand has a signed representation and zero extended otherwise, and it is finally
+/// scaled, so that the value mapped into the instruction variant is the architectural value rather than the raw bits.
 #[derive(Debug)]
 pub struct Operand {
     name: String,
-    width: usize,
-    shift: usize,
-    typeid: TypeId,
+    slices: Vec<BitSlice>,
+    scale: usize,
     repr: OperandRepr,
-    map: Option<OperatorMapFn>
+    kind: OperandKind,
+    map: Option<OperatorMapFn>,
+    read: Option<OperatorReadFn>
 }
 
+#[allow(clippy::new_ret_no_self)]
 impl Operand {
     pub fn new() -> OperandBuilder {
         OperandBuilder {
             inner: Operand {
                 name: Default::default(),
-                width: Default::default(),
-                shift: Default::default(),
+                slices: Vec::new(),
+                scale: 0,
                 repr: OperandRepr::UnsignedByte,
-                typeid: std::any::TypeId::of::<Self>(),
-                map: None
-            }
+                kind: OperandKind::Immediate,
+                map: None,
+                read: None
+            },
+            width: None,
+            shift: 0,
         }
     }
-    
-    pub fn permutations(&self) -> (i64, i64) {
-        let range = std::cmp::max(1u64, 2u64.pow(self.width as u32));
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn slices(&self) -> &[BitSlice] {
+        &self.slices
+    }
+
+    pub fn kind(&self) -> OperandKind {
+        self.kind
+    }
+
+    /// The number of halfword bits which make up the operand
+    pub fn width(&self) -> usize {
+        self.slices.iter().filter(|s| s.mask() != 0).map(|s| s.width()).sum()
+    }
+
+    /// The width of the concatenated operand before it is extended and scaled
+    fn concatenated_width(&self) -> usize {
+        self.slices.iter().map(|s| s.width()).sum()
+    }
+
+    fn is_signed(&self) -> bool {
         match self.repr {
-            OperandRepr::SignedByte |
-            OperandRepr::SignedShort |
-            OperandRepr::SignedWord => {
-                let low = ((range / 2) as i64) * -1;
-                let high = ((range / 2) - 1) as i64;
-                (low, high)
-            },
+            OperandRepr::SignedByte | OperandRepr::SignedShort | OperandRepr::SignedWord => true,
+            OperandRepr::UnsignedByte | OperandRepr::UnsignedShort | OperandRepr::UnsignedWord => false,
+        }
+    }
 
-            OperandRepr::UnsignedByte |
-            OperandRepr::UnsignedShort |
-            OperandRepr::UnsignedWord => {
-                (0i64, (range - 1) as i64)
+    /// The number of distinct values the operand can take
+    pub fn permutations(&self) -> usize {
+        1usize << self.width()
+    }
+
+    /// The bits of the halfword occupied by this operand
+    pub fn mask(&self) -> u16 {
+        self.slices.iter().fold(0, |acc, s| acc | s.mask())
+    }
+
+    /// Scatters the `n`th permutation of the operand into the bits of the halfword it occupies
+    /// 
+    /// The permutation is taken as the concatenation of the slices, most significant first, and any constant slices
+    /// are skipped over.
+    pub fn deposit(&self, mut n: usize) -> u16 {
+        let mut bits = 0;
+        for slice in self.slices.iter().rev() {
+            if let BitSlice::Field { width, shift } = *slice {
+                bits |= (n & ((1 << width) - 1)) << shift;
+                n >>= width;
+            }
+        }
+        bits as u16
+    }
+
+    /// Extracts the architectural value of this operand from an encoded halfword
+    pub fn extract(&self, halfword: u16) -> i64 {
+        let mut raw = 0u64;
+        for slice in self.slices.iter() {
+            raw <<= slice.width();
+            if let BitSlice::Field { width, shift } = *slice {
+                raw |= ((halfword as u64) >> shift) & ((1 << width) - 1);
             }
         }
+
+        let width = self.concatenated_width() as u32;
+        let value = if self.is_signed() && width > 0 {
+            ((raw << (64 - width)) as i64) >> (64 - width)
+        } else {
+            raw as i64
+        };
+
+        value << self.scale
+    }
+
+    /// Places an architectural value into the bits of the halfword occupied by this operand, the inverse of `extract`
+    /// 
+    /// Returns `None` if the value cannot be represented, because it is out of range, is not a multiple of the scale,
+    /// or has bits set where the operand holds constant zeros.
+    pub fn encode(&self, value: i64) -> Option<u16> {
+        if value & ((1 << self.scale) - 1) != 0 {
+            return None;
+        }
+
+        let raw = value >> self.scale;
+        let width = self.concatenated_width() as u32;
+        let (low, high) = if self.is_signed() {
+            (-(1i64 << width) / 2, (1i64 << width) / 2 - 1)
+        } else {
+            (0, (1i64 << width) - 1)
+        };
+
+        if raw < low || raw > high {
+            return None;
+        }
+
+        let mut raw = raw as u64;
+        let mut bits = 0;
+        for slice in self.slices.iter().rev() {
+            let part = raw & ((1 << slice.width()) - 1);
+            match *slice {
+                BitSlice::Field { shift, .. } => bits |= part << shift,
+                BitSlice::Zeros(_) if part != 0 => return None,
+                BitSlice::Zeros(_) => (),
+            }
+            raw >>= slice.width();
+        }
+
+        Some(bits as u16)
     }
 }
 
 #[derive(Debug)]
 pub struct OperandBuilder {
-    inner: Operand
+    inner: Operand,
+    width: Option<usize>,
+    shift: usize,
 }
 
 impl OperandBuilder {
@@ -585,17 +1323,43 @@ impl OperandBuilder {
         self
     }
 
+    /// Sets the width of an operand which is a single contiguous field
     pub fn width(mut self, width: usize) -> Self {
         trace!("Setting operand width {:?}", width);
 
-        self.inner.width = width;
+        self.width = Some(width);
         self
     }
 
+    /// Sets the position of an operand which is a single contiguous field
     pub fn shift(mut self, shift: usize) -> Self {
         trace!("Setting operand shift {:?}", shift);
 
-        self.inner.shift = shift;
+        self.shift = shift;
+        self
+    }
+
+    /// Appends a slice of the halfword to a composite operand, slices are given most significant first
+    pub fn slice(mut self, width: usize, shift: usize) -> Self {
+        trace!("Appending operand slice, width {:?}, shifted {:?} bits to the left", width, shift);
+
+        self.inner.slices.push(BitSlice::Field { width, shift });
+        self
+    }
+
+    /// Appends constant zero bits to a composite operand
+    pub fn zeros(mut self, count: usize) -> Self {
+        trace!("Appending {:?} constant zero bit(s) to operand", count);
+
+        self.inner.slices.push(BitSlice::Zeros(count));
+        self
+    }
+
+    /// Scales the extended operand value, shifting it `scale` bits to the left
+    pub fn scale(mut self, scale: usize) -> Self {
+        trace!("Setting operand scale {:?}", scale);
+
+        self.inner.scale = scale;
         self
     }
 
@@ -606,16 +1370,29 @@ impl OperandBuilder {
         self
     }
 
-    pub fn map(mut self, map_func: OperatorMapFn) -> Self {
-        trace!("Setting operand to decoded instruction mapping method");
+    pub fn kind(mut self, kind: OperandKind) -> Self {
+        trace!("Setting operand kind {:?}", kind);
+
+        self.inner.kind = kind;
+        self
+    }
+
+    pub fn map(mut self, (map_func, read_func): (OperatorMapFn, OperatorReadFn)) -> Self {
+        trace!("Setting operand to decoded instruction mapping methods");
 
         self.inner.map = Some(map_func);
+        self.inner.read = Some(read_func);
         self
     }
 
-    pub fn build(self) -> Operand {
+    pub fn build(mut self) -> Operand {
         trace!("Building operand {:?}", self.inner.name);
 
+        if let Some(width) = self.width {
+            assert!(self.inner.slices.is_empty(), "operand {:?} is given both a width and a list of slices", self.inner.name);
+            self.inner.slices.push(BitSlice::Field { width, shift: self.shift });
+        }
+
         self.inner
     }
 }
@@ -630,6 +1407,200 @@ pub enum OperandRepr {
     UnsignedWord,
 }
 
+/// What an operand value means to the instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    Immediate,
+
+    /// The number of a register
+    Register(Access),
+
+    /// A register list, with bit `n` set for register `n`
+    RegisterList(Access),
+}
+
+/// How an instruction accesses a register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// The broad class of an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstrClass {
+    DataProcessing,
+    Branch,
+    Load,
+    Store,
+    Miscellaneous,
+}
+
+/// A set of APSR condition flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Flags(u8);
+
+impl Flags {
+    pub const NONE: Flags = Flags(0);
+    pub const N: Flags = Flags(1 << 3);
+    pub const Z: Flags = Flags(1 << 2);
+    pub const C: Flags = Flags(1 << 1);
+    pub const V: Flags = Flags(1 << 0);
+
+    /// Parses a set of flags from their letters, such as `"NZC"`
+    pub fn parse(letters: &str) -> Flags {
+        letters.chars().fold(Flags::NONE, |acc, letter| acc | match letter {
+            'N' => Flags::N,
+            'Z' => Flags::Z,
+            'C' => Flags::C,
+            'V' => Flags::V,
+            _ => panic!("invalid flag {:?} in {:?}", letter, letters),
+        })
+    }
+
+    pub fn contains(self, other: Flags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl std::ops::BitOr for Flags {
+    type Output = Flags;
+
+    fn bitor(self, other: Flags) -> Flags {
+        Flags(self.0 | other.0)
+    }
+}
+
+impl std::fmt::Display for Flags {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "-");
+        }
+
+        for (flag, letter) in [(Flags::N, 'N'), (Flags::Z, 'Z'), (Flags::C, 'C'), (Flags::V, 'V')].iter() {
+            if self.contains(*flag) {
+                write!(f, "{}", letter)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The cycles taken to refill the pipeline after a write to the PC
+/// 
+/// On a Cortex-M3 this is between 1 and 3 cycles depending on the alignment and width of the target instruction, the
+/// nominal cycle counts use the lower bound.
+pub const PIPELINE_REFILL: u32 = 1;
+
+/// The semantics shared by the encodings of an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Semantics {
+    class: InstrClass,
+    flags: Flags,
+    cycles: u32,
+    per_register: bool,
+    reads: u16,
+    writes: u16,
+}
+
+impl Default for Semantics {
+    fn default() -> Self {
+        Semantics {
+            class: InstrClass::DataProcessing,
+            flags: Flags::NONE,
+            cycles: 1,
+            per_register: false,
+            reads: 0,
+            writes: 0,
+        }
+    }
+}
+
+impl Semantics {
+    fn add_implicit(&mut self, register: u8, access: Access) {
+        if access != Access::Write {
+            self.reads |= 1 << register;
+        }
+
+        if access != Access::Read {
+            self.writes |= 1 << register;
+        }
+    }
+
+    pub fn class(&self) -> InstrClass {
+        self.class
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+}
+
+/// Semantic information about a decoded instruction
+/// 
+/// Register sets have bit `n` set for register `n`, in the same way as a register list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstrInfo {
+    class: InstrClass,
+    flags: Flags,
+    reads: u16,
+    writes: u16,
+    cycles: u32,
+}
+
+impl InstrInfo {
+    pub fn class(&self) -> InstrClass {
+        self.class
+    }
+
+    /// The APSR flags the instruction writes
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    /// The registers the instruction reads
+    pub fn reads(&self) -> u16 {
+        self.reads
+    }
+
+    /// The registers the instruction writes
+    pub fn writes(&self) -> u16 {
+        self.writes
+    }
+
+    pub fn reads_register(&self, register: u8) -> bool {
+        self.reads & (1 << register) != 0
+    }
+
+    pub fn writes_register(&self, register: u8) -> bool {
+        self.writes & (1 << register) != 0
+    }
+
+    /// Whether the instruction may change the flow of execution, either as a branch or by writing the PC
+    pub fn is_branch(&self) -> bool {
+        self.class == InstrClass::Branch || self.writes_register(15)
+    }
+
+    pub fn is_load(&self) -> bool {
+        self.class == InstrClass::Load
+    }
+
+    pub fn is_store(&self) -> bool {
+        self.class == InstrClass::Store
+    }
+
+    /// The nominal Cortex-M3 cycle count, assuming a taken branch and a single cycle pipeline refill
+    pub fn cycles(&self) -> u32 {
+        self.cycles
+    }
+}
+
 pub trait OperandMap: Fn(&Operand, &mut InstrThumb16, &dyn Any) { }
 
 impl<F: Clone> OperandMap for F where F: Fn(&Operand, &mut InstrThumb16, &dyn Any) { }
@@ -642,6 +1613,18 @@ impl std::fmt::Debug for OperatorMapFn {
     }
 }
 
+pub trait OperandRead: Fn(&InstrThumb16) -> Option<i64> { }
+
+impl<F> OperandRead for F where F: Fn(&InstrThumb16) -> Option<i64> { }
+
+pub type OperatorReadFn = Box<dyn OperandRead>;
+
+impl std::fmt::Debug for OperatorReadFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[Operand Read Function]")
+    }
+}
+
 pub trait VariantCtor: Fn() -> InstrThumb16 { }
 impl<F> VariantCtor for F where F: Fn() -> InstrThumb16 { }
 pub type VariantCtorFn = Box<dyn VariantCtor>;
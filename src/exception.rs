@@ -0,0 +1,168 @@
+// ARMv7-M Exception Model
+
+use std::fmt;
+
+/// The number of exceptions supported, the 16 system exceptions followed by up to 240 external interrupts
+pub const NUM_EXCEPTIONS: usize = 16 + 240;
+
+/// EXC_RETURN values, loaded into the PC from Handler mode to return from an exception
+pub const EXC_RETURN_HANDLER: u32 = 0xFFFF_FFF1;
+pub const EXC_RETURN_THREAD_MSP: u32 = 0xFFFF_FFF9;
+pub const EXC_RETURN_THREAD_PSP: u32 = 0xFFFF_FFFD;
+
+/// Exceptions, identified by their exception number
+///
+/// | Number | Exception    | Number | Exception      |
+/// | 1      | Reset        | 11     | SVCall         |
+/// | 2      | NMI          | 12     | DebugMonitor   |
+/// | 3      | HardFault    | 14     | PendSV         |
+/// | 4      | MemManage    | 15     | SysTick        |
+/// | 5      | BusFault     | 16+n   | External IRQ n |
+/// | 6      | UsageFault   |        |                |
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exception {
+    Reset,
+    Nmi,
+    HardFault,
+    MemManage,
+    BusFault,
+    UsageFault,
+    SvCall,
+    DebugMonitor,
+    PendSv,
+    SysTick,
+    Interrupt(u16),
+}
+
+impl Exception {
+    /// The exception number, which is also its index in the vector table and its value in the IPSR
+    pub fn number(self) -> u16 {
+        match self {
+            Exception::Reset => 1,
+            Exception::Nmi => 2,
+            Exception::HardFault => 3,
+            Exception::MemManage => 4,
+            Exception::BusFault => 5,
+            Exception::UsageFault => 6,
+            Exception::SvCall => 11,
+            Exception::DebugMonitor => 12,
+            Exception::PendSv => 14,
+            Exception::SysTick => 15,
+            Exception::Interrupt(n) => 16 + n,
+        }
+    }
+
+    /// The exception with the given number, reserved numbers have none
+    pub fn from_number(number: u16) -> Option<Exception> {
+        let exception = match number {
+            1 => Exception::Reset,
+            2 => Exception::Nmi,
+            3 => Exception::HardFault,
+            4 => Exception::MemManage,
+            5 => Exception::BusFault,
+            6 => Exception::UsageFault,
+            11 => Exception::SvCall,
+            12 => Exception::DebugMonitor,
+            14 => Exception::PendSv,
+            15 => Exception::SysTick,
+            n if n >= 16 && (n as usize) < NUM_EXCEPTIONS => Exception::Interrupt(n - 16),
+            _ => return None,
+        };
+
+        Some(exception)
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Exception::Reset => write!(f, "Reset"),
+            Exception::Nmi => write!(f, "NMI"),
+            Exception::HardFault => write!(f, "HardFault"),
+            Exception::MemManage => write!(f, "MemManage"),
+            Exception::BusFault => write!(f, "BusFault"),
+            Exception::UsageFault => write!(f, "UsageFault"),
+            Exception::SvCall => write!(f, "SVCall"),
+            Exception::DebugMonitor => write!(f, "DebugMonitor"),
+            Exception::PendSv => write!(f, "PendSV"),
+            Exception::SysTick => write!(f, "SysTick"),
+            Exception::Interrupt(n) => write!(f, "IRQ{}", n),
+        }
+    }
+}
+
+/// A fault raised by the processor, identified by the fault status bit it sets
+///
+/// Each fault belongs to one of the fault exceptions, and the configurable faults escalate to HardFault when their
+/// exception is disabled or cannot preempt the current execution priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// MemManage, an instruction was fetched from an Execute Never region of the system address map
+    IAccViol,
+
+    /// BusFault, an instruction was fetched from an address with no memory
+    IBusErr,
+
+    /// BusFault, a data access was made to an address with no memory
+    PreciseErr { address: u32 },
+
+    /// BusFault, popping the frame on an exception return failed
+    UnstkErr,
+
+    /// BusFault, pushing the frame on exception entry failed
+    StkErr,
+
+    /// UsageFault, an undefined instruction was executed
+    UndefInstr,
+
+    /// UsageFault, an instruction was executed with EPSR.T clear, as ARMv7-M has no ARM state
+    InvState,
+
+    /// UsageFault, an exception return was attempted with an invalid EXC_RETURN value or exception state
+    InvPc,
+
+    /// UsageFault, a coprocessor instruction was executed and no coprocessor is implemented
+    NoCp,
+
+    /// UsageFault, an unaligned access was made by an instruction which requires alignment, or with CCR.UNALIGN_TRP set
+    Unaligned,
+
+    /// UsageFault, an integer division by zero was executed with CCR.DIV_0_TRP set
+    DivByZero,
+
+    /// HardFault, the vector of an exception could not be read
+    VectTbl,
+}
+
+impl Fault {
+    /// The exception the fault is reported through before any escalation
+    pub fn exception(self) -> Exception {
+        match self {
+            Fault::IAccViol => Exception::MemManage,
+            Fault::IBusErr | Fault::PreciseErr { .. } | Fault::UnstkErr | Fault::StkErr => Exception::BusFault,
+            Fault::VectTbl => Exception::HardFault,
+            _ => Exception::UsageFault,
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            Fault::IAccViol => "IACCVIOL",
+            Fault::IBusErr => "IBUSERR",
+            Fault::PreciseErr { address } => return write!(f, "BusFault (PRECISERR at {:#010X})", address),
+            Fault::UnstkErr => "UNSTKERR",
+            Fault::StkErr => "STKERR",
+            Fault::UndefInstr => "UNDEFINSTR",
+            Fault::InvState => "INVSTATE",
+            Fault::InvPc => "INVPC",
+            Fault::NoCp => "NOCP",
+            Fault::Unaligned => "UNALIGNED",
+            Fault::DivByZero => "DIVBYZERO",
+            Fault::VectTbl => "VECTTBL",
+        };
+
+        write!(f, "{} ({})", self.exception(), status)
+    }
+}
@@ -0,0 +1,192 @@
+//! Condition flags and condition code evaluation
+//!
+//! The condition codes are those of the ARMv7-M Architecture Reference Manual, table A7-1:
+//!
+//!   cond    Mnemonic  Meaning                       Condition flags
+//!   0000    EQ        Equal                         Z == 1
+//!   0001    NE        Not equal                     Z == 0
+//!   0010    CS        Carry set                     C == 1
+//!   0011    CC        Carry clear                   C == 0
+//!   0100    MI        Minus, negative               N == 1
+//!   0101    PL        Plus, positive or zero        N == 0
+//!   0110    VS        Overflow                      V == 1
+//!   0111    VC        No overflow                   V == 0
+//!   1000    HI        Unsigned higher               C == 1 and Z == 0
+//!   1001    LS        Unsigned lower or same        C == 0 or Z == 1
+//!   1010    GE        Signed greater or equal       N == V
+//!   1011    LT        Signed less than              N != V
+//!   1100    GT        Signed greater than           Z == 0 and N == V
+//!   1101    LE        Signed less than or equal     Z == 1 or N != V
+//!   1110    None (AL) Always                        Any
+//!
+//! Instructions within an IT block take their condition from ITSTATE, which the IT instruction sets and which
+//! advances after each instruction of the block. ITSTATE is held in the EPSR, and is saved and restored along with the
+//! rest of the xPSR on exception entry and return.
+
+use crate::instructions::ItPosition;
+
+/// The condition flags held in bits [31:28] of the APSR
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConditionFlags {
+    pub n: bool,
+    pub z: bool,
+    pub c: bool,
+    pub v: bool,
+}
+
+impl ConditionFlags {
+    const N_BIT: u32 = 1 << 31;
+    const Z_BIT: u32 = 1 << 30;
+    const C_BIT: u32 = 1 << 29;
+    const V_BIT: u32 = 1 << 28;
+
+    /// Reads the condition flags from an APSR value
+    pub fn from_apsr(apsr: u32) -> ConditionFlags {
+        ConditionFlags {
+            n: apsr & Self::N_BIT != 0,
+            z: apsr & Self::Z_BIT != 0,
+            c: apsr & Self::C_BIT != 0,
+            v: apsr & Self::V_BIT != 0,
+        }
+    }
+
+    /// Replaces the condition flags of an APSR value, leaving its other bits untouched
+    pub fn into_apsr(self, apsr: u32) -> u32 {
+        let mut bits = apsr & !(Self::N_BIT | Self::Z_BIT | Self::C_BIT | Self::V_BIT);
+
+        if self.n { bits |= Self::N_BIT }
+        if self.z { bits |= Self::Z_BIT }
+        if self.c { bits |= Self::C_BIT }
+        if self.v { bits |= Self::V_BIT }
+
+        bits
+    }
+
+    /// ConditionPassed() from the ARMv7-M Architecture Reference Manual, for the 4 bit condition `cond`
+    ///
+    /// Bits [3:1] select the condition and bit 0 inverts it, except for 0b1111 which, like AL, always passes.
+    pub fn condition_passed(self, cond: u8) -> bool {
+        let result = match (cond >> 1) & 0b111 {
+            0b000 => self.z,
+            0b001 => self.c,
+            0b010 => self.n,
+            0b011 => self.v,
+            0b100 => self.c && !self.z,
+            0b101 => self.n == self.v,
+            0b110 => self.n == self.v && !self.z,
+            _ => true,
+        };
+
+        if cond & 1 == 1 && cond != 0b1111 {
+            !result
+        } else {
+            result
+        }
+    }
+}
+
+/// The IT execution state, ITSTATE, from the ARMv7-M Architecture Reference Manual
+///
+/// Bits [7:5] hold the base condition of the block and bits [4:0] hold the condition of the current instruction in
+/// its lowest bit, followed by the remaining mask. The block ends when bits [3:0] become zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ItState(u8);
+
+impl ItState {
+    /// The state set by an IT instruction with the given first condition and mask
+    pub fn new(firstcond: u8, mask: u8) -> ItState {
+        ItState((firstcond & 0b1111) << 4 | mask & 0b1111)
+    }
+
+    /// Reads ITSTATE from an EPSR value, where IT[1:0] is held in bits [26:25] and IT[7:2] in bits [15:10]
+    pub fn from_epsr(epsr: u32) -> ItState {
+        ItState(((epsr >> 25) & 0b11 | ((epsr >> 10) & 0b11_1111) << 2) as u8)
+    }
+
+    /// Replaces ITSTATE in an EPSR value, leaving its other bits untouched
+    pub fn into_epsr(self, epsr: u32) -> u32 {
+        let it = self.0 as u32;
+        epsr & !(0b11 << 25 | 0b11_1111 << 10) | (it & 0b11) << 25 | (it >> 2) << 10
+    }
+
+    /// The position of the current instruction relative to the IT block
+    pub fn position(self) -> ItPosition {
+        match self.0 & 0b1111 {
+            0b0000 => ItPosition::Outside,
+            0b1000 => ItPosition::Last,
+            _ => ItPosition::Inside,
+        }
+    }
+
+    /// The condition of the current instruction, or None outside of an IT block
+    pub fn condition(self) -> Option<u8> {
+        if self.position().in_it_block() {
+            Some(self.0 >> 4)
+        } else {
+            None
+        }
+    }
+
+    /// ITAdvance() from the ARMv7-M Architecture Reference Manual
+    pub fn advance(self) -> ItState {
+        if self.0 & 0b111 == 0 {
+            ItState(0)
+        } else {
+            ItState(self.0 & 0b1110_0000 | (self.0 << 1) & 0b1_1111)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn condition_passed() {
+        let flags = |n, z, c, v| ConditionFlags { n, z, c, v };
+        let passed = |f: ConditionFlags| (0..16u8).filter(|&cond| f.condition_passed(cond)).collect::<Vec<_>>();
+
+        assert_eq!(passed(flags(false, false, false, false)), [1, 3, 5, 7, 9, 10, 12, 14, 15]);
+        assert_eq!(passed(flags(false, true, false, false)), [0, 3, 5, 7, 9, 10, 13, 14, 15]);
+        assert_eq!(passed(flags(false, false, true, false)), [1, 2, 5, 7, 8, 10, 12, 14, 15]);
+        assert_eq!(passed(flags(false, true, true, false)), [0, 2, 5, 7, 9, 10, 13, 14, 15]);
+        assert_eq!(passed(flags(true, false, false, false)), [1, 3, 4, 7, 9, 11, 13, 14, 15]);
+        assert_eq!(passed(flags(false, false, false, true)), [1, 3, 5, 6, 9, 11, 13, 14, 15]);
+        assert_eq!(passed(flags(true, false, false, true)), [1, 3, 4, 6, 9, 10, 12, 14, 15]);
+    }
+
+    #[test]
+    fn it_state_advance() {
+        // itete ne: ne, eq, ne, eq
+        let mut state = ItState::new(0b0001, 0b0101);
+        let mut conditions = Vec::new();
+
+        while let Some(cond) = state.condition() {
+            conditions.push((cond, state.position()));
+            state = state.advance();
+        }
+
+        assert_eq!(conditions, [
+            (0b0001, ItPosition::Inside),
+            (0b0000, ItPosition::Inside),
+            (0b0001, ItPosition::Inside),
+            (0b0000, ItPosition::Last),
+        ]);
+        assert_eq!(state, ItState::default());
+
+        // it gt
+        let state = ItState::new(0b1100, 0b1000);
+        assert_eq!(state.position(), ItPosition::Last);
+        assert_eq!(state.advance().position(), ItPosition::Outside);
+    }
+
+    #[test]
+    fn it_state_epsr() {
+        let state = ItState::new(0b1010, 0b0110);
+        let epsr = state.into_epsr(1 << 24);
+
+        assert_eq!(epsr, 1 << 24 | 0b10 << 25 | 0b101001 << 10);
+        assert_eq!(ItState::from_epsr(epsr), state);
+        assert_eq!(ItState::default().into_epsr(epsr), 1 << 24);
+    }
+}
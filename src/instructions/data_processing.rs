@@ -27,8 +27,297 @@
 //! |0  1  0  0  0  0 |opcode     |                 |
 //! =================================================
 
-use crate::instructions::InstrThumb16;
+//! The shift and immediate instructions which share the same flag semantics are executed here as well:
+//!
+//!   LSL/LSR/ASR (immediate), ADD/SUB (register), ADD/SUB (3 bit immediate), MOV/CMP/ADD/SUB (8 bit immediate)
+//!
+//! Execution follows the pseudocode of the Architecture Reference Manual. Instructions which write a destination
+//! register set the flags only outside of an IT block, while the comparisons and TST always set them.
 
-pub struct DataProcessingInstructions {
-    dct: Option<Vec<InstrThumb16>>,
+use crate::instructions::{ ConditionFlags, InstrThumb16, ShiftType };
+
+/// AddWithCarry() from the ARMv7-M Architecture Reference Manual, returns the result, carry out and overflow
+pub fn add_with_carry(x: u32, y: u32, carry_in: bool) -> (u32, bool, bool) {
+    let unsigned_sum = x as u64 + y as u64 + carry_in as u64;
+    let signed_sum = x as i32 as i64 + y as i32 as i64 + carry_in as i64;
+    let result = unsigned_sum as u32;
+
+    let carry_out = result as u64 != unsigned_sum;
+    let overflow = result as i32 as i64 != signed_sum;
+
+    (result, carry_out, overflow)
+}
+
+/// Shift_C() from the ARMv7-M Architecture Reference Manual, returns the result and carry out
+///
+/// A shift of zero leaves both the value and the carry unchanged. Register controlled shifts may be of any amount up
+/// to 255, shifts of 32 or more behave as the architecture's bit string extension describes.
+pub fn shift_c(value: u32, ty: ShiftType, amount: u32, carry_in: bool) -> (u32, bool) {
+    if amount == 0 {
+        return (value, carry_in)
+    }
+
+    match ty {
+        ShiftType::Lsl => lsl_c(value, amount),
+        ShiftType::Lsr => lsr_c(value, amount),
+        ShiftType::Asr => asr_c(value, amount),
+        ShiftType::Ror => ror_c(value, amount),
+        ShiftType::Rrx => rrx_c(value, carry_in),
+    }
+}
+
+fn lsl_c(x: u32, shift: u32) -> (u32, bool) {
+    match shift {
+        1..=31 => (x << shift, (x >> (32 - shift)) & 1 != 0),
+        32 => (0, x & 1 != 0),
+        _ => (0, false),
+    }
+}
+
+fn lsr_c(x: u32, shift: u32) -> (u32, bool) {
+    match shift {
+        1..=31 => (x >> shift, (x >> (shift - 1)) & 1 != 0),
+        32 => (0, x >> 31 != 0),
+        _ => (0, false),
+    }
+}
+
+fn asr_c(x: u32, shift: u32) -> (u32, bool) {
+    match shift {
+        1..=31 => (((x as i32) >> shift) as u32, (x >> (shift - 1)) & 1 != 0),
+        _ => (((x as i32) >> 31) as u32, x >> 31 != 0),
+    }
+}
+
+fn ror_c(x: u32, shift: u32) -> (u32, bool) {
+    let result = x.rotate_right(shift % 32);
+    (result, result >> 31 != 0)
+}
+
+fn rrx_c(x: u32, carry_in: bool) -> (u32, bool) {
+    ((carry_in as u32) << 31 | x >> 1, x & 1 != 0)
+}
+
+/// The effect of an executed data processing instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    /// The destination register and the value written to it, comparisons and TST have none
+    pub write: Option<(u8, u32)>,
+
+    /// The condition flags after execution
+    pub flags: ConditionFlags,
+}
+
+/// Executes a 16 bit data processing instruction
+///
+/// Registers are read through `r`, and `setflags` is false within an IT block. Returns `None` for instructions which
+/// are not part of the data processing group.
+///
+/// The high register and SP relative forms never set the flags, and may write the SP or the PC. A write to the PC is
+/// returned like any other, applying ALUWritePC() is left to the caller.
+pub fn execute_data_processing(instr: InstrThumb16, r: impl Fn(u8) -> u32, flags: ConditionFlags, setflags: bool) -> Option<Outcome> {
+    use InstrThumb16::*;
+
+    // ADR is relative to Align(PC, 4), where the PC reads as the instruction address plus 4
+    let unflagged = match instr {
+        MovReg { rm, rd } => Some((rd, r(rm))),
+        AddRegHigh { rm, rdn } => Some((rdn, r(rdn).wrapping_add(r(rm)))),
+        AddSpImm { imm, rd } => Some((rd, r(13).wrapping_add(imm as u32))),
+        AddSpReg { rm, rd } => Some((rd, r(13).wrapping_add(r(rm)))),
+        SubSpImm { imm } => Some((13, r(13).wrapping_sub(imm as u32))),
+        Adr { rd, imm } => Some((rd, (r(15) & !0b11).wrapping_add(imm as u32))),
+        _ => None,
+    };
+
+    if let Some(write) = unflagged {
+        return Some(Outcome { write: Some(write), flags })
+    }
+
+    let carry = flags.c;
+
+    // Each operation yields its destination, result, and the carry and overflow flags, None where they are unchanged
+    let (rd, result, c, v) = match instr {
+        AndReg { rm, rdn } => (Some(rdn), r(rdn) & r(rm), None, None),
+        EorReg { rm, rdn } => (Some(rdn), r(rdn) ^ r(rm), None, None),
+        OrrReg { rm, rdn } => (Some(rdn), r(rdn) | r(rm), None, None),
+        BicReg { rm, rdn } => (Some(rdn), r(rdn) & !r(rm), None, None),
+        MvnReg { rm, rd } => (Some(rd), !r(rm), None, None),
+        TstReg { rm, rn } => (None, r(rn) & r(rm), None, None),
+        Mul { rn, rdm } => (Some(rdm), r(rn).wrapping_mul(r(rdm)), None, None),
+        MovImm { rd, imm } => (Some(rd), imm as u32, None, None),
+
+        LslImm { imm, rm, rd } => shifted(rd, r(rm), ShiftType::Lsl, imm as u32, carry),
+        LsrImm { imm, rm, rd } => shifted(rd, r(rm), ShiftType::Lsr, immediate_shift(imm), carry),
+        AsrImm { imm, rm, rd } => shifted(rd, r(rm), ShiftType::Asr, immediate_shift(imm), carry),
+        LslReg { rm, rdn } => shifted(rdn, r(rdn), ShiftType::Lsl, r(rm) & 0xFF, carry),
+        LsrReg { rm, rdn } => shifted(rdn, r(rdn), ShiftType::Lsr, r(rm) & 0xFF, carry),
+        AsrReg { rm, rdn } => shifted(rdn, r(rdn), ShiftType::Asr, r(rm) & 0xFF, carry),
+        RorReg { rm, rdn } => shifted(rdn, r(rdn), ShiftType::Ror, r(rm) & 0xFF, carry),
+
+        AddImm { imm, rdn, rd } => arithmetic(Some(rd), add_with_carry(r(rdn), imm as u32, false)),
+        AddReg { rm, rdn, rd } => arithmetic(Some(rd), add_with_carry(r(rdn), r(rm), false)),
+        SubImm { imm, rn, rdn } => arithmetic(Some(rdn), add_with_carry(r(rn), !(imm as u32), true)),
+        SubReg { rm, rn, rd } => arithmetic(Some(rd), add_with_carry(r(rn), !r(rm), true)),
+        AdcReg { rm, rdn } => arithmetic(Some(rdn), add_with_carry(r(rdn), r(rm), carry)),
+        SbcReg { rm, rdn } => arithmetic(Some(rdn), add_with_carry(r(rdn), !r(rm), carry)),
+        RsbImm { rn, rd } => arithmetic(Some(rd), add_with_carry(!r(rn), 0, true)),
+        CmpImm { rn, imm } => arithmetic(None, add_with_carry(r(rn), !(imm as u32), true)),
+        CmpReg { rm, rn } => arithmetic(None, add_with_carry(r(rn), !r(rm), true)),
+        CmnReg { rm, rn } => arithmetic(None, add_with_carry(r(rn), r(rm), false)),
+
+        _ => return None,
+    };
+
+    let flags = if setflags || rd.is_none() {
+        ConditionFlags {
+            n: result >> 31 != 0,
+            z: result == 0,
+            c: c.unwrap_or(flags.c),
+            v: v.unwrap_or(flags.v),
+        }
+    } else {
+        flags
+    };
+
+    Some(Outcome { write: rd.map(|rd| (rd, result)), flags })
+}
+
+/// DecodeImmShift() for the LSR and ASR immediate encodings, where an imm5 of zero encodes a shift of 32
+fn immediate_shift(imm5: u8) -> u32 {
+    if imm5 == 0 { 32 } else { imm5 as u32 }
+}
+
+fn shifted(rd: u8, value: u32, ty: ShiftType, amount: u32, carry: bool) -> (Option<u8>, u32, Option<bool>, Option<bool>) {
+    let (result, carry) = shift_c(value, ty, amount, carry);
+    (Some(rd), result, Some(carry), None)
+}
+
+fn arithmetic(rd: Option<u8>, (result, carry, overflow): (u32, bool, bool)) -> (Option<u8>, u32, Option<bool>, Option<bool>) {
+    (rd, result, Some(carry), Some(overflow))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NONE: ConditionFlags = ConditionFlags { n: false, z: false, c: false, v: false };
+
+    fn flags(nzcv: &str) -> ConditionFlags {
+        ConditionFlags {
+            n: nzcv.contains('N'),
+            z: nzcv.contains('Z'),
+            c: nzcv.contains('C'),
+            v: nzcv.contains('V'),
+        }
+    }
+
+    #[test]
+    fn add_with_carry_flags() {
+        assert_eq!(add_with_carry(1, 2, false), (3, false, false));
+        assert_eq!(add_with_carry(0xFFFF_FFFF, 1, false), (0, true, false));
+        assert_eq!(add_with_carry(0x7FFF_FFFF, 1, false), (0x8000_0000, false, true));
+        assert_eq!(add_with_carry(0x8000_0000, 0x8000_0000, false), (0, true, true));
+        assert_eq!(add_with_carry(0xFFFF_FFFF, 0, true), (0, true, false));
+
+        // subtraction is x + NOT(y) + 1, the carry is set when there is no borrow
+        assert_eq!(add_with_carry(5, !3, true), (2, true, false));
+        assert_eq!(add_with_carry(3, !5, true), (0xFFFF_FFFE, false, false));
+        assert_eq!(add_with_carry(0x8000_0000, !1, true), (0x7FFF_FFFF, true, true));
+    }
+
+    #[test]
+    fn shift_c_amounts() {
+        assert_eq!(shift_c(0x8000_0001, ShiftType::Lsl, 0, true), (0x8000_0001, true));
+        assert_eq!(shift_c(0x8000_0001, ShiftType::Lsl, 1, false), (0x0000_0002, true));
+        assert_eq!(shift_c(0x0000_0001, ShiftType::Lsl, 32, false), (0, true));
+        assert_eq!(shift_c(0xFFFF_FFFF, ShiftType::Lsl, 33, true), (0, false));
+        assert_eq!(shift_c(0x8000_0001, ShiftType::Lsr, 1, false), (0x4000_0000, true));
+        assert_eq!(shift_c(0x8000_0000, ShiftType::Lsr, 32, false), (0, true));
+        assert_eq!(shift_c(0x8000_0000, ShiftType::Lsr, 200, true), (0, false));
+        assert_eq!(shift_c(0x8000_0000, ShiftType::Asr, 4, false), (0xF800_0000, false));
+        assert_eq!(shift_c(0x8000_0000, ShiftType::Asr, 40, false), (0xFFFF_FFFF, true));
+        assert_eq!(shift_c(0x4000_0000, ShiftType::Asr, 32, true), (0, false));
+        assert_eq!(shift_c(0x0000_0001, ShiftType::Ror, 1, false), (0x8000_0000, true));
+        assert_eq!(shift_c(0x8000_0000, ShiftType::Ror, 32, false), (0x8000_0000, true));
+        assert_eq!(shift_c(0x0000_0003, ShiftType::Rrx, 1, true), (0x8000_0001, true));
+    }
+
+    #[test]
+    fn data_processing_instructions() {
+        use InstrThumb16::*;
+
+        let regs = [0u32, 1, 0xFFFF_FFFF, 0x8000_0000, 5, 3, 0, 0];
+        let r = |n: u8| regs[n as usize];
+        let run = |instr, flags, setflags| execute_data_processing(instr, r, flags, setflags).unwrap();
+
+        let out = run(AddReg { rm: 1, rdn: 2, rd: 0 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((0, 0)), flags: flags("ZC") });
+
+        let out = run(SubReg { rm: 4, rn: 5, rd: 0 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((0, 0xFFFF_FFFE)), flags: flags("N") });
+
+        let out = run(CmpImm { rn: 4, imm: 5 }, NONE, true);
+        assert_eq!(out, Outcome { write: None, flags: flags("ZC") });
+
+        let out = run(CmnReg { rm: 3, rn: 3 }, NONE, false);
+        assert_eq!(out, Outcome { write: None, flags: flags("ZCV") }, "comparisons set flags within an IT block");
+
+        let out = run(AdcReg { rm: 1, rdn: 4 }, flags("C"), true);
+        assert_eq!(out, Outcome { write: Some((4, 7)), flags: NONE });
+
+        let out = run(SbcReg { rm: 5, rdn: 4 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((4, 1)), flags: flags("C") });
+
+        let out = run(RsbImm { rn: 1, rd: 0 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((0, 0xFFFF_FFFF)), flags: flags("N") });
+
+        let out = run(AndReg { rm: 3, rdn: 2 }, flags("CV"), true);
+        assert_eq!(out, Outcome { write: Some((2, 0x8000_0000)), flags: flags("NCV") }, "logical operations keep C and V");
+
+        let out = run(BicReg { rm: 2, rdn: 4 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((4, 0)), flags: flags("Z") });
+
+        let out = run(MvnReg { rm: 2, rd: 0 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((0, 0)), flags: flags("Z") });
+
+        let out = run(TstReg { rm: 1, rn: 4 }, NONE, false);
+        assert_eq!(out, Outcome { write: None, flags: NONE });
+
+        let out = run(Mul { rn: 4, rdm: 5 }, flags("CV"), true);
+        assert_eq!(out, Outcome { write: Some((5, 15)), flags: flags("CV") });
+
+        let out = run(LslImm { imm: 0, rm: 3, rd: 0 }, flags("C"), true);
+        assert_eq!(out, Outcome { write: Some((0, 0x8000_0000)), flags: flags("NC") }, "movs keeps the carry");
+
+        let out = run(LsrImm { imm: 0, rm: 3, rd: 0 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((0, 0)), flags: flags("ZC") }, "an imm5 of zero shifts by 32");
+
+        let out = run(AsrImm { imm: 1, rm: 2, rd: 0 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((0, 0xFFFF_FFFF)), flags: flags("NC") });
+
+        let out = run(RorReg { rm: 1, rdn: 1 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((1, 0x8000_0000)), flags: flags("NC") });
+
+        let out = run(MovImm { rd: 0, imm: 0 }, flags("V"), false);
+        assert_eq!(out, Outcome { write: Some((0, 0)), flags: flags("V") }, "no flags are set within an IT block");
+
+        let out = run(MovReg { rm: 2, rd: 8 }, flags("Z"), true);
+        assert_eq!(out, Outcome { write: Some((8, 0xFFFF_FFFF)), flags: flags("Z") }, "mov between high registers keeps the flags");
+
+        let out = run(AddRegHigh { rm: 4, rdn: 5 }, NONE, true);
+        assert_eq!(out, Outcome { write: Some((5, 8)), flags: NONE });
+
+        let regs = [0u32, 0, 0, 0, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0x800, 0, 0x106];
+        let r = |n: u8| regs[n as usize];
+        let run = |instr| execute_data_processing(instr, r, NONE, true).unwrap().write;
+
+        assert_eq!(run(AddSpImm { imm: 16, rd: 13 }), Some((13, 0x810)));
+        assert_eq!(run(AddSpImm { imm: 8, rd: 7 }), Some((7, 0x808)));
+        assert_eq!(run(AddSpReg { rm: 4, rd: 4 }), Some((4, 0x810)));
+        assert_eq!(run(SubSpImm { imm: 16 }), Some((13, 0x7F0)));
+        assert_eq!(run(Adr { rd: 0, imm: 4 }), Some((0, 0x108)), "adr is relative to the word aligned PC");
+        assert_eq!(run(MovReg { rm: 14, rd: 15 }), Some((15, 0)), "a write to the PC is left to the caller");
+
+        assert_eq!(execute_data_processing(Nop, r, NONE, true), None);
+    }
 }
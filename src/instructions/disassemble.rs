@@ -0,0 +1,564 @@
+//! Unified Assembler Language (UAL) rendering of decoded instructions
+//!
+//! Both instruction sets implement `Display`, which renders PC relative operands relative to the instruction itself
+//! (`b.n .+8`). Pairing an instruction with its address through `disassemble` resolves them to absolute targets
+//! instead (`b.n 0x11008`), the literal loads and ADR gain the target address as a trailing comment.
+//!
+//! 16 bit data processing instructions are rendered in their flag setting form (`adds`), which is what they are
+//! outside of an IT block.
+
+use std::fmt;
+
+use crate::instructions::{ InstrThumb16, InstrThumb32, ImmShift, ShiftType };
+use crate::instructions::thumb32::thumb_expand_imm;
+
+/// An instruction paired with the address it was fetched from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Disassembly<T> {
+    instr: T,
+    address: Option<u32>,
+}
+
+impl InstrThumb16 {
+    /// Disassembles the instruction as if it were located at `address`
+    pub fn disassemble(self, address: u32) -> Disassembly<InstrThumb16> {
+        Disassembly { instr: self, address: Some(address) }
+    }
+}
+
+impl InstrThumb32 {
+    /// Disassembles the instruction as if it were located at `address`
+    pub fn disassemble(self, address: u32) -> Disassembly<InstrThumb32> {
+        Disassembly { instr: self, address: Some(address) }
+    }
+}
+
+impl fmt::Display for InstrThumb16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&Disassembly { instr: *self, address: None }, f)
+    }
+}
+
+impl fmt::Display for InstrThumb32 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&Disassembly { instr: *self, address: None }, f)
+    }
+}
+
+const REGISTERS: [&str; 16] = [
+    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "sp", "lr", "pc"
+];
+
+const CONDITIONS: [&str; 16] = [
+    "eq", "ne", "hs", "lo", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "", ""
+];
+
+fn reg(n: u8) -> &'static str {
+    REGISTERS[(n & 0xF) as usize]
+}
+
+fn cond(c: u8) -> &'static str {
+    CONDITIONS[(c & 0xF) as usize]
+}
+
+/// Renders a register list, runs of three or more registers are collapsed into a range
+fn reglist(list: u16) -> String {
+    let mut parts = Vec::new();
+    let mut n = 0;
+
+    while n < 16 {
+        if list & (1 << n) == 0 {
+            n += 1;
+            continue;
+        }
+
+        let start = n;
+        while n < 16 && list & (1 << n) != 0 {
+            n += 1;
+        }
+
+        match n - start {
+            1 => parts.push(String::from(reg(start))),
+            2 => parts.push(format!("{}, {}", reg(start), reg(start + 1))),
+            _ => parts.push(format!("{}-{}", reg(start), reg(n - 1))),
+        }
+    }
+
+    format!("{{{}}}", parts.join(", "))
+}
+
+/// Renders an immediate shift applied to a register operand, including the leading separator
+fn shift(shift: ImmShift) -> String {
+    match shift {
+        ImmShift { ty: ShiftType::Lsl, n: 0 } => String::new(),
+        ImmShift { ty: ShiftType::Rrx, .. } => String::from(", rrx"),
+        ImmShift { ty, n } => format!(", {} #{}", shift_name(ty), n),
+    }
+}
+
+fn shift_name(ty: ShiftType) -> &'static str {
+    match ty {
+        ShiftType::Lsl => "lsl",
+        ShiftType::Lsr => "lsr",
+        ShiftType::Asr => "asr",
+        ShiftType::Ror => "ror",
+        ShiftType::Rrx => "rrx",
+    }
+}
+
+fn signed(imm: u32, add: bool) -> String {
+    match add {
+        true => format!("#{}", imm),
+        false => format!("#-{}", imm),
+    }
+}
+
+/// Renders an immediate offset addressing mode
+fn memory(rn: u8, imm: u32, index: bool, add: bool, wback: bool) -> String {
+    match (index, wback) {
+        (true, false) if imm == 0 && add => format!("[{}]", reg(rn)),
+        (true, false) => format!("[{}, {}]", reg(rn), signed(imm, add)),
+        (true, true) => format!("[{}, {}]!", reg(rn), signed(imm, add)),
+        (false, _) => format!("[{}], {}", reg(rn), signed(imm, add)),
+    }
+}
+
+/// Renders a register offset addressing mode
+fn memory_reg(rn: u8, rm: u8, lsl: u8) -> String {
+    match lsl {
+        0 => format!("[{}, {}]", reg(rn), reg(rm)),
+        n => format!("[{}, {}, lsl #{}]", reg(rn), reg(rm), n),
+    }
+}
+
+/// The special register names accepted by MRS and MSR
+fn special_register(sysm: u8) -> String {
+    match sysm {
+        0 => String::from("apsr"),
+        1 => String::from("iapsr"),
+        2 => String::from("eapsr"),
+        3 => String::from("xpsr"),
+        5 => String::from("ipsr"),
+        6 => String::from("epsr"),
+        7 => String::from("iepsr"),
+        8 => String::from("msp"),
+        9 => String::from("psp"),
+        16 => String::from("primask"),
+        17 => String::from("basepri"),
+        18 => String::from("basepri_max"),
+        19 => String::from("faultmask"),
+        20 => String::from("control"),
+        n => format!("#{}", n),
+    }
+}
+
+fn barrier_option(option: u8) -> String {
+    match option {
+        0xF => String::from("sy"),
+        n => format!("#{}", n),
+    }
+}
+
+/// Renders a PC relative target, `offset` is relative to the address of the instruction
+fn target(address: Option<u32>, offset: i64) -> String {
+    match address {
+        Some(address) => format!("{:#x}", (address as i64 + offset) as u32),
+        None if offset < 0 => format!(".-{}", -offset),
+        None => format!(".+{}", offset),
+    }
+}
+
+/// Renders the Align(PC, 4) relative target of a literal load or ADR as a trailing comment, when the address is known
+fn resolved(address: Option<u32>, imm: u32, add: bool) -> String {
+    match address {
+        Some(address) => {
+            let base = (address + 4) & !3;
+            let resolved = if add { base.wrapping_add(imm) } else { base.wrapping_sub(imm) };
+            format!(" @ {:#x}", resolved)
+        },
+        None => String::new(),
+    }
+}
+
+/// Renders a literal load addressing mode
+fn literal(address: Option<u32>, imm: u32, add: bool) -> String {
+    format!("[pc, {}]{}", signed(imm, add), resolved(address, imm, add))
+}
+
+impl fmt::Display for Disassembly<InstrThumb16> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use InstrThumb16::*;
+
+        let address = self.address;
+        match self.instr {
+            AdcReg { rm, rdn } => write!(f, "adcs {}, {}", reg(rdn), reg(rm)),
+            AddImm { imm, rdn, rd } if rdn == rd => write!(f, "adds {}, #{}", reg(rd), imm),
+            AddImm { imm, rdn, rd } => write!(f, "adds {}, {}, #{}", reg(rd), reg(rdn), imm),
+            AddReg { rm, rdn, rd } => write!(f, "adds {}, {}, {}", reg(rd), reg(rdn), reg(rm)),
+            AddRegHigh { rm, rdn } => write!(f, "add {}, {}", reg(rdn), reg(rm)),
+            AddSpImm { imm, rd: 13 } => write!(f, "add sp, #{}", imm),
+            AddSpImm { imm, rd } => write!(f, "add {}, sp, #{}", reg(rd), imm),
+            AddSpReg { rm, rd: 13 } => write!(f, "add sp, {}", reg(rm)),
+            AddSpReg { rm, rd } => write!(f, "add {}, sp, {}", reg(rd), reg(rm)),
+            Adr { rd, imm } => write!(f, "adr {}, #{}{}", reg(rd), imm, resolved(address, imm as u32, true)),
+            AndReg { rm, rdn } => write!(f, "ands {}, {}", reg(rdn), reg(rm)),
+            AsrImm { imm, rm, rd } => write!(f, "asrs {}, {}, #{}", reg(rd), reg(rm), if imm == 0 { 32 } else { imm }),
+            AsrReg { rm, rdn } => write!(f, "asrs {}, {}", reg(rdn), reg(rm)),
+            BranchE1 { cond: c, imm } => write!(f, "b{}.n {}", cond(c), target(address, 4 + imm as i64)),
+            BranchE2 { imm } => write!(f, "b.n {}", target(address, 4 + imm as i64)),
+            BicReg { rm, rdn } => write!(f, "bics {}, {}", reg(rdn), reg(rm)),
+            Breakpoint { imm } => write!(f, "bkpt #{}", imm),
+            BranchLx { rm } => write!(f, "blx {}", reg(rm)),
+            BranchX { rm } => write!(f, "bx {}", reg(rm)),
+            Cbnz { imm, rn } => write!(f, "cbnz {}, {}", reg(rn), target(address, 4 + imm as i64)),
+            Cbz { imm, rn } => write!(f, "cbz {}, {}", reg(rn), target(address, 4 + imm as i64)),
+            CmnReg { rm, rn } => write!(f, "cmn {}, {}", reg(rn), reg(rm)),
+            CmpImm { rn, imm } => write!(f, "cmp {}, #{}", reg(rn), imm),
+            CmpReg { rm, rn } => write!(f, "cmp {}, {}", reg(rn), reg(rm)),
+            Cps { imm, fi, ff } => {
+                let flags = format!("{}{}", if fi != 0 { "i" } else { "" }, if ff != 0 { "f" } else { "" });
+                write!(f, "cps{} {}", if imm != 0 { "id" } else { "ie" }, flags)
+            },
+            EorReg { rm, rdn } => write!(f, "eors {}, {}", reg(rdn), reg(rm)),
+            IfThen { cond: c, mask } => {
+                // Each mask bit above the terminating one selects then or else for a further instruction
+                let mut suffix = String::new();
+                let lowest = mask.trailing_zeros();
+                for n in (lowest + 1..4).rev() {
+                    suffix.push(if (mask >> n) & 1 == c & 1 { 't' } else { 'e' });
+                }
+                write!(f, "it{} {}", suffix, cond(c))
+            },
+            Ldm { list, rn } => {
+                let wback = if list & (1 << rn) == 0 { "!" } else { "" };
+                write!(f, "ldm {}{}, {}", reg(rn), wback, reglist(list as u16))
+            },
+            LdrImm { imm, rn, rt } => write!(f, "ldr {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            LdrLit { rt, imm } => write!(f, "ldr {}, {}", reg(rt), literal(address, imm as u32, true)),
+            LdrReg { rm, rn, rt } => write!(f, "ldr {}, {}", reg(rt), memory_reg(rn, rm, 0)),
+            LdrbImm { imm, rn, rt } => write!(f, "ldrb {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            LdrbReg { rm, rn, rt } => write!(f, "ldrb {}, {}", reg(rt), memory_reg(rn, rm, 0)),
+            LdrhImm { imm, rn, rt } => write!(f, "ldrh {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            LdrhReg { rm, rn, rt } => write!(f, "ldrh {}, {}", reg(rt), memory_reg(rn, rm, 0)),
+            LdrSbReg { rm, rn, rt } => write!(f, "ldrsb {}, {}", reg(rt), memory_reg(rn, rm, 0)),
+            LdrShReg { rm, rn, rt } => write!(f, "ldrsh {}, {}", reg(rt), memory_reg(rn, rm, 0)),
+            LdrSpImm { rt, imm } => write!(f, "ldr {}, {}", reg(rt), memory(13, imm as u32, true, true, false)),
+            LslImm { imm: 0, rm, rd } => write!(f, "movs {}, {}", reg(rd), reg(rm)),
+            LslImm { imm, rm, rd } => write!(f, "lsls {}, {}, #{}", reg(rd), reg(rm), imm),
+            LslReg { rm, rdn } => write!(f, "lsls {}, {}", reg(rdn), reg(rm)),
+            LsrImm { imm, rm, rd } => write!(f, "lsrs {}, {}, #{}", reg(rd), reg(rm), if imm == 0 { 32 } else { imm }),
+            LsrReg { rm, rdn } => write!(f, "lsrs {}, {}", reg(rdn), reg(rm)),
+            MovImm { rd, imm } => write!(f, "movs {}, #{}", reg(rd), imm),
+            MovReg { rm, rd } => write!(f, "mov {}, {}", reg(rd), reg(rm)),
+            Mul { rn, rdm } => write!(f, "muls {}, {}, {}", reg(rdm), reg(rn), reg(rdm)),
+            MvnReg { rm, rd } => write!(f, "mvns {}, {}", reg(rd), reg(rm)),
+            Nop => write!(f, "nop"),
+            OrrReg { rm, rdn } => write!(f, "orrs {}, {}", reg(rdn), reg(rm)),
+            Pop { list } => write!(f, "pop {}", reglist(list)),
+            Push { list } => write!(f, "push {}", reglist(list)),
+            Rev { rm, rd } => write!(f, "rev {}, {}", reg(rd), reg(rm)),
+            Rev16 { rm, rd } => write!(f, "rev16 {}, {}", reg(rd), reg(rm)),
+            RevSh { rm, rd } => write!(f, "revsh {}, {}", reg(rd), reg(rm)),
+            RorReg { rm, rdn } => write!(f, "rors {}, {}", reg(rdn), reg(rm)),
+            RsbImm { rn, rd } => write!(f, "rsbs {}, {}, #0", reg(rd), reg(rn)),
+            SbcReg { rm, rdn } => write!(f, "sbcs {}, {}", reg(rdn), reg(rm)),
+            Sev => write!(f, "sev"),
+            Stm { rn, list } => write!(f, "stm {}!, {}", reg(rn), reglist(list as u16)),
+            StrImm { imm, rn, rt } => write!(f, "str {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            StrReg { rm, rn, rt } => write!(f, "str {}, {}", reg(rt), memory_reg(rn, rm, 0)),
+            StrbImm { imm, rn, rt } => write!(f, "strb {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            StrbReg { rm, rn, rt } => write!(f, "strb {}, {}", reg(rt), memory_reg(rn, rm, 0)),
+            StrhImm { imm, rn, rt } => write!(f, "strh {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            StrhReg { rm, rn, rt } => write!(f, "strh {}, {}", reg(rt), memory_reg(rn, rm, 0)),
+            StrSpImm { rt, imm } => write!(f, "str {}, {}", reg(rt), memory(13, imm as u32, true, true, false)),
+            SubImm { imm, rn, rdn } if rn == rdn => write!(f, "subs {}, #{}", reg(rdn), imm),
+            SubImm { imm, rn, rdn } => write!(f, "subs {}, {}, #{}", reg(rdn), reg(rn), imm),
+            SubReg { rm, rn, rd } => write!(f, "subs {}, {}, {}", reg(rd), reg(rn), reg(rm)),
+            SubSpImm { imm } => write!(f, "sub sp, #{}", imm),
+            Svc { imm } => write!(f, "svc #{}", imm),
+            Sxtb { rm, rd } => write!(f, "sxtb {}, {}", reg(rd), reg(rm)),
+            Sxth { rm, rd } => write!(f, "sxth {}, {}", reg(rd), reg(rm)),
+            TstReg { rm, rn } => write!(f, "tst {}, {}", reg(rn), reg(rm)),
+            Udf { imm } => write!(f, "udf #{}", imm),
+            Uxtb { rm, rd } => write!(f, "uxtb {}, {}", reg(rd), reg(rm)),
+            Uxth { rm, rd } => write!(f, "uxth {}, {}", reg(rd), reg(rm)),
+            Wfe => write!(f, "wfe"),
+            Wfi => write!(f, "wfi"),
+            Yield => write!(f, "yield"),
+
+            // The first halfword of a 32 bit instruction means nothing on its own
+            Thumb2A { high, low } => write!(f, ".short {:#06x}", 0xE800 | (high as u16) << 8 | low as u16),
+            Thumb2B { high, low } => write!(f, ".short {:#06x}", 0xF000 | (high as u16) << 8 | low as u16),
+            Thumb2C { high, low } => write!(f, ".short {:#06x}", 0xF800 | (high as u16) << 8 | low as u16),
+
+            Test { .. } | Undefined | UnknownInstruction => write!(f, "undefined"),
+        }
+    }
+}
+
+impl fmt::Display for Disassembly<InstrThumb32> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use InstrThumb32::*;
+
+        let address = self.address;
+        let s = |s: bool| if s { "s" } else { "" };
+        let l = |long: bool| if long { "l" } else { "" };
+
+        match self.instr {
+            AdcImm { s: sf, rn, rd, imm12 } => write!(f, "adc{} {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            AddImm { s: sf, rn, rd, imm12 } => write!(f, "add{}.w {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            AndImm { s: sf, rn, rd, imm12 } => write!(f, "and{} {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            BicImm { s: sf, rn, rd, imm12 } => write!(f, "bic{} {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            CmnImm { rn, imm12 } => write!(f, "cmn.w {}, #{}", reg(rn), thumb_expand_imm(imm12)),
+            CmpImm { rn, imm12 } => write!(f, "cmp.w {}, #{}", reg(rn), thumb_expand_imm(imm12)),
+            EorImm { s: sf, rn, rd, imm12 } => write!(f, "eor{} {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            MovImm { s: sf, rd, imm12 } => write!(f, "mov{}.w {}, #{}", s(sf), reg(rd), thumb_expand_imm(imm12)),
+            MvnImm { s: sf, rd, imm12 } => write!(f, "mvn{} {}, #{}", s(sf), reg(rd), thumb_expand_imm(imm12)),
+            OrnImm { s: sf, rn, rd, imm12 } => write!(f, "orn{} {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            OrrImm { s: sf, rn, rd, imm12 } => write!(f, "orr{} {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            RsbImm { s: sf, rn, rd, imm12 } => write!(f, "rsb{}.w {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            SbcImm { s: sf, rn, rd, imm12 } => write!(f, "sbc{} {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            SubImm { s: sf, rn, rd, imm12 } => write!(f, "sub{}.w {}, {}, #{}", s(sf), reg(rd), reg(rn), thumb_expand_imm(imm12)),
+            TeqImm { rn, imm12 } => write!(f, "teq {}, #{}", reg(rn), thumb_expand_imm(imm12)),
+            TstImm { rn, imm12 } => write!(f, "tst.w {}, #{}", reg(rn), thumb_expand_imm(imm12)),
+
+            Addw { rn, rd, imm } => write!(f, "addw {}, {}, #{}", reg(rd), reg(rn), imm),
+            Adr { rd, imm, add } => write!(f, "adr.w {}, {}{}", reg(rd), signed(imm as u32, add), resolved(address, imm as u32, add)),
+            Bfc { rd, msb, lsb } => write!(f, "bfc {}, #{}, #{}", reg(rd), lsb, msb as i32 - lsb as i32 + 1),
+            Bfi { rn, rd, msb, lsb } => write!(f, "bfi {}, {}, #{}, #{}", reg(rd), reg(rn), lsb, msb as i32 - lsb as i32 + 1),
+            Movt { rd, imm } => write!(f, "movt {}, #{}", reg(rd), imm),
+            Movw { rd, imm } => write!(f, "movw {}, #{}", reg(rd), imm),
+            Sbfx { rn, rd, lsb, widthm1 } => write!(f, "sbfx {}, {}, #{}, #{}", reg(rd), reg(rn), lsb, widthm1 + 1),
+            Ssat { rn, rd, sat_imm, shift: sh } => write!(f, "ssat {}, #{}, {}{}", reg(rd), sat_imm, reg(rn), shift(sh)),
+            Subw { rn, rd, imm } => write!(f, "subw {}, {}, #{}", reg(rd), reg(rn), imm),
+            Ubfx { rn, rd, lsb, widthm1 } => write!(f, "ubfx {}, {}, #{}, #{}", reg(rd), reg(rn), lsb, widthm1 + 1),
+            Usat { rn, rd, sat_imm, shift: sh } => write!(f, "usat {}, #{}, {}{}", reg(rd), sat_imm, reg(rn), shift(sh)),
+
+            BranchE3 { cond: c, imm } => write!(f, "b{}.w {}", cond(c), target(address, 4 + imm as i64)),
+            BranchE4 { imm } => write!(f, "b.w {}", target(address, 4 + imm as i64)),
+            BranchL { imm } => write!(f, "bl {}", target(address, 4 + imm as i64)),
+            ClrEx => write!(f, "clrex"),
+            Dbg { option } => write!(f, "dbg #{}", option),
+            Dmb { option } => write!(f, "dmb {}", barrier_option(option)),
+            Dsb { option } => write!(f, "dsb {}", barrier_option(option)),
+            Isb { option } => write!(f, "isb {}", barrier_option(option)),
+            Mrs { rd, sysm } => write!(f, "mrs {}, {}", reg(rd), special_register(sysm)),
+            Msr { rn, mask, sysm } => {
+                // The APSR forms name the fields being written
+                let fields = match (sysm < 8, mask) {
+                    (true, 0b10) => "_nzcvq",
+                    (true, 0b01) => "_g",
+                    (true, 0b11) => "_nzcvqg",
+                    _ => "",
+                };
+                write!(f, "msr {}{}, {}", special_register(sysm), fields, reg(rn))
+            },
+            Nop => write!(f, "nop.w"),
+            Sev => write!(f, "sev.w"),
+            Udf { imm } => write!(f, "udf.w #{}", imm),
+            Wfe => write!(f, "wfe.w"),
+            Wfi => write!(f, "wfi.w"),
+            Yield => write!(f, "yield.w"),
+
+            Ldm { rn, w, list } => write!(f, "ldm.w {}{}, {}", reg(rn), if w { "!" } else { "" }, reglist(list)),
+            Ldmdb { rn, w, list } => write!(f, "ldmdb {}{}, {}", reg(rn), if w { "!" } else { "" }, reglist(list)),
+            Pop { list } => write!(f, "pop.w {}", reglist(list)),
+            Push { list } => write!(f, "push.w {}", reglist(list)),
+            Stm { rn, w, list } => write!(f, "stm.w {}{}, {}", reg(rn), if w { "!" } else { "" }, reglist(list)),
+            Stmdb { rn, w, list } => write!(f, "stmdb {}{}, {}", reg(rn), if w { "!" } else { "" }, reglist(list)),
+
+            LdrdImm { rn, rt, rt2, imm, index, add, wback } => write!(f, "ldrd {}, {}, {}", reg(rt), reg(rt2), memory(rn, imm as u32, index, add, wback)),
+            LdrdLit { rt, rt2, imm, add } => write!(f, "ldrd {}, {}, {}", reg(rt), reg(rt2), literal(address, imm as u32, add)),
+            LdrEx { rn, rt, imm } => write!(f, "ldrex {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            LdrExB { rn, rt } => write!(f, "ldrexb {}, [{}]", reg(rt), reg(rn)),
+            LdrExH { rn, rt } => write!(f, "ldrexh {}, [{}]", reg(rt), reg(rn)),
+            StrdImm { rn, rt, rt2, imm, index, add, wback } => write!(f, "strd {}, {}, {}", reg(rt), reg(rt2), memory(rn, imm as u32, index, add, wback)),
+            StrEx { rn, rt, rd, imm } => write!(f, "strex {}, {}, {}", reg(rd), reg(rt), memory(rn, imm as u32, true, true, false)),
+            StrExB { rn, rt, rd } => write!(f, "strexb {}, {}, [{}]", reg(rd), reg(rt), reg(rn)),
+            StrExH { rn, rt, rd } => write!(f, "strexh {}, {}, [{}]", reg(rd), reg(rt), reg(rn)),
+            Tbb { rn, rm } => write!(f, "tbb [{}, {}]", reg(rn), reg(rm)),
+            Tbh { rn, rm } => write!(f, "tbh [{}, {}, lsl #1]", reg(rn), reg(rm)),
+
+            AdcReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "adc{}.w {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            AddReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "add{}.w {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            AndReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "and{}.w {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            AsrImm { s: sf, rd, rm, imm } => write!(f, "asr{}.w {}, {}, #{}", s(sf), reg(rd), reg(rm), imm),
+            BicReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "bic{}.w {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            CmnReg { rn, rm, shift: sh } => write!(f, "cmn.w {}, {}{}", reg(rn), reg(rm), shift(sh)),
+            CmpReg { rn, rm, shift: sh } => write!(f, "cmp.w {}, {}{}", reg(rn), reg(rm), shift(sh)),
+            EorReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "eor{}.w {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            LslImm { s: sf, rd, rm, imm } => write!(f, "lsl{}.w {}, {}, #{}", s(sf), reg(rd), reg(rm), imm),
+            LsrImm { s: sf, rd, rm, imm } => write!(f, "lsr{}.w {}, {}, #{}", s(sf), reg(rd), reg(rm), imm),
+            MovReg { s: sf, rd, rm } => write!(f, "mov{}.w {}, {}", s(sf), reg(rd), reg(rm)),
+            MvnReg { s: sf, rd, rm, shift: sh } => write!(f, "mvn{}.w {}, {}{}", s(sf), reg(rd), reg(rm), shift(sh)),
+            OrnReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "orn{} {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            OrrReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "orr{}.w {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            RorImm { s: sf, rd, rm, imm } => write!(f, "ror{}.w {}, {}, #{}", s(sf), reg(rd), reg(rm), imm),
+            RsbReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "rsb{} {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            Rrx { s: sf, rd, rm } => write!(f, "rrx{} {}, {}", s(sf), reg(rd), reg(rm)),
+            SbcReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "sbc{}.w {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            SubReg { s: sf, rn, rd, rm, shift: sh } => write!(f, "sub{}.w {}, {}, {}{}", s(sf), reg(rd), reg(rn), reg(rm), shift(sh)),
+            TeqReg { rn, rm, shift: sh } => write!(f, "teq {}, {}{}", reg(rn), reg(rm), shift(sh)),
+            TstReg { rn, rm, shift: sh } => write!(f, "tst.w {}, {}{}", reg(rn), reg(rm), shift(sh)),
+
+            AsrReg { s: sf, rn, rd, rm } => write!(f, "asr{}.w {}, {}, {}", s(sf), reg(rd), reg(rn), reg(rm)),
+            Clz { rd, rm } => write!(f, "clz {}, {}", reg(rd), reg(rm)),
+            LslReg { s: sf, rn, rd, rm } => write!(f, "lsl{}.w {}, {}, {}", s(sf), reg(rd), reg(rn), reg(rm)),
+            LsrReg { s: sf, rn, rd, rm } => write!(f, "lsr{}.w {}, {}, {}", s(sf), reg(rd), reg(rn), reg(rm)),
+            Rbit { rd, rm } => write!(f, "rbit {}, {}", reg(rd), reg(rm)),
+            Rev { rd, rm } => write!(f, "rev.w {}, {}", reg(rd), reg(rm)),
+            Rev16 { rd, rm } => write!(f, "rev16.w {}, {}", reg(rd), reg(rm)),
+            RevSh { rd, rm } => write!(f, "revsh.w {}, {}", reg(rd), reg(rm)),
+            RorReg { s: sf, rn, rd, rm } => write!(f, "ror{}.w {}, {}, {}", s(sf), reg(rd), reg(rn), reg(rm)),
+            Sxtb { rd, rm, rotation } => write!(f, "sxtb.w {}, {}{}", reg(rd), reg(rm), rotate(rotation)),
+            Sxth { rd, rm, rotation } => write!(f, "sxth.w {}, {}{}", reg(rd), reg(rm), rotate(rotation)),
+            Uxtb { rd, rm, rotation } => write!(f, "uxtb.w {}, {}{}", reg(rd), reg(rm), rotate(rotation)),
+            Uxth { rd, rm, rotation } => write!(f, "uxth.w {}, {}{}", reg(rd), reg(rm), rotate(rotation)),
+
+            Mla { rn, rd, rm, ra } => write!(f, "mla {}, {}, {}, {}", reg(rd), reg(rn), reg(rm), reg(ra)),
+            Mls { rn, rd, rm, ra } => write!(f, "mls {}, {}, {}, {}", reg(rd), reg(rn), reg(rm), reg(ra)),
+            Mul { rn, rd, rm } => write!(f, "mul.w {}, {}, {}", reg(rd), reg(rn), reg(rm)),
+            Sdiv { rn, rd, rm } => write!(f, "sdiv {}, {}, {}", reg(rd), reg(rn), reg(rm)),
+            SmlaL { rn, rdlo, rdhi, rm } => write!(f, "smlal {}, {}, {}, {}", reg(rdlo), reg(rdhi), reg(rn), reg(rm)),
+            SmulL { rn, rdlo, rdhi, rm } => write!(f, "smull {}, {}, {}, {}", reg(rdlo), reg(rdhi), reg(rn), reg(rm)),
+            Udiv { rn, rd, rm } => write!(f, "udiv {}, {}, {}", reg(rd), reg(rn), reg(rm)),
+            UmlaL { rn, rdlo, rdhi, rm } => write!(f, "umlal {}, {}, {}, {}", reg(rdlo), reg(rdhi), reg(rn), reg(rm)),
+            UmulL { rn, rdlo, rdhi, rm } => write!(f, "umull {}, {}, {}, {}", reg(rdlo), reg(rdhi), reg(rn), reg(rm)),
+
+            LdrImm { rn, rt, imm, index, add, wback } => write!(f, "ldr.w {}, {}", reg(rt), memory(rn, imm as u32, index, add, wback)),
+            LdrLit { rt, imm, add } => write!(f, "ldr.w {}, {}", reg(rt), literal(address, imm as u32, add)),
+            LdrReg { rn, rt, rm, shift: sh } => write!(f, "ldr.w {}, {}", reg(rt), memory_reg(rn, rm, sh)),
+            Ldrt { rn, rt, imm } => write!(f, "ldrt {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            LdrbImm { rn, rt, imm, index, add, wback } => write!(f, "ldrb.w {}, {}", reg(rt), memory(rn, imm as u32, index, add, wback)),
+            LdrbLit { rt, imm, add } => write!(f, "ldrb.w {}, {}", reg(rt), literal(address, imm as u32, add)),
+            LdrbReg { rn, rt, rm, shift: sh } => write!(f, "ldrb.w {}, {}", reg(rt), memory_reg(rn, rm, sh)),
+            Ldrbt { rn, rt, imm } => write!(f, "ldrbt {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            LdrhImm { rn, rt, imm, index, add, wback } => write!(f, "ldrh.w {}, {}", reg(rt), memory(rn, imm as u32, index, add, wback)),
+            LdrhLit { rt, imm, add } => write!(f, "ldrh.w {}, {}", reg(rt), literal(address, imm as u32, add)),
+            LdrhReg { rn, rt, rm, shift: sh } => write!(f, "ldrh.w {}, {}", reg(rt), memory_reg(rn, rm, sh)),
+            Ldrht { rn, rt, imm } => write!(f, "ldrht {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            LdrsbImm { rn, rt, imm, index, add, wback } => write!(f, "ldrsb.w {}, {}", reg(rt), memory(rn, imm as u32, index, add, wback)),
+            LdrsbLit { rt, imm, add } => write!(f, "ldrsb.w {}, {}", reg(rt), literal(address, imm as u32, add)),
+            LdrsbReg { rn, rt, rm, shift: sh } => write!(f, "ldrsb.w {}, {}", reg(rt), memory_reg(rn, rm, sh)),
+            Ldrsbt { rn, rt, imm } => write!(f, "ldrsbt {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            LdrshImm { rn, rt, imm, index, add, wback } => write!(f, "ldrsh.w {}, {}", reg(rt), memory(rn, imm as u32, index, add, wback)),
+            LdrshLit { rt, imm, add } => write!(f, "ldrsh.w {}, {}", reg(rt), literal(address, imm as u32, add)),
+            LdrshReg { rn, rt, rm, shift: sh } => write!(f, "ldrsh.w {}, {}", reg(rt), memory_reg(rn, rm, sh)),
+            Ldrsht { rn, rt, imm } => write!(f, "ldrsht {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            PldImm { rn, imm, add } => write!(f, "pld {}", memory(rn, imm as u32, true, add, false)),
+            PldLit { imm, add } => write!(f, "pld {}", literal(address, imm as u32, add)),
+            PldReg { rn, rm, shift: sh } => write!(f, "pld {}", memory_reg(rn, rm, sh)),
+            PliImm { rn, imm, add } => write!(f, "pli {}", memory(rn, imm as u32, true, add, false)),
+            PliLit { imm, add } => write!(f, "pli {}", literal(address, imm as u32, add)),
+            PliReg { rn, rm, shift: sh } => write!(f, "pli {}", memory_reg(rn, rm, sh)),
+            StrImm { rn, rt, imm, index, add, wback } => write!(f, "str.w {}, {}", reg(rt), memory(rn, imm as u32, index, add, wback)),
+            StrReg { rn, rt, rm, shift: sh } => write!(f, "str.w {}, {}", reg(rt), memory_reg(rn, rm, sh)),
+            Strt { rn, rt, imm } => write!(f, "strt {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            StrbImm { rn, rt, imm, index, add, wback } => write!(f, "strb.w {}, {}", reg(rt), memory(rn, imm as u32, index, add, wback)),
+            StrbReg { rn, rt, rm, shift: sh } => write!(f, "strb.w {}, {}", reg(rt), memory_reg(rn, rm, sh)),
+            Strbt { rn, rt, imm } => write!(f, "strbt {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+            StrhImm { rn, rt, imm, index, add, wback } => write!(f, "strh.w {}, {}", reg(rt), memory(rn, imm as u32, index, add, wback)),
+            StrhReg { rn, rt, rm, shift: sh } => write!(f, "strh.w {}, {}", reg(rt), memory_reg(rn, rm, sh)),
+            Strht { rn, rt, imm } => write!(f, "strht {}, {}", reg(rt), memory(rn, imm as u32, true, true, false)),
+
+            Cdp { coproc, opc1, crd, crn, crm, opc2 } => write!(f, "cdp p{}, #{}, c{}, c{}, c{}, #{}", coproc, opc1, crd, crn, crm, opc2),
+            Cdp2 { coproc, opc1, crd, crn, crm, opc2 } => write!(f, "cdp2 p{}, #{}, c{}, c{}, c{}, #{}", coproc, opc1, crd, crn, crm, opc2),
+            LdcImm { coproc, rn, crd, imm, index, add, wback, long } => write!(f, "ldc{} p{}, c{}, {}", l(long), coproc, crd, coprocessor_memory(rn, imm, index, add, wback)),
+            Ldc2Imm { coproc, rn, crd, imm, index, add, wback, long } => write!(f, "ldc2{} p{}, c{}, {}", l(long), coproc, crd, coprocessor_memory(rn, imm, index, add, wback)),
+            LdcLit { coproc, crd, imm, index, add, long } => write!(f, "ldc{} p{}, c{}, {}", l(long), coproc, crd, coprocessor_memory(15, imm, index, add, false)),
+            Ldc2Lit { coproc, crd, imm, index, add, long } => write!(f, "ldc2{} p{}, c{}, {}", l(long), coproc, crd, coprocessor_memory(15, imm, index, add, false)),
+            Mcr { coproc, opc1, rt, crn, crm, opc2 } => write!(f, "mcr p{}, #{}, {}, c{}, c{}, #{}", coproc, opc1, reg(rt), crn, crm, opc2),
+            Mcr2 { coproc, opc1, rt, crn, crm, opc2 } => write!(f, "mcr2 p{}, #{}, {}, c{}, c{}, #{}", coproc, opc1, reg(rt), crn, crm, opc2),
+            Mcrr { coproc, opc1, rt, rt2, crm } => write!(f, "mcrr p{}, #{}, {}, {}, c{}", coproc, opc1, reg(rt), reg(rt2), crm),
+            Mcrr2 { coproc, opc1, rt, rt2, crm } => write!(f, "mcrr2 p{}, #{}, {}, {}, c{}", coproc, opc1, reg(rt), reg(rt2), crm),
+            Mrc { coproc, opc1, rt, crn, crm, opc2 } => write!(f, "mrc p{}, #{}, {}, c{}, c{}, #{}", coproc, opc1, reg(rt), crn, crm, opc2),
+            Mrc2 { coproc, opc1, rt, crn, crm, opc2 } => write!(f, "mrc2 p{}, #{}, {}, c{}, c{}, #{}", coproc, opc1, reg(rt), crn, crm, opc2),
+            Mrrc { coproc, opc1, rt, rt2, crm } => write!(f, "mrrc p{}, #{}, {}, {}, c{}", coproc, opc1, reg(rt), reg(rt2), crm),
+            Mrrc2 { coproc, opc1, rt, rt2, crm } => write!(f, "mrrc2 p{}, #{}, {}, {}, c{}", coproc, opc1, reg(rt), reg(rt2), crm),
+            Stc { coproc, rn, crd, imm, index, add, wback, long } => write!(f, "stc{} p{}, c{}, {}", l(long), coproc, crd, coprocessor_memory(rn, imm, index, add, wback)),
+            Stc2 { coproc, rn, crd, imm, index, add, wback, long } => write!(f, "stc2{} p{}, c{}, {}", l(long), coproc, crd, coprocessor_memory(rn, imm, index, add, wback)),
+
+            Undefined => write!(f, "undefined"),
+        }
+    }
+}
+
+fn rotate(rotation: u8) -> String {
+    match rotation {
+        0 => String::new(),
+        n => format!(", ror #{}", n),
+    }
+}
+
+/// Renders the LDC and STC addressing modes, where the unindexed form passes an 8 bit option to the coprocessor
+fn coprocessor_memory(rn: u8, imm: u16, index: bool, add: bool, wback: bool) -> String {
+    match (index, wback) {
+        (false, false) => format!("[{}], {{{}}}", reg(rn), imm >> 2),
+        _ => memory(rn, imm as u32, index, add, wback),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn disassemble_thumb16() {
+        let table = InstrThumb16::generate_decode_table();
+
+        // Halfwords and their addresses paired with the expected text, as checked against the assembler
+        let expected = [
+            (0x1CC8, 0x0000, "adds r0, r1, #3"),
+            (0xD000, 0x0002, "beq.n 0x6"),
+            (0xB5F0, 0x0004, "push {r4-r7, lr}"),
+            (0x4802, 0x0006, "ldr r0, [pc, #8] @ 0x10"),
+            (0xA804, 0x0008, "add r0, sp, #16"),
+            (0xB119, 0x000A, "cbz r1, 0x14"),
+            (0xBF06, 0x000C, "itte eq"),
+            (0x4608, 0x000E, "mov r0, r1"),
+            (0xB672, 0x0014, "cpsid i"),
+            (0xD2F6, 0x0016, "bhs.n 0x6"),
+            (0xA001, 0x0038, "adr r0, #4 @ 0x40"),
+            (0x0008, 0x003A, "movs r0, r1"),
+            (0xBF00, 0x0040, "nop"),
+            (0xE7FE, 0x11000, "b.n 0x11000"),
+            (0xBD10, 0x0000, "pop {r4, pc}"),
+            (0xC90C, 0x0000, "ldm r1!, {r2, r3}"),
+            (0xC90E, 0x0000, "ldm r1, {r1-r3}"),
+            (0x6A61, 0x0000, "ldr r1, [r4, #36]"),
+            (0x4770, 0x0000, "bx lr"),
+            (0xDF2A, 0x0000, "svc #42"),
+        ];
+
+        for (encoded, address, text) in expected.iter() {
+            assert_eq!(table[*encoded as usize].disassemble(*address).to_string(), *text, "disassembling {:#06X}", encoded);
+        }
+
+        assert_eq!(table[0xD000].to_string(), "beq.n .+4");
+        assert_eq!(table[0xE7FE].to_string(), "b.n .+0");
+    }
+
+    #[test]
+    fn disassemble_thumb32() {
+        let expected = [
+            (0xF002, 0x11FF, 0x18, "and r1, r2, #16711935"),
+            (0xF04F, 0x5090, 0x00, "mov.w r0, #301989888"),
+            (0xF383, 0x8811, 0x1C, "msr basepri, r3"),
+            (0xF3EF, 0x8210, 0x20, "mrs r2, primask"),
+            (0xE96D, 0x2302, 0x24, "strd r2, r3, [sp, #-8]!"),
+            (0xF852, 0x1B04, 0x28, "ldr.w r1, [r2], #4"),
+            (0xE8D2, 0xF011, 0x2C, "tbh [r2, r1, lsl #1]"),
+            (0xFCC2, 0x1307, 0x30, "stc2l p3, c1, [r2], {7}"),
+            (0xF3BF, 0x8F5F, 0x34, "dmb sy"),
+            (0xF7FF, 0xFFE3, 0x3C, "bl 0x6"),
+            (0xE92D, 0x4FF0, 0x00, "push.w {r4-r11, lr}"),
+            (0xEB02, 0x0183, 0x00, "add.w r1, r2, r3, lsl #2"),
+            (0xF85F, 0x1008, 0x02, "ldr.w r1, [pc, #-8] @ 0xfffffffc"),
+        ];
+
+        for (hw1, hw2, address, text) in expected.iter() {
+            let decoded = InstrThumb32::decode(*hw1, *hw2);
+            assert_eq!(decoded.disassemble(*address).to_string(), *text, "disassembling {:#06X} {:#06X}", hw1, hw2);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Load/Store Single Data Item Instruction Definitions
+//!
+//!
+//! The instructions defined in this module are listed in section A5.2.4 of the ARMv7-M Architecture Reference Manual:
+//!
+//!   opA   opB   Instruction
+//!   0101  000   Store Register                    STR (register)
+//!   0101  001   Store Register Halfword           STRH (register)
+//!   0101  010   Store Register Byte               STRB (register)
+//!   0101  011   Load Register Signed Byte         LDRSB (register)
+//!   0101  100   Load Register                     LDR (register)
+//!   0101  101   Load Register Halfword            LDRH (register)
+//!   0101  110   Load Register Byte                LDRB (register)
+//!   0101  111   Load Register Signed Halfword     LDRSH (register)
+//!   0110  0xx   Store Register                    STR (immediate)
+//!   0110  1xx   Load Register                     LDR (immediate)
+//!   0111  0xx   Store Register Byte               STRB (immediate)
+//!   0111  1xx   Load Register Byte                LDRB (immediate)
+//!   1000  0xx   Store Register Halfword           STRH (immediate)
+//!   1000  1xx   Load Register Halfword            LDRH (immediate)
+//!   1001  0xx   Store Register SP relative        STR (immediate)
+//!   1001  1xx   Load Register SP relative         LDR (immediate)
+//!
+//! Along with the literal load, LDR (literal), whose base is the word aligned PC.
+//!
+//! The multiple register transfers are executed here as well, LDM and STM from section A6.7 and the PUSH and POP of
+//! the miscellaneous instructions in section A5.2.5. These transfer the registers of their list in ascending order
+//! to or from consecutive words of memory.
+//!
+//! Load/store single data item encoding:
+//! =================================================
+//! |15 14 13 12|11 10 09|08 07 06 05 04 03 02 01 00|
+//! |opA        |opB     |                          |
+//! =================================================
+
+use crate::instructions::InstrThumb16;
+
+/// The size of a single data item
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataSize {
+    Byte,
+    Halfword,
+    Word,
+}
+
+impl DataSize {
+    /// The number of bytes in an item of this size
+    pub fn bytes(self) -> usize {
+        match self {
+            DataSize::Byte => 1,
+            DataSize::Halfword => 2,
+            DataSize::Word => 4,
+        }
+    }
+}
+
+/// Whether a transfer reads from or writes to memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Loads into the transfer register, sign extending the item when `signed`, or zero extending it otherwise
+    Load { signed: bool },
+    Store,
+}
+
+/// A single data item transfer between a register and memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transfer {
+    pub direction: Direction,
+    pub size: DataSize,
+    pub rt: u8,
+    pub address: u32,
+}
+
+impl Transfer {
+    /// Extends a loaded item to the value written to the transfer register
+    pub fn extend(&self, item: u32) -> u32 {
+        let signed = self.direction == Direction::Load { signed: true };
+
+        match self.size {
+            DataSize::Byte if signed => item as u8 as i8 as u32,
+            DataSize::Byte => item as u8 as u32,
+            DataSize::Halfword if signed => item as u16 as i16 as u32,
+            DataSize::Halfword => item as u16 as u32,
+            DataSize::Word => item,
+        }
+    }
+}
+
+/// Computes the transfer performed by a 16 bit load/store single data item instruction
+///
+/// Registers are read through `r`, where the PC reads as the address of the instruction plus 4. Returns `None` for
+/// instructions which are not part of the load/store single group.
+pub fn load_store_single(instr: InstrThumb16, r: impl Fn(u8) -> u32) -> Option<Transfer> {
+    use InstrThumb16::*;
+    use DataSize::*;
+
+    let load = Direction::Load { signed: false };
+    let load_signed = Direction::Load { signed: true };
+    let store = Direction::Store;
+
+    let offset = |rn: u8, imm: u32| r(rn).wrapping_add(imm);
+
+    let (direction, size, rt, address) = match instr {
+        LdrImm { imm, rn, rt } => (load, Word, rt, offset(rn, imm as u32)),
+        LdrbImm { imm, rn, rt } => (load, Byte, rt, offset(rn, imm as u32)),
+        LdrhImm { imm, rn, rt } => (load, Halfword, rt, offset(rn, imm as u32)),
+        LdrSpImm { rt, imm } => (load, Word, rt, offset(13, imm as u32)),
+        LdrLit { rt, imm } => (load, Word, rt, (r(15) & !0b11).wrapping_add(imm as u32)),
+
+        LdrReg { rm, rn, rt } => (load, Word, rt, offset(rn, r(rm))),
+        LdrbReg { rm, rn, rt } => (load, Byte, rt, offset(rn, r(rm))),
+        LdrhReg { rm, rn, rt } => (load, Halfword, rt, offset(rn, r(rm))),
+        LdrSbReg { rm, rn, rt } => (load_signed, Byte, rt, offset(rn, r(rm))),
+        LdrShReg { rm, rn, rt } => (load_signed, Halfword, rt, offset(rn, r(rm))),
+
+        StrImm { imm, rn, rt } => (store, Word, rt, offset(rn, imm as u32)),
+        StrbImm { imm, rn, rt } => (store, Byte, rt, offset(rn, imm as u32)),
+        StrhImm { imm, rn, rt } => (store, Halfword, rt, offset(rn, imm as u32)),
+        StrSpImm { rt, imm } => (store, Word, rt, offset(13, imm as u32)),
+
+        StrReg { rm, rn, rt } => (store, Word, rt, offset(rn, r(rm))),
+        StrbReg { rm, rn, rt } => (store, Byte, rt, offset(rn, r(rm))),
+        StrhReg { rm, rn, rt } => (store, Halfword, rt, offset(rn, r(rm))),
+
+        _ => return None,
+    };
+
+    Some(Transfer { direction, size, rt, address })
+}
+
+/// A transfer of a list of registers between the registers and consecutive words of memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultipleTransfer {
+    pub load: bool,
+
+    /// The registers to transfer, bit `n` set for register `n`
+    pub list: u16,
+
+    /// The address of the lowest word, which is transferred to or from the lowest numbered register
+    pub address: u32,
+
+    /// The base register and the value written back to it after the transfer
+    pub writeback: Option<(u8, u32)>,
+}
+
+/// Computes the transfer performed by a 16 bit load/store multiple instruction
+///
+/// Returns `None` for instructions which are not part of the load/store multiple group.
+pub fn load_store_multiple(instr: InstrThumb16, r: impl Fn(u8) -> u32) -> Option<MultipleTransfer> {
+    use InstrThumb16::*;
+
+    let size = |list: u16| 4 * list.count_ones();
+
+    let transfer = match instr {
+        // The base register is written back only if it is not also loaded
+        Ldm { list, rn } => {
+            let list = list as u16;
+            let address = r(rn);
+            let writeback = if list & (1 << rn) == 0 { Some((rn, address.wrapping_add(size(list)))) } else { None };
+            MultipleTransfer { load: true, list, address, writeback }
+        },
+
+        Stm { rn, list } => {
+            let list = list as u16;
+            let address = r(rn);
+            MultipleTransfer { load: false, list, address, writeback: Some((rn, address.wrapping_add(size(list)))) }
+        },
+
+        Pop { list } => {
+            let address = r(13);
+            MultipleTransfer { load: true, list, address, writeback: Some((13, address.wrapping_add(size(list)))) }
+        },
+
+        Push { list } => {
+            let address = r(13).wrapping_sub(size(list));
+            MultipleTransfer { load: false, list, address, writeback: Some((13, address)) }
+        },
+
+        _ => return None,
+    };
+
+    Some(transfer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn load_store_single_transfers() {
+        use InstrThumb16::*;
+
+        let r = |n: u8| match n {
+            13 => 0x2000_0100,
+            15 => 0x0000_1006,
+            n => n as u32 * 0x10,
+        };
+        let transfer = |instr| load_store_single(instr, r).unwrap();
+
+        let t = transfer(LdrImm { imm: 124, rn: 1, rt: 0 });
+        assert_eq!(t, Transfer { direction: Direction::Load { signed: false }, size: DataSize::Word, rt: 0, address: 0x8C });
+
+        let t = transfer(LdrLit { rt: 2, imm: 8 });
+        assert_eq!(t.address, 0x1004 + 8, "literal loads are relative to Align(PC, 4)");
+
+        let t = transfer(LdrSpImm { rt: 3, imm: 1020 });
+        assert_eq!(t.address, 0x2000_0100 + 1020);
+
+        let t = transfer(StrSpImm { rt: 3, imm: 4 });
+        assert_eq!((t.direction, t.address), (Direction::Store, 0x2000_0104));
+
+        let t = transfer(LdrSbReg { rm: 2, rn: 1, rt: 0 });
+        assert_eq!((t.size, t.address), (DataSize::Byte, 0x30));
+        assert_eq!(t.extend(0x80), 0xFFFF_FF80);
+
+        let t = transfer(LdrShReg { rm: 2, rn: 1, rt: 0 });
+        assert_eq!(t.extend(0x8001), 0xFFFF_8001);
+
+        let t = transfer(LdrhImm { imm: 62, rn: 4, rt: 0 });
+        assert_eq!((t.size, t.address), (DataSize::Halfword, 0x7E));
+        assert_eq!(t.extend(0x8001), 0x8001);
+
+        let t = transfer(StrbReg { rm: 7, rn: 6, rt: 5 });
+        assert_eq!(t, Transfer { direction: Direction::Store, size: DataSize::Byte, rt: 5, address: 0xD0 });
+
+        assert_eq!(load_store_single(Nop, r), None);
+    }
+
+    #[test]
+    fn load_store_multiple_transfers() {
+        use InstrThumb16::*;
+
+        let r = |n: u8| match n {
+            13 => 0x2000_0100,
+            n => n as u32 * 0x10,
+        };
+        let transfer = |instr| load_store_multiple(instr, r).unwrap();
+
+        let t = transfer(Ldm { list: 0b0000_0110, rn: 3 });
+        assert_eq!(t, MultipleTransfer { load: true, list: 0b0110, address: 0x30, writeback: Some((3, 0x38)) });
+
+        let t = transfer(Ldm { list: 0b0000_1110, rn: 3 });
+        assert_eq!(t.writeback, None, "a loaded base register is not written back");
+
+        let t = transfer(Stm { rn: 1, list: 0b0000_0011 });
+        assert_eq!(t, MultipleTransfer { load: false, list: 0b0011, address: 0x10, writeback: Some((1, 0x18)) });
+
+        let t = transfer(Push { list: 0x4030 });
+        assert_eq!(t, MultipleTransfer { load: false, list: 0x4030, address: 0x2000_00F4, writeback: Some((13, 0x2000_00F4)) });
+
+        let t = transfer(Pop { list: 0x8030 });
+        assert_eq!(t, MultipleTransfer { load: true, list: 0x8030, address: 0x2000_0100, writeback: Some((13, 0x2000_010C)) });
+
+        assert_eq!(load_store_multiple(Nop, r), None);
+    }
+}
@@ -1,37 +1,52 @@
 //! Definition of 16 bit thumb instructions
 
+mod condition;
 mod data_processing;
+mod disassemble;
+mod load_store;
+mod thumb32;
+mod unpredictable;
 
-pub const NUM_TH16_INSTRUCTIONS: usize = (::std::u16::MAX as usize) + 1;
+pub use condition::{ ConditionFlags, ItState };
+pub use data_processing::{ Outcome, add_with_carry, shift_c, execute_data_processing };
+pub use disassemble::Disassembly;
+pub use load_store::{ DataSize, Direction, Transfer, MultipleTransfer, load_store_single, load_store_multiple };
+pub use thumb32::{ InstrThumb32, ImmShift, ShiftType, thumb_expand_imm };
+pub use unpredictable::{ ItPosition, Unpredictable };
+
+use crate::decode::{ InstrDesc, Encoding, InstrInfo };
+
+pub const NUM_TH16_INSTRUCTIONS: usize = (u16::MAX as usize) + 1;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum InstrThumb16 {
     AdcReg { rm: u8, rdn: u8 },
     AddImm { imm: u8, rdn: u8, rd: u8 },
     AddReg { rm: u8, rdn: u8, rd: u8 },
-    AddSpImm { imm: u8, rd: u8},
-    AddSpReg { rdm: u8 },
-    Adr { rd: u8, imm: u8 },
+    AddRegHigh { rm: u8, rdn: u8 },
+    AddSpImm { imm: u16, rd: u8 },
+    AddSpReg { rm: u8, rd: u8 },
+    Adr { rd: u8, imm: u16 },
     AndReg { rm: u8, rdn: u8 },
     AsrImm { imm: u8, rm: u8, rd: u8 },
     AsrReg { rm: u8, rdn: u8 },
-    BranchE1 { cond: u8, imm: i8  },
+    BranchE1 { cond: u8, imm: i16 },
     BranchE2 { imm: i16 },
     BicReg { rm: u8, rdn: u8 },
     Breakpoint { imm: u8 },
     BranchLx { rm: u8 },
     BranchX { rm: u8 },
-    Cbnz { imm1: u8, imm5: u8, rn: u8 },
-    Cbz { imm1: u8, imm5: u8, rn: u8 },
+    Cbnz { imm: u8, rn: u8 },
+    Cbz { imm: u8, rn: u8 },
     CmnReg { rm: u8, rn: u8 },
     CmpImm { rn: u8, imm: u8 },
-    CmpReg { rm: u8, rn: u8, n: u8 },
+    CmpReg { rm: u8, rn: u8 },
     Cps { imm: u8, fi: u8, ff: u8 },
     EorReg { rm: u8, rdn: u8 },
     IfThen { cond: u8, mask: u8 },
     Ldm { list: u8, rn: u8 },
     LdrImm { imm: u8, rn: u8, rt: u8 },
-    LdrLit { rt: u8, imm: u8 },
+    LdrLit { rt: u8, imm: u16 },
     LdrReg { rm: u8, rn: u8, rt: u8 },
     LdrbImm { imm: u8, rn: u8, rt: u8 },
     LdrbReg { rm: u8, rn: u8, rt: u8 },
@@ -39,18 +54,19 @@ pub enum InstrThumb16 {
     LdrhReg { rm: u8, rn: u8, rt: u8 },
     LdrSbReg { rm: u8, rn: u8, rt: u8 },
     LdrShReg { rm: u8, rn: u8, rt: u8 },
+    LdrSpImm { rt: u8, imm: u16 },
     LslImm { imm: u8, rm: u8, rd: u8 },
     LslReg { rm: u8, rdn: u8 },
     LsrImm { imm: u8, rm: u8, rd: u8 },
     LsrReg { rm: u8, rdn: u8 },
     MovImm { rd: u8, imm: u8 },
-    MovReg { rm: u8, rd: u8, d: u8 },
+    MovReg { rm: u8, rd: u8 },
     Mul { rn: u8, rdm: u8 },
     MvnReg { rm: u8, rd: u8 },
     Nop,
     OrrReg { rm: u8, rdn: u8 },
-    Pop { p: u8, list: u8 },
-    Push { m: u8, list: u8 },
+    Pop { list: u16 },
+    Push { list: u16 },
     Rev { rm: u8, rd: u8 },
     Rev16 { rm: u8, rd: u8 },
     RevSh { rm: u8, rd: u8 },
@@ -65,10 +81,11 @@ pub enum InstrThumb16 {
     StrbReg { rm: u8, rn: u8, rt: u8 },
     StrhImm { imm: u8, rn: u8, rt: u8 },
     StrhReg { rm: u8, rn: u8, rt: u8 },
+    StrSpImm { rt: u8, imm: u16 },
     SubImm { imm: u8, rn: u8, rdn: u8 },
     SubReg { rm: u8, rn: u8, rd: u8 },
-    SubSpImm { imm: u8 },
-    Svc,
+    SubSpImm { imm: u16 },
+    Svc { imm: u8 },
     Sxtb { rm: u8, rd: u8 },
     Sxth { rm: u8, rd: u8 },
     TstReg { rm: u8, rn: u8 },
@@ -89,1179 +106,1529 @@ pub enum InstrThumb16 {
     UnknownInstruction
 }
 
-#[allow(dead_code, unreachable_code)]
-fn _assert_instrthumb16_size() { unsafe { std::mem::transmute::<InstrThumb16, [u8; 4]>(return); } }
+const _ASSERT_INSTRTHUMB16_SIZE: [u8; 4] = [0; std::mem::size_of::<InstrThumb16>()];
 
 impl InstrThumb16 {
+    /// Builds the complete 16 bit decode table from the instruction descriptions
+    /// 
+    /// Every permutation of every encoding is written into the table at the index of its halfword. Where encodings
+    /// overlap, the encoding with more invariant bits is the more specific one and takes precedence. This is how the
+    /// hints override IT, UDF and SVC override the conditional branch, and the SP forms override the high register add.
     pub fn generate_decode_table() -> [InstrThumb16; NUM_TH16_INSTRUCTIONS] {
-        assert!(::std::mem::size_of::<InstrThumb16>() < 4);
-
-        unimplemented!()
-
-        /*
-        define_instructions! {
-
-            // todo: AdcImm (thumb2 only)
-
-            instruction! {
-                name: AdcReg,
-                encoding: [
-                    base: 0x4140,
-                    operand: [rm, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: AddImm,
-                encoding: [
-                    base: 0x1C00,
-                    operand: [rd, 3 << 0],
-                    operand: [rdn, 3 << 3],
-                    operand: [imm, 3 << 6]
-                ],
-                encoding: [
-                    base: 0x3000,
-                    operand: [imm, 8 << 0],
-                    operand: [rdn, 3 << 8],
-                    operand: [rd, unused]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: AddReg,
-                encoding: [
-                    base: 0x1800,
-                    operand: [rm, 3 << 6],
-                    operand: [rdn, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ],
-                encoding: [
-                    base: 0x4400,
-                    operand: [rm, 4 << 3],
-                    operand: [rdn, 3 << 0],
-                    operand: [rd, 1 << 7]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: AddSpImm,
-                encoding: [
-                    base: 0xA800,
-                    operand: [rd, 3 << 8],
-                    operand: [imm, 8 << 0]
-                ],
-                encoding: [
-                    base: 0xB000,
-                    operand: [imm, 7 << 0],
-                    operand: [rd, unused]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: AddSpReg,
-                encoding: [
-                    base: 0x4468,
-                    operand: [rdm, 3 << 0]
-                ],
-                encoding: [
-                    base: 0x4485,
-                    operand: [rdm, 4 << 3]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: Adr,
-                encoding: [
-                    base: 0xA000,
-                    operand: [rd, 3 << 8],
-                    operand: [imm, 8 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: AndImm (thumb2 only)
-
-            instruction! {
-                name: AndReg,
-                encoding: [
-                    base: 0x4000,
-                    operand: [rm, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: AsrImm,
-                encoding: [
-                    base: 0x1000,
-                    operand: [imm, 5 << 6],
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: AsrReg,
-                encoding: [
-                    base: 0x4100,
-                    operand: [rm, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ]
-            },
-
-            instruction! {
-                name: BranchE1,
-                encoding: [
-                    base: 0xD000,
-                    operand: [cond, 4 << 8],
-                    operand: [imm, 8 << 0]
-                ]
-            },
-            
-            instruction! {
-                name: BranchE2,
-                encoding: [
-                    base: 0xE000,
-                    operand: [imm, 11 << 0, signed],
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Bfc (thumb2 only)
-
-            // todo: Bfi (thumb2 only)
-
-            // todo: BicImm (thumb2 only)
-
-            instruction! {
-                name: BicReg,
-                encoding: [
-                    base: 0x4380,
-                    operand: [rm, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: Breakpoint,
-                encoding: [
-                    base: 0xBE00,
-                    operand: [imm, 8 << 0]
-                ]
-            },
-
-            // todo: BranchL (thumb2 only)
-
-            instruction! {
-                name: BranchLx,
-                encoding: [
-                    base: 0x4780,
-                    operand: [rm, 4 << 3]
-                ]
-            },
-
-            instruction! {
-                name: BranchX,
-                encoding: [
-                    base: 0x4700,
-                    operand: [rm, 4 << 3]
-                ]
-            },
-
-            instruction! {
-                name: Cbnz,
-                encoding: [
-                    base: 0xB900,
-                    operand: [imm1, 1 << 9],
-                    operand: [imm5, 5 << 3],
-                    operand: [rn, 3 << 0]
-                ]
-            },
-
-            instruction! {
-                name: Cbz,
-                encoding: [
-                    base: 0xB100,
-                    operand: [imm1, 1 << 9],
-                    operand: [imm5, 5 << 3],
-                    operand: [rn, 3 << 0]
-                ]
-            },
-
-            // todo: Cdp (thumb2 only)
-
-            // todo: Cdp2 (thumb2 only)
-
-            // todo: ClrEx (thumb2 only)
-
-            // todo: Clz (thumb2 only)
-
-            // todo: CmnImm (thumb2 only)
-
-            instruction! {
-                name: CmnReg,
-                encoding: [
-                    base: 0x42C0,
-                    operand: [rm, 3 << 3],
-                    operand: [rn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: CmpImm,
-                encoding: [
-                    base: 0x2800,
-                    operand: [rn, 3 << 8],
-                    operand: [imm, 8 << 0]
-                ]
-            },
-
-            instruction! {
-                name: CmpReg,
-                encoding: [
-                    base: 0x4280,
-                    operand: [rm, 3 << 3],
-                    operand: [rn, 3 << 0],
-                    operand: [n, unused]
-                ],
-                encoding: [
-                    base: 0x2280,
-                    operand: [n, 1 << 7],
-                    operand: [rm, 4 << 3],
-                    operand: [rn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: Cps,
-                encoding: [
-                    base: 0x5B30,
-                    operand: [imm, 1 << 4],
-                    operand: [fi, 1 << 1],
-                    operand: [ff, 1 << 0]
-                ]
-            },
-
-            // Cpy == Mov
-
-            // todo: Csdb (thumb2 only)
-            
-            // todo: Dbg (thumb2 only)
-            
-            // todo: Dmb (thumb2 only)
-
-            // todo: Dsb (thumb2 only)
-
-            // todo: EorImm (thumb2 only)
-
-            instruction! {
-                name: EorReg,
-                encoding: [
-                    base: 0x4040,
-                    operand: [rm, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Isb (thumb2 only)
-
-            instruction! {
-                name: IfThen,
-                encoding: [
-                    base: 0xBF00,
-                    operand: [cond, 4 << 4],
-                    operand: [mask, 4 << 0]
-                ]
-            },
-
-            // todo: LdcImm (thumb2 only)
-            
-            // todo: Ldc2Imm (thumb2 only)
-
-            // todo: LdcLit (thumb2 only)
-            
-            // todo: Ldc2Lit (thumb2 only)
-
-            instruction! {
-                name: Ldm, // Ldmia, Ldmfd
-                encoding: [
-                    base: 0xC800,
-                    operand: [list, 8 << 0],
-                    operand: [rn, 3 << 8]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Ldmdb (Ldmea) (thumb2 only)
-
-            instruction! {
-                name: LdrImm,
-                encoding: [
-                    base: 0x6800,
-                    operand: [imm, 5 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ],
-                encoding: [
-                    base: 0x9800,
-                    operand: [rt, 3 << 8],
-                    operand: [imm, 8 << 0],
-                    operand: [rn, unused]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: LdrLit,
-                encoding: [
-                    base: 0x4800,
-                    operand: [rt, 3 << 8],
-                    operand: [imm, 8 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: LdrReg,
-                encoding: [
-                    base: 0x5800,
-                    operand: [rm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: LdrbImm,
-                encoding: [
-                    base: 0x7800,
-                    operand: [imm, 5 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: LdrbLit (thumb2 only)
-
-            instruction! {
-                name: LdrbReg,
-                encoding: [
-                    base: 0x5C00,
-                    operand: [rm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Ldrbt (thumb2 only)
-
-            // todo: LdrdImm (thumb2 only)
-
-            // todo: LdrdLit (thumb2 only)
-
-            // todo: LdrEx (thumb2 only)
-
-            // todo: LdrExB (thumb2 only)
-
-            // todo: LdrExH (thumb2 only)
-
-            instruction! {
-                name: LdrhImm,
-                encoding: [
-                    base: 0x8800,
-                    operand: [imm, 5 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: LdrhLit (thumb2 only)
-
-            instruction! {
-                name: LdrhReg,
-                encoding: [
-                    base: 0x5A00,
-                    operand: [rm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-                
-                // todo: thumb2 encodings
-            },
-
-            // todo: Ldrht (thumb2 only)
-            
-            // todo: LdrsbImm (thumb2 only)
-
-            // todo: LdrsbLit (thumb2 only)
-            
-            instruction! {
-                name: LdrSbReg,
-                encoding: [
-                    base: 0x5600,
-                    operand: [rm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Ldrsbt (thumb2 only)
-
-            // todo: LdrshImm (thumb2 only)
-
-            // todo: LdrshLit (thumb2 only)
-
-            instruction! {
-                name: LdrShReg,
-                encoding: [
-                    base: 0x5E00,
-                    operand: [rm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Ldrsht (thumb2 only)
-
-            // todo: Ldrt (thumb2 only)
-
-            instruction! {
-                name: LslImm,
-                encoding: [
-                    base: 0x0000,
-                    operand: [imm, 5 << 6],
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: LslReg,
-                encoding: [
-                    base: 0x4080,
-                    operand: [rm, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: LsrImm,
-                encoding: [
-                    base: 0x0800,
-                    operand: [imm, 5 << 6],
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: LsrReg,
-                encoding: [
-                    base: 0x40C0,
-                    operand: [rm, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Mcr (thumb2 only)
-
-            // todo: Mcr2 (thumb2 only)
-
-            // todo: Mcrr (thumb2 only)
-            
-            // todo: Mcrr2 (thumb2 only)
-
-            // todo: Mla (thumb2 only)
-
-            // todo: Mls (thumb2 only)
-
-            instruction! {
-                name: MovImm,
-                encoding: [
-                    base: 0x2000,
-                    operand: [rd, 3 << 8],
-                    operand: [imm, 8 << 0]
-                ]
-                
-                // todo: thumb2 encodings
-            },
+        let descriptions = instruction_descriptions();
+
+        let mut encodings: Vec<&Encoding> = descriptions.iter().flat_map(|desc| desc.encodings()).collect();
+        encodings.sort_by_key(|enc| enc.invariant_mask().count_ones());
+
+        let mut table = [InstrThumb16::Undefined; NUM_TH16_INSTRUCTIONS];
+        for encoding in encodings {
+            for pair in encoding.generate_decode_table() {
+                table[pair.encoded() as usize] = pair.decoded();
+            }
+        }
+
+        table
+    }
+}
+
+/// Encodes 16 bit instructions back into halfwords using the same instruction descriptions as the decode table
+pub struct Encoder {
+    encodings: Vec<Encoding>,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder { encodings: specific_encodings() }
+    }
+
+    /// Encodes an instruction, or returns `None` if none of its encodings can express it
+    pub fn encode(&self, instr: InstrThumb16) -> Option<u16> {
+        self.encodings.iter().find_map(|enc| enc.encode(&instr))
+    }
+}
+
+/// Answers semantic queries about decoded 16 bit instructions using the metadata of the instruction descriptions
+pub struct Analyzer {
+    encodings: Vec<Encoding>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Analyzer {
+    pub fn new() -> Analyzer {
+        Analyzer { encodings: specific_encodings() }
+    }
+
+    /// Describes an instruction, or returns `None` if it has no encoding, such as `Undefined`
+    pub fn info(&self, instr: InstrThumb16) -> Option<InstrInfo> {
+        self.encodings.iter()
+            .find(|enc| enc.encode(&instr).is_some())
+            .and_then(|enc| enc.info(&instr))
+    }
+}
+
+/// All of the encodings, most specific first, mirroring the precedence of the decode table
+fn specific_encodings() -> Vec<Encoding> {
+    let mut encodings: Vec<Encoding> = instruction_descriptions()
+        .into_iter()
+        .flat_map(|desc| desc.into_encodings())
+        .collect();
+
+    encodings.sort_by_key(|enc| std::cmp::Reverse(enc.invariant_mask().count_ones()));
+    encodings
+}
+
+fn instruction_descriptions() -> Vec<InstrDesc> {
+    define_instructions! {
+        // todo: AdcImm (thumb2 only)
+
+        instruction! {
+            name: AdcReg,
+            flags: NZCV,
+            encoding: [
+                base: 0x4140,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: AddImm,
+            flags: NZCV,
+            encoding: [
+                base: 0x1C00,
+                operand: [rd, 3 << 0, write],
+                operand: [rdn, 3 << 3, read],
+                operand: [imm, 3 << 6]
+            ],
+            encoding: [
+                base: 0x3000,
+                operand: [imm, 8 << 0],
+                operand: [rdn | rd, 3 << 8, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: AddReg,
+            flags: NZCV,
+            encoding: [
+                base: 0x1800,
+                operand: [rm, 3 << 6, read],
+                operand: [rdn, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: AddRegHigh,
+            encoding: [
+                base: 0x4400,
+                operand: [rm, 4 << 3, read],
+                operand: [rdn, 1 << 7 : 3 << 0, readwrite]
+            ]
+        },
+
+        instruction! {
+            name: AddSpImm,
+            reads: [sp],
+            encoding: [
+                base: 0xA800,
+                operand: [rd, 3 << 8, write],
+                operand: [imm, 8 << 0, scale 2, as u16]
+            ],
+            encoding: [
+                base: 0xB000,
+                operand: [imm, 7 << 0, scale 2, as u16],
+                operand: [rd, unused = 13, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: AddSpReg,
+            reads: [sp],
+            encoding: [
+                base: 0x4468,
+                operand: [rm | rd, 3 << 0, readwrite]
+            ],
+            encoding: [
+                base: 0x4485,
+                operand: [rm, 4 << 3, read],
+                operand: [rd, unused = 13, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: Adr,
+            reads: [pc],
+            encoding: [
+                base: 0xA000,
+                operand: [rd, 3 << 8, write],
+                operand: [imm, 8 << 0, scale 2, as u16]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: AndImm (thumb2 only)
+
+        instruction! {
+            name: AndReg,
+            flags: NZ,
+            encoding: [
+                base: 0x4000,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: AsrImm,
+            flags: NZC,
+            encoding: [
+                base: 0x1000,
+                operand: [imm, 5 << 6],
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: AsrReg,
+            flags: NZC,
+            encoding: [
+                base: 0x4100,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
+        },
+
+        instruction! {
+            name: BranchE1,
+            class: Branch,
+            reads: [pc],
+            writes: [pc],
+            encoding: [
+                base: 0xD000,
+                operand: [cond, 4 << 8],
+                operand: [imm, 8 << 0, scale 1, as i16]
+            ]
+        },
+
+        instruction! {
+            name: BranchE2,
+            class: Branch,
+            reads: [pc],
+            writes: [pc],
+            encoding: [
+                base: 0xE000,
+                operand: [imm, 11 << 0, scale 1, as i16]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Bfc (thumb2 only)
+
+        // todo: Bfi (thumb2 only)
+
+        // todo: BicImm (thumb2 only)
+
+        instruction! {
+            name: BicReg,
+            flags: NZ,
+            encoding: [
+                base: 0x4380,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: Breakpoint,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xBE00,
+                operand: [imm, 8 << 0]
+            ]
+        },
+
+        // todo: BranchL (thumb2 only)
+
+        instruction! {
+            name: BranchLx,
+            class: Branch,
+            reads: [pc],
+            writes: [lr, pc],
+            encoding: [
+                base: 0x4780,
+                operand: [rm, 4 << 3, read]
+            ]
+        },
+
+        instruction! {
+            name: BranchX,
+            class: Branch,
+            writes: [pc],
+            encoding: [
+                base: 0x4700,
+                operand: [rm, 4 << 3, read]
+            ]
+        },
+
+        instruction! {
+            name: Cbnz,
+            class: Branch,
+            reads: [pc],
+            writes: [pc],
+            encoding: [
+                base: 0xB900,
+                operand: [imm, 1 << 9 : 5 << 3 : zeros 1],
+                operand: [rn, 3 << 0, read]
+            ]
+        },
+
+        instruction! {
+            name: Cbz,
+            class: Branch,
+            reads: [pc],
+            writes: [pc],
+            encoding: [
+                base: 0xB100,
+                operand: [imm, 1 << 9 : 5 << 3 : zeros 1],
+                operand: [rn, 3 << 0, read]
+            ]
+        },
+
+        // todo: Cdp (thumb2 only)
+
+        // todo: Cdp2 (thumb2 only)
+
+        // todo: ClrEx (thumb2 only)
+
+        // todo: Clz (thumb2 only)
+
+        // todo: CmnImm (thumb2 only)
+
+        instruction! {
+            name: CmnReg,
+            flags: NZCV,
+            encoding: [
+                base: 0x42C0,
+                operand: [rm, 3 << 3, read],
+                operand: [rn, 3 << 0, read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: CmpImm,
+            flags: NZCV,
+            encoding: [
+                base: 0x2800,
+                operand: [rn, 3 << 8, read],
+                operand: [imm, 8 << 0]
+            ]
+        },
+
+        instruction! {
+            name: CmpReg,
+            flags: NZCV,
+            encoding: [
+                base: 0x4280,
+                operand: [rm, 3 << 3, read],
+                operand: [rn, 3 << 0, read]
+            ],
+            encoding: [
+                base: 0x4500,
+                operand: [rm, 4 << 3, read],
+                operand: [rn, 1 << 7 : 3 << 0, read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: Cps,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xB660,
+                operand: [imm, 1 << 4],
+                operand: [fi, 1 << 1],
+                operand: [ff, 1 << 0]
+            ]
+        },
+
+        // Cpy == Mov
+
+        // todo: Csdb (thumb2 only)
+
+        // todo: Dbg (thumb2 only)
+
+        // todo: Dmb (thumb2 only)
+
+        // todo: Dsb (thumb2 only)
+
+        // todo: EorImm (thumb2 only)
+
+        instruction! {
+            name: EorReg,
+            flags: NZ,
+            encoding: [
+                base: 0x4040,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Isb (thumb2 only)
+
+        instruction! {
+            name: IfThen,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xBF00,
+                operand: [cond, 4 << 4],
+                operand: [mask, 4 << 0]
+            ]
+        },
+
+        // todo: LdcImm (thumb2 only)
+
+        // todo: Ldc2Imm (thumb2 only)
+
+        // todo: LdcLit (thumb2 only)
+
+        // todo: Ldc2Lit (thumb2 only)
+
+        instruction! {
+            name: Ldm, // Ldmia, Ldmfd
+            class: Load,
+            cycles: 1 + N,
+            encoding: [
+                base: 0xC800,
+                operand: [list, 8 << 0, list write],
+                operand: [rn, 3 << 8, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Ldmdb (Ldmea) (thumb2 only)
+
+        instruction! {
+            name: LdrImm,
+            class: Load,
+            cycles: 2,
+            encoding: [
+                base: 0x6800,
+                operand: [imm, 5 << 6, scale 2],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: LdrLit,
+            class: Load,
+            cycles: 2,
+            reads: [pc],
+            encoding: [
+                base: 0x4800,
+                operand: [rt, 3 << 8, write],
+                operand: [imm, 8 << 0, scale 2, as u16]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: LdrReg,
+            class: Load,
+            cycles: 2,
+            encoding: [
+                base: 0x5800,
+                operand: [rm, 3 << 6, read],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: LdrbImm,
+            class: Load,
+            cycles: 2,
+            encoding: [
+                base: 0x7800,
+                operand: [imm, 5 << 6],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: LdrbLit (thumb2 only)
+
+        instruction! {
+            name: LdrbReg,
+            class: Load,
+            cycles: 2,
+            encoding: [
+                base: 0x5C00,
+                operand: [rm, 3 << 6, read],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Ldrbt (thumb2 only)
+
+        // todo: LdrdImm (thumb2 only)
+
+        // todo: LdrdLit (thumb2 only)
+
+        // todo: LdrEx (thumb2 only)
+
+        // todo: LdrExB (thumb2 only)
+
+        // todo: LdrExH (thumb2 only)
+
+        instruction! {
+            name: LdrhImm,
+            class: Load,
+            cycles: 2,
+            encoding: [
+                base: 0x8800,
+                operand: [imm, 5 << 6, scale 1],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: LdrhLit (thumb2 only)
+
+        instruction! {
+            name: LdrhReg,
+            class: Load,
+            cycles: 2,
+            encoding: [
+                base: 0x5A00,
+                operand: [rm, 3 << 6, read],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Ldrht (thumb2 only)
+
+        // todo: LdrsbImm (thumb2 only)
+
+        // todo: LdrsbLit (thumb2 only)
+
+        instruction! {
+            name: LdrSbReg,
+            class: Load,
+            cycles: 2,
+            encoding: [
+                base: 0x5600,
+                operand: [rm, 3 << 6, read],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Ldrsbt (thumb2 only)
+
+        // todo: LdrshImm (thumb2 only)
+
+        // todo: LdrshLit (thumb2 only)
+
+        instruction! {
+            name: LdrShReg,
+            class: Load,
+            cycles: 2,
+            encoding: [
+                base: 0x5E00,
+                operand: [rm, 3 << 6, read],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // The SP relative encoding of LdrImm, which has its own variant to hold the wider offset
+        instruction! {
+            name: LdrSpImm,
+            class: Load,
+            cycles: 2,
+            reads: [sp],
+            encoding: [
+                base: 0x9800,
+                operand: [rt, 3 << 8, write],
+                operand: [imm, 8 << 0, scale 2, as u16]
+            ]
+        },
+
+        // todo: Ldrsht (thumb2 only)
+
+        // todo: Ldrt (thumb2 only)
+
+        instruction! {
+            name: LslImm,
+            flags: NZC,
+            encoding: [
+                base: 0x0000,
+                operand: [imm, 5 << 6],
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: LslReg,
+            flags: NZC,
+            encoding: [
+                base: 0x4080,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: LsrImm,
+            flags: NZC,
+            encoding: [
+                base: 0x0800,
+                operand: [imm, 5 << 6],
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: LsrReg,
+            flags: NZC,
+            encoding: [
+                base: 0x40C0,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Mcr (thumb2 only)
+
+        // todo: Mcr2 (thumb2 only)
+
+        // todo: Mcrr (thumb2 only)
+
+        // todo: Mcrr2 (thumb2 only)
+
+        // todo: Mla (thumb2 only)
+
+        // todo: Mls (thumb2 only)
+
+        instruction! {
+            name: MovImm,
+            flags: NZ,
+            encoding: [
+                base: 0x2000,
+                operand: [rd, 3 << 8, write],
+                operand: [imm, 8 << 0]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: MovReg,
+            encoding: [
+                base: 0x4600,
+                operand: [rm, 4 << 3, read],
+                operand: [rd, 1 << 7 : 3 << 0, write]
+            ]
 
-            instruction! {
-                name: MovReg,
-                encoding: [
-                    base: 0x4600,
-                    operand: [d, 1 << 7],
-                    operand: [rm, 4 << 3],
-                    operand: [rd, 3 << 0]
-                ]
-                
-                // todo: thumb2 encodings
-            },
-
-            // todo: MovTop (thumb2 only)
-
-            // todo: Mrc (thumb2 only)
-            
-            // todo: Mrc2 (thumb2 only)
+            // todo: thumb2 encodings
+        },
 
-            // todo: Mrrc (thumb2 only)
-            
-            // todo: Mrrc2 (thumb2 only)
+        // todo: MovTop (thumb2 only)
 
-            // todo: Mrs (thumb2 only)
+        // todo: Mrc (thumb2 only)
 
-            // todo: Msr (thumb2 only)
+        // todo: Mrc2 (thumb2 only)
 
-            instruction! {
-                name: Mul,
-                encoding: [
-                    base: 0x4340,
-                    operand: [rn, 3 << 3],
-                    operand: [rdm, 3 << 0]
-                ]
+        // todo: Mrrc (thumb2 only)
 
-                // todo: thumb2 encodings
-            },
+        // todo: Mrrc2 (thumb2 only)
 
-            // todo: MvnImm (thumb2 only)
+        // todo: Mrs (thumb2 only)
 
-            instruction! {
-                name: MvnReg,
-                encoding: [
-                    base: 0x43C0,
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
+        // todo: Msr (thumb2 only)
 
-                // todo: thumb2 encodings
-            },
+        instruction! {
+            name: Mul,
+            flags: NZ,
+            encoding: [
+                base: 0x4340,
+                operand: [rn, 3 << 3, read],
+                operand: [rdm, 3 << 0, readwrite]
+            ]
 
-            instruction! {
-                name: Nop,
-                encoding: [
-                    base: 0xBF00
-                ]
+            // todo: thumb2 encodings
+        },
 
-                // todo: thumb2 encodings
-            },
+        // todo: MvnImm (thumb2 only)
 
-            // todo: OrnImm (thumb2 only)
+        instruction! {
+            name: MvnReg,
+            flags: NZ,
+            encoding: [
+                base: 0x43C0,
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
 
-            // todo: OrnReg (thumb2 only)
+            // todo: thumb2 encodings
+        },
 
-            // todo: OrrImm (thumb2 only)
+        instruction! {
+            name: Nop,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xBF00
+            ]
 
-            instruction! {
-                name: OrrReg,
-                encoding: [
-                    base: 0x4300,
-                    operand: [rm, 3 << 0],
-                    operand: [rdn, 3 << 0]
-                ]
+            // todo: thumb2 encodings
+        },
 
-                // todo: thumb2 encodings
-            },
+        // todo: OrnImm (thumb2 only)
 
-            // todo: Pkhbt (thumb2 only)
+        // todo: OrnReg (thumb2 only)
 
-            // todo: Pkhtb (thumb2 only)
+        // todo: OrrImm (thumb2 only)
 
-            // todo: PldImm (thumb2 only)
+        instruction! {
+            name: OrrReg,
+            flags: NZ,
+            encoding: [
+                base: 0x4300,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
 
-            // todo: PldLit (thumb2 only)
+            // todo: thumb2 encodings
+        },
 
-            // todo: PldReg (thumb2 only)
+        // todo: Pkhbt (thumb2 only)
 
-            // todo: PliImm (thumb2 only)
+        // todo: Pkhtb (thumb2 only)
 
-            // todo: PliLit (thumb2 only)
+        // todo: PldImm (thumb2 only)
 
-            // todo: PliReg (thumb2 only)
+        // todo: PldLit (thumb2 only)
 
-            instruction! {
-                name: Pop,
-                encoding: [
-                    base: 0xBC00,
-                    operand: [p, 1 << 8],
-                    operand: [list, 8 << 0]
-                ]
+        // todo: PldReg (thumb2 only)
 
-                // todo: thumb2 encodings
-            },
+        // todo: PliImm (thumb2 only)
 
-            // todo:: Pssbb (thumb2 only)
+        // todo: PliLit (thumb2 only)
 
-            instruction! {
-                name: Push,
-                encoding: [
-                    base: 0xB400,
-                    operand: [m, 1 << 8],
-                    operand: [list, 8 << 0],
-                ]
+        // todo: PliReg (thumb2 only)
 
-                // todo: thumb2 encodings
-            },
+        instruction! {
+            name: Pop,
+            class: Load,
+            cycles: 1 + N,
+            reads: [sp],
+            writes: [sp],
+            encoding: [
+                base: 0xBC00,
+                operand: [list, 1 << 8 : zeros 7 : 8 << 0, as u16, list write]
+            ]
 
-            // todo: Qadd (thumb2 only)
+            // todo: thumb2 encodings
+        },
 
-            // todo: Qadd16 (thumb2 only)
+        // todo:: Pssbb (thumb2 only)
 
-            // todo: Qadd8 (thumb2 only)
+        instruction! {
+            name: Push,
+            class: Store,
+            cycles: 1 + N,
+            reads: [sp],
+            writes: [sp],
+            encoding: [
+                base: 0xB400,
+                operand: [list, 1 << 8 : zeros 6 : 8 << 0, as u16, list read]
+            ]
 
-            // todo: QasX (thumb2 only)
+            // todo: thumb2 encodings
+        },
 
-            // todo: QdAdd (thumb2 only)
+        // todo: Qadd (thumb2 only)
 
-            // todo: QdSub (thumb2 only)
+        // todo: Qadd16 (thumb2 only)
 
-            // todo: QsaX (thumb2 only)
+        // todo: Qadd8 (thumb2 only)
 
-            // todo: Qsub (thumb2 only)
+        // todo: QasX (thumb2 only)
 
-            // todo: Qsub16 (thumb2 only)
+        // todo: QdAdd (thumb2 only)
 
-            // todo: Qsub8 (thumb2 only)
+        // todo: QdSub (thumb2 only)
 
-            // todo: Rbit (thumb2 only)
+        // todo: QsaX (thumb2 only)
 
-            instruction! {
-                name: Rev,
-                encoding: [
-                    base: 0xBA00,
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
+        // todo: Qsub (thumb2 only)
 
-                // todo: thumb2 encodings
-            },
+        // todo: Qsub16 (thumb2 only)
 
-            instruction! {
-                name: Rev16,
-                encoding: [
-                    base: 0xBA40,
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0],
-                ]
-            },
+        // todo: Qsub8 (thumb2 only)
 
-            instruction! {
-                name: RevSh,
-                encoding: [
-                    base: 0xBAC0,
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0],
-                ]
+        // todo: Rbit (thumb2 only)
 
-                // todo: thumb2 encodings
-            },
+        instruction! {
+            name: Rev,
+            encoding: [
+                base: 0xBA00,
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
 
-            // todo: RorImm (thumb2 only)
+            // todo: thumb2 encodings
+        },
 
-            instruction! {
-                name: RorReg,
-                encoding: [
-                    base: 0x41C0,
-                    operand: [rm, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ]
-                
-                // todo: thumb2 encodings
-            },
-
-            // todo: RrX (thumb2 only)
-
-            instruction! {
-                name: RsbImm,
-                encoding: [
-                    base: 0x4240,
-                    operand: [rn, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: RsbReg (thumb2 only)
-
-            // todo: Sadd16 (thumb2 only)
-            
-            // todo: Sadd8 (thumb2 only)
-
-            // todo: SasX (thumb2 only)
-
-            // todo: SbcImm (thumb2 only)
-
-            instruction! {
-                name: SbcReg,
-                encoding: [
-                    base: 0x4180,
-                    operand: [rm, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: SbfX (thumb2 only)
-
-            // todo: Sdiv (thumb2 only)
-
-            // todo: Sel (thumb2 only)
-
-            instruction! {
-                name: Sev,
-                encoding: [
-                    base: 0xBF40
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: ShAdd16 (thumbs only)
-            // todo: ShAdd8 (thumbs only)
-
-            // todo: ShasX (thumbs only)
-            // todo: ShsaX (thumbs only)
-
-            // todo: ShSub16 (thumbs only)
-            // todo: ShSub8 (thumbs only)
-            // todo: ShSub8 (thumbs only)
-
-            // todo: SmulaBb (thumb2 only)
-            // todo: SmulaBt (thumb2 only)
-            // todo: SmulaTb (thumb2 only)
-            // todo: SmulaTt (thumb2 only)
-            // todo: SmulaD (thumb2 only)
-            // todo: SmulaX (thumb2 only)
-            // todo: SmulaL (thumb2 only)
-            // todo: SmulaLbb (thumb2 only)
-            // todo: SmulaLbt (thumb2 only)
-            // todo: SmulaLtb (thumb2 only)
-            // todo: SmulaLtt (thumb2 only)
-            // todo: SmulaLd (thumb2 only)
-            // todo: SmulaLx (thumb2 only)
-            // todo: SmulaWb (thumb2 only)
-            // todo: SmulaWt (thumb2 only)
-            // todo: SmulSd (thumb2 only)
-            // todo: SmulSdx (thumb2 only)
-            // todo: SmulsLd (thumb2 only)
-            // todo: SmulsLx (thumb2 only)
-            // todo: SmmLa (thumb2 only)
-            // todo: SmmLar (thumb2 only)
-            // todo: SmmLs (thumb2 only)
-            // todo: SmmLsr (thumb2 only)
-            // todo: SmmuL (thumb2 only)
-            // todo: SmmuLr (thumb2 only)
-            // todo: SmulAd (thumb2 only)
-            // todo: SmulAdx (thumb2 only)
-            // todo: SmulBb (thumb2 only)
-            // todo: SmulBt (thumb2 only)
-            // todo: SmulTb (thumb2 only)
-            // todo: SmulTt (thumb2 only)
-            // todo: SmulL (thumb2 only)
-            // todo: SmulWb (thumb2 only)
-            // todo: SmulWt (thumb2 only)
-            // todo: SmulSd (thumb2 only)
-            // todo: SmulSdx (thumb2 only)
-
-            // todo: Ssat (thumb2 only)
-            // todo: Ssat16 (thumb2 only)
-            
-            // todo: SsaX (thumb2 only)
-            
-            // todo: SsBb (thumb2 only)
-            
-            // todo: Ssub16 (thumb2 only)
-            // todo: Ssub8 (thumb2 only)
-            // todo: Ssub8 (thumb2 only)
-
-            // todo: Stc (thumb2 only)
-            // todo: Stc2 (thumb2 only)
-
-            instruction! {
-                name: Stm, // Stmia, Stmea
-                encoding: [
-                    base: 0xC000,
-                    operand: [rn, 3 << 8],
-                    operand: [list, 8 << 0],
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: StmDb (thumb2 only)
-            // todo: StmFd (thumb2 only)
-
-            instruction! {
-                name: StrImm,
-                encoding: [
-                    base: 0x6000,
-                    operand: [imm, 5 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: StrReg,
-                encoding: [
-                    base: 0x5000,
-                    operand: [rm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: StrbImm,
-                encoding: [
-                    base: 0x7000,
-                    operand: [imm, 5 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: StrbReg,
-                encoding: [
-                    base: 0x5400,
-                    operand: [rm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: StrBt (thumb2 only)
-
-            // todo: StrBtImm (thumb2 only)
-
-            // todo: StrEx (thumb2 only)
-
-            // todo: StrExb (thumb2 only)
-
-            // todo: StrExh (thumb2 only)
-
-            instruction! {
-                name: StrhImm,
-                encoding: [
-                    base: 0x8000,
-                    operand: [imm, 5 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: StrhReg,
-                encoding: [
-                    base: 0x5200,
-                    operand: [rm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rt, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: StrHt (thumb2 only)
-            // todo: Strt (thumb2 only)
-
-            instruction! {
-                name: SubImm,
-                encoding: [
-                    base: 0x1E00,
-                    operand: [imm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rdn, 3 << 0]
-                ],
-                encoding: [
-                    base: 0x3800,
-                    operand: [rdn, 3 << 8],
-                    operand: [imm, 8 << 0],
-                    operand: [rn, unused]
-                ]
-                
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: SubReg,
-                encoding: [
-                    base: 0x1A00,
-                    operand: [rm, 3 << 6],
-                    operand: [rn, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: SubSpImm,
-                encoding: [
-                    base: 0xB080,
-                    operand: [imm, 7 << 0]
-                ]
-                
-                // todo: thumb2 encodings
-            },
-            
-            // todo: SubSpReg (thumb2 only)
-
-            instruction! {
-                name: Svc,
-                encoding: [
-                    base: 0xDF00
-                ]
-            },
-
-            // todo: SxtAb (thumb2 only)
-            // todo: SxtAb16 (thumb2 only)
-            // todo: SxtAh (thumb2 only)
-
-            instruction! {
-                name: Sxtb,
-                encoding: [
-                    base: 0xB240,
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Sxtb16 (thumb2 only)
-            
-            instruction! {
-                name: Sxth,
-                encoding: [
-                    base: 0xB200,
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0],
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Tbb (thumb2 only)
-            // todo: Tbh (thumb2 only)
-
-            // todo: TeqImm (thumb2 only)
-            // todo: TeqReg (thumb2 only)
-            // todo: TstImm (thumb2 only)
-
-            instruction! {
-                name: TstReg,
-                encoding: [
-                    base: 0x4200,
-                    operand: [rm, 3 << 3],
-                    operand: [rn, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Uadd16 (thumb2 only)
-            // todo: Uadd8 (thumb2 only)
-
-            // todo: UasX (thumb2 only)
-
-            // todo: UbFx (thumb2 only)
-
-            instruction! {
-                name: Udf,
-                encoding: [
-                    base: 0xDE00,
-                    operand: [imm, 8 << 0],
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Udiv (thumb2 only)
-
-            // todo: UhAdd16 (thumb2 only)
-            // todo: UhAdd8 (thumb2 only)
-
-            // todo: UhAsX (thumb2 only)            
-            // todo: UhSaX (thumb2 only)
-            
-            // todo: UhSub16 (thumb2 only)
-            // todo: UhSub8 (thumb2 only)
-
-            // todo: UmulAaL (thumb2 only)
-            // todo: UmulAl (thumb2 only)
-            // todo: UmulL (thumb2 only)
-
-            // todo: UqAdd16 (thumb2 only)
-            // todo: UqAdd8 (thumb2 only)
-
-            // todo: UqAsX (thumb2 only)
-            // todo: UqSaX (thumb2 only)
-            
-            // todo: UqSub16 (thumb2 only)
-            // todo: UqSub8 (thumb2 only)
-
-            // todo: Usad8 (thumb2 only)
-            // todo: UsadA16 (thumb2 only)
-
-            // todo: Usat (thumb2 only)
-            // todo: Usat16 (thumb2 only)
-
-            // todo: UsaX (thumb2 only)
-            // todo: Usub16 (thumb2 only)
-            // todo: Usub8 (thumb2 only)
-
-            // todo: UxtAb (thumb2 only)
-            // todo: UxtAb16 (thumb2 only)
-            // todo: UxtAh (thumb2 only)
-            
-            instruction! {
-                name: Uxtb,
-                encoding: [
-                    base: 0xB2C0,
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // todo: Uxtb16 (thumb2 only)
-
-            instruction! {
-                name: Uxth,
-                encoding: [
-                    base: 0xB280,
-                    operand: [rm, 3 << 3],
-                    operand: [rd, 3 << 0]
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-
-
-            // !!!
-            // todo: Optional floating point module operations
-            // !!!
-
-
-
-            instruction! {
-                name: Wfe,
-                encoding: [
-                    base: 0xBF20
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: Wfi,
-                encoding: [
-                    base: 0xBF30
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            instruction! {
-                name: Yield,
-                encoding: [
-                    base: 0xBF10
-                ]
-
-                // todo: thumb2 encodings
-            },
-
-            // 32 bit thumb2 instructions
-            instruction! {
-                name: Thumb2A,
-                encoding: [
-                    base: 0xE800,
-                    operand: [high, 3 << 8],
-                    operand: [low, 8 << 0]
-                ]
-            },
-
-            instruction! {
-                name: Thumb2B,
-                encoding: [
-                    base: 0xF000,
-                    operand: [high, 3 << 8],
-                    operand: [low, 8 << 0]
-                ]
-            },
-
-            instruction! {
-                name: Thumb2C,
-                encoding: [
-                    base: 0xF800,
-                    operand: [high, 3 << 8],
-                    operand: [low, 8 << 0]
-                ]
+        instruction! {
+            name: Rev16,
+            encoding: [
+                base: 0xBA40,
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+        },
+
+        instruction! {
+            name: RevSh,
+            encoding: [
+                base: 0xBAC0,
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: RorImm (thumb2 only)
+
+        instruction! {
+            name: RorReg,
+            flags: NZC,
+            encoding: [
+                base: 0x41C0,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: RrX (thumb2 only)
+
+        instruction! {
+            name: RsbImm,
+            flags: NZCV,
+            encoding: [
+                base: 0x4240,
+                operand: [rn, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: RsbReg (thumb2 only)
+
+        // todo: Sadd16 (thumb2 only)
+
+        // todo: Sadd8 (thumb2 only)
+
+        // todo: SasX (thumb2 only)
+
+        // todo: SbcImm (thumb2 only)
+
+        instruction! {
+            name: SbcReg,
+            flags: NZCV,
+            encoding: [
+                base: 0x4180,
+                operand: [rm, 3 << 3, read],
+                operand: [rdn, 3 << 0, readwrite]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: SbfX (thumb2 only)
+
+        // todo: Sdiv (thumb2 only)
+
+        // todo: Sel (thumb2 only)
+
+        instruction! {
+            name: Sev,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xBF40
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: ShAdd16 (thumbs only)
+        // todo: ShAdd8 (thumbs only)
+
+        // todo: ShasX (thumbs only)
+        // todo: ShsaX (thumbs only)
+
+        // todo: ShSub16 (thumbs only)
+        // todo: ShSub8 (thumbs only)
+        // todo: ShSub8 (thumbs only)
+
+        // todo: SmulaBb (thumb2 only)
+        // todo: SmulaBt (thumb2 only)
+        // todo: SmulaTb (thumb2 only)
+        // todo: SmulaTt (thumb2 only)
+        // todo: SmulaD (thumb2 only)
+        // todo: SmulaX (thumb2 only)
+        // todo: SmulaL (thumb2 only)
+        // todo: SmulaLbb (thumb2 only)
+        // todo: SmulaLbt (thumb2 only)
+        // todo: SmulaLtb (thumb2 only)
+        // todo: SmulaLtt (thumb2 only)
+        // todo: SmulaLd (thumb2 only)
+        // todo: SmulaLx (thumb2 only)
+        // todo: SmulaWb (thumb2 only)
+        // todo: SmulaWt (thumb2 only)
+        // todo: SmulSd (thumb2 only)
+        // todo: SmulSdx (thumb2 only)
+        // todo: SmulsLd (thumb2 only)
+        // todo: SmulsLx (thumb2 only)
+        // todo: SmmLa (thumb2 only)
+        // todo: SmmLar (thumb2 only)
+        // todo: SmmLs (thumb2 only)
+        // todo: SmmLsr (thumb2 only)
+        // todo: SmmuL (thumb2 only)
+        // todo: SmmuLr (thumb2 only)
+        // todo: SmulAd (thumb2 only)
+        // todo: SmulAdx (thumb2 only)
+        // todo: SmulBb (thumb2 only)
+        // todo: SmulBt (thumb2 only)
+        // todo: SmulTb (thumb2 only)
+        // todo: SmulTt (thumb2 only)
+        // todo: SmulL (thumb2 only)
+        // todo: SmulWb (thumb2 only)
+        // todo: SmulWt (thumb2 only)
+        // todo: SmulSd (thumb2 only)
+        // todo: SmulSdx (thumb2 only)
+
+        // todo: Ssat (thumb2 only)
+        // todo: Ssat16 (thumb2 only)
+
+        // todo: SsaX (thumb2 only)
+
+        // todo: SsBb (thumb2 only)
+
+        // todo: Ssub16 (thumb2 only)
+        // todo: Ssub8 (thumb2 only)
+        // todo: Ssub8 (thumb2 only)
+
+        // todo: Stc (thumb2 only)
+        // todo: Stc2 (thumb2 only)
+
+        instruction! {
+            name: Stm, // Stmia, Stmea
+            class: Store,
+            cycles: 1 + N,
+            encoding: [
+                base: 0xC000,
+                operand: [rn, 3 << 8, readwrite],
+                operand: [list, 8 << 0, list read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: StmDb (thumb2 only)
+        // todo: StmFd (thumb2 only)
+
+        instruction! {
+            name: StrImm,
+            class: Store,
+            cycles: 2,
+            encoding: [
+                base: 0x6000,
+                operand: [imm, 5 << 6, scale 2],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: StrReg,
+            class: Store,
+            cycles: 2,
+            encoding: [
+                base: 0x5000,
+                operand: [rm, 3 << 6, read],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: StrbImm,
+            class: Store,
+            cycles: 2,
+            encoding: [
+                base: 0x7000,
+                operand: [imm, 5 << 6],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: StrbReg,
+            class: Store,
+            cycles: 2,
+            encoding: [
+                base: 0x5400,
+                operand: [rm, 3 << 6, read],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: StrBt (thumb2 only)
+
+        // todo: StrBtImm (thumb2 only)
+
+        // todo: StrEx (thumb2 only)
+
+        // todo: StrExb (thumb2 only)
+
+        // todo: StrExh (thumb2 only)
+
+        instruction! {
+            name: StrhImm,
+            class: Store,
+            cycles: 2,
+            encoding: [
+                base: 0x8000,
+                operand: [imm, 5 << 6, scale 1],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: StrhReg,
+            class: Store,
+            cycles: 2,
+            encoding: [
+                base: 0x5200,
+                operand: [rm, 3 << 6, read],
+                operand: [rn, 3 << 3, read],
+                operand: [rt, 3 << 0, read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // The SP relative encoding of StrImm, which has its own variant to hold the wider offset
+        instruction! {
+            name: StrSpImm,
+            class: Store,
+            cycles: 2,
+            reads: [sp],
+            encoding: [
+                base: 0x9000,
+                operand: [rt, 3 << 8, read],
+                operand: [imm, 8 << 0, scale 2, as u16]
+            ]
+        },
+
+        // todo: StrHt (thumb2 only)
+        // todo: Strt (thumb2 only)
+
+        instruction! {
+            name: SubImm,
+            flags: NZCV,
+            encoding: [
+                base: 0x1E00,
+                operand: [imm, 3 << 6],
+                operand: [rn, 3 << 3, read],
+                operand: [rdn, 3 << 0, write]
+            ],
+            encoding: [
+                base: 0x3800,
+                operand: [rdn | rn, 3 << 8, readwrite],
+                operand: [imm, 8 << 0]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: SubReg,
+            flags: NZCV,
+            encoding: [
+                base: 0x1A00,
+                operand: [rm, 3 << 6, read],
+                operand: [rn, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: SubSpImm,
+            reads: [sp],
+            writes: [sp],
+            encoding: [
+                base: 0xB080,
+                operand: [imm, 7 << 0, scale 2, as u16]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: SubSpReg (thumb2 only)
+
+        instruction! {
+            name: Svc,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xDF00,
+                operand: [imm, 8 << 0]
+            ]
+        },
+
+        // todo: SxtAb (thumb2 only)
+        // todo: SxtAb16 (thumb2 only)
+        // todo: SxtAh (thumb2 only)
+
+        instruction! {
+            name: Sxtb,
+            encoding: [
+                base: 0xB240,
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Sxtb16 (thumb2 only)
+
+        instruction! {
+            name: Sxth,
+            encoding: [
+                base: 0xB200,
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Tbb (thumb2 only)
+        // todo: Tbh (thumb2 only)
+
+        // todo: TeqImm (thumb2 only)
+        // todo: TeqReg (thumb2 only)
+        // todo: TstImm (thumb2 only)
+
+        instruction! {
+            name: TstReg,
+            flags: NZ,
+            encoding: [
+                base: 0x4200,
+                operand: [rm, 3 << 3, read],
+                operand: [rn, 3 << 0, read]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Uadd16 (thumb2 only)
+        // todo: Uadd8 (thumb2 only)
+
+        // todo: UasX (thumb2 only)
+
+        // todo: UbFx (thumb2 only)
+
+        instruction! {
+            name: Udf,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xDE00,
+                operand: [imm, 8 << 0]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Udiv (thumb2 only)
+
+        // todo: UhAdd16 (thumb2 only)
+        // todo: UhAdd8 (thumb2 only)
+
+        // todo: UhAsX (thumb2 only)
+        // todo: UhSaX (thumb2 only)
+
+        // todo: UhSub16 (thumb2 only)
+        // todo: UhSub8 (thumb2 only)
+
+        // todo: UmulAaL (thumb2 only)
+        // todo: UmulAl (thumb2 only)
+        // todo: UmulL (thumb2 only)
+
+        // todo: UqAdd16 (thumb2 only)
+        // todo: UqAdd8 (thumb2 only)
+
+        // todo: UqAsX (thumb2 only)
+        // todo: UqSaX (thumb2 only)
+
+        // todo: UqSub16 (thumb2 only)
+        // todo: UqSub8 (thumb2 only)
+
+        // todo: Usad8 (thumb2 only)
+        // todo: UsadA16 (thumb2 only)
+
+        // todo: Usat (thumb2 only)
+        // todo: Usat16 (thumb2 only)
+
+        // todo: UsaX (thumb2 only)
+        // todo: Usub16 (thumb2 only)
+        // todo: Usub8 (thumb2 only)
+
+        // todo: UxtAb (thumb2 only)
+        // todo: UxtAb16 (thumb2 only)
+        // todo: UxtAh (thumb2 only)
+
+        instruction! {
+            name: Uxtb,
+            encoding: [
+                base: 0xB2C0,
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // todo: Uxtb16 (thumb2 only)
+
+        instruction! {
+            name: Uxth,
+            encoding: [
+                base: 0xB280,
+                operand: [rm, 3 << 3, read],
+                operand: [rd, 3 << 0, write]
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // !!!
+        // todo: Optional floating point module operations
+        // !!!
+
+        instruction! {
+            name: Wfe,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xBF20
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: Wfi,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xBF30
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        instruction! {
+            name: Yield,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xBF10
+            ]
+
+            // todo: thumb2 encodings
+        },
+
+        // 32 bit thumb2 instructions
+
+        instruction! {
+            name: Thumb2A,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xE800,
+                operand: [high, 3 << 8],
+                operand: [low, 8 << 0]
+            ]
+        },
+
+        instruction! {
+            name: Thumb2B,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xF000,
+                operand: [high, 3 << 8],
+                operand: [low, 8 << 0]
+            ]
+        },
+
+        instruction! {
+            name: Thumb2C,
+            class: Miscellaneous,
+            encoding: [
+                base: 0xF800,
+                operand: [high, 3 << 8],
+                operand: [low, 8 << 0]
+            ]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_table_matches_assembler_output() {
+        let table = InstrThumb16::generate_decode_table();
+
+        // Halfwords as emitted by the assembler, paired with the expected decoding
+        let expected = [
+            (0x1CC8, InstrThumb16::AddImm { imm: 3, rdn: 1, rd: 0 }),        // adds r0, r1, #3
+            (0x31C8, InstrThumb16::AddImm { imm: 200, rdn: 1, rd: 1 }),      // adds r1, #200
+            (0x1E42, InstrThumb16::SubImm { imm: 1, rn: 0, rdn: 2 }),        // subs r2, r0, #1
+            (0x3A01, InstrThumb16::SubImm { imm: 1, rn: 2, rdn: 2 }),        // subs r2, #1
+            (0x18D1, InstrThumb16::AddReg { rm: 3, rdn: 2, rd: 1 }),         // adds r1, r2, r3
+            (0x449A, InstrThumb16::AddRegHigh { rm: 3, rdn: 10 }),           // add r10, r3
+            (0x449D, InstrThumb16::AddSpReg { rm: 3, rd: 13 }),              // add sp, r3
+            (0x446B, InstrThumb16::AddSpReg { rm: 3, rd: 3 }),               // add r3, sp, r3
+            (0xAA04, InstrThumb16::AddSpImm { imm: 16, rd: 2 }),             // add r2, sp, #16
+            (0xB004, InstrThumb16::AddSpImm { imm: 16, rd: 13 }),            // add sp, #16
+            (0xB084, InstrThumb16::SubSpImm { imm: 16 }),                    // sub sp, #16
+            (0x6841, InstrThumb16::LdrImm { imm: 4, rn: 0, rt: 1 }),         // ldr r1, [r0, #4]
+            (0x9901, InstrThumb16::LdrSpImm { rt: 1, imm: 4 }),              // ldr r1, [sp, #4]
+            (0x9102, InstrThumb16::StrSpImm { rt: 1, imm: 8 }),              // str r1, [sp, #8]
+            (0x4311, InstrThumb16::OrrReg { rm: 2, rdn: 1 }),                // orrs r1, r2
+            (0x4291, InstrThumb16::CmpReg { rm: 2, rn: 1 }),                 // cmp r1, r2
+            (0x4591, InstrThumb16::CmpReg { rm: 2, rn: 9 }),                 // cmp r9, r2
+            (0xB672, InstrThumb16::Cps { imm: 1, fi: 1, ff: 0 }),            // cpsid i
+            (0xBF00, InstrThumb16::Nop),                                     // nop
+            (0xBF10, InstrThumb16::Yield),                                   // yield
+            (0xBF30, InstrThumb16::Wfi),                                     // wfi
+            (0xBF08, InstrThumb16::IfThen { cond: 0, mask: 8 }),             // it eq
+            (0xD0FE, InstrThumb16::BranchE1 { cond: 0, imm: -4 }),           // beq .
+            (0xE7FE, InstrThumb16::BranchE2 { imm: -4 }),                    // b .
+            (0xB1E9, InstrThumb16::Cbz { imm: 58, rn: 1 }),                  // cbz r1, .+62
+            (0xDE05, InstrThumb16::Udf { imm: 5 }),                          // udf #5
+            (0xDF00, InstrThumb16::Svc { imm: 0 }),                          // svc #0
+            (0xDFAB, InstrThumb16::Svc { imm: 0xAB }),                       // svc #171
+            (0xB5F0, InstrThumb16::Push { list: 0x40F0 }),                   // push {r4-r7, lr}
+            (0xBD10, InstrThumb16::Pop { list: 0x8010 }),                    // pop {r4, pc}
+            (0x4770, InstrThumb16::BranchX { rm: 14 }),                      // bx lr
+            (0x4798, InstrThumb16::BranchLx { rm: 3 }),                      // blx r3
+            (0x4688, InstrThumb16::MovReg { rm: 1, rd: 8 }),                 // mov r8, r1
+            (0xF000, InstrThumb16::Thumb2B { high: 0, low: 0 }),             // first half of bl
+        ];
+
+        for (encoded, decoded) in expected.iter() {
+            assert_eq!(table[*encoded as usize], *decoded, "decoding {:#06X}", encoded);
+        }
+    }
+
+    #[test]
+    fn decode_table_thumb2_prefixes() {
+        let table = InstrThumb16::generate_decode_table();
+
+        for (encoded, decoded) in table.iter().enumerate() {
+            let is_prefix = matches!(decoded,
+                InstrThumb16::Thumb2A { .. } | InstrThumb16::Thumb2B { .. } | InstrThumb16::Thumb2C { .. });
+
+            assert_eq!(is_prefix, encoded >= 0xE800, "decoding {:#06X} as {:?}", encoded, decoded);
+        }
+    }
+
+    #[test]
+    fn instruction_descriptions_are_consistent() {
+        use crate::decode::{ validate, EncodingError };
+
+        // BX and BLX hold bits 2:0 at zero, and the remaining gaps are unallocated in ARMv7-M
+        let mut unallocated: Vec<EncodingError> = (0x4700..0x4800)
+            .step_by(8)
+            .map(|low| EncodingError::Unclaimed { low: low + 1, high: low + 7 })
+            .collect();
+
+        unallocated.extend([(0xB600, 0xB65F), (0xB664, 0xB66F), (0xB674, 0xB8FF), (0xBA80, 0xBABF)]
+            .iter()
+            .map(|&(low, high)| EncodingError::Unclaimed { low, high }));
+
+        let report: Vec<String> = validate(&instruction_descriptions())
+            .iter()
+            .filter(|error| !unallocated.contains(error))
+            .map(|error| error.to_string())
+            .collect();
+
+        assert!(report.is_empty(), "invalid instruction descriptions:\n{}", report.join("\n"));
+    }
+
+    #[test]
+    fn encode_round_trips_decode_table() {
+        let table = InstrThumb16::generate_decode_table();
+        let encoder = Encoder::new();
+
+        for (halfword, decoded) in table.iter().enumerate() {
+            if *decoded == InstrThumb16::Undefined {
+                continue;
             }
+
+            let encoded = encoder.encode(*decoded).unwrap_or_else(|| panic!("no encoding for {:?} ({:#06X})", decoded, halfword));
+            assert_eq!(table[encoded as usize], *decoded, "{:?} from {:#06X} encoded as {:#06X}", decoded, halfword, encoded);
         }
-        */
+    }
+
+    #[test]
+    fn encode_symbolic_instructions() {
+        let encoder = Encoder::new();
+
+        assert_eq!(encoder.encode(InstrThumb16::AddImm { imm: 3, rdn: 1, rd: 0 }), Some(0x1CC8));           // adds r0, r1, #3
+        assert_eq!(encoder.encode(InstrThumb16::AddImm { imm: 200, rdn: 2, rd: 2 }), Some(0x32C8));         // adds r2, #200
+        assert_eq!(encoder.encode(InstrThumb16::AddImm { imm: 200, rdn: 2, rd: 3 }), None);
+        assert_eq!(encoder.encode(InstrThumb16::Push { list: 0x40F0 }), Some(0xB5F0));                     // push {r4-r7, lr}
+        assert_eq!(encoder.encode(InstrThumb16::BranchE2 { imm: -4 }), Some(0xE7FE));                       // b .
+        assert_eq!(encoder.encode(InstrThumb16::BranchE2 { imm: -3 }), None);
+        assert_eq!(encoder.encode(InstrThumb16::Cbz { imm: 58, rn: 1 }), Some(0xB1E9));                    // cbz r1, .+62
+        assert_eq!(encoder.encode(InstrThumb16::LdrSpImm { rt: 1, imm: 1020 }), Some(0x99FF));              // ldr r1, [sp, #1020]
+        assert_eq!(encoder.encode(InstrThumb16::LdrSpImm { rt: 1, imm: 1024 }), None);
+        assert_eq!(encoder.encode(InstrThumb16::Undefined), None);
+    }
+
+    #[test]
+    fn instruction_semantics() {
+        use crate::decode::{ Flags, InstrClass };
+
+        let analyzer = Analyzer::new();
+        let info = |instr| analyzer.info(instr).expect("no semantics");
+
+        let adds = info(InstrThumb16::AddImm { imm: 3, rdn: 1, rd: 0 });                         // adds r0, r1, #3
+        assert_eq!((adds.reads(), adds.writes()), (0b0010, 0b0001));
+        assert_eq!(adds.flags(), Flags::N | Flags::Z | Flags::C | Flags::V);
+        assert_eq!(adds.class(), InstrClass::DataProcessing);
+        assert_eq!(adds.cycles(), 1);
+
+        let adds = info(InstrThumb16::AddImm { imm: 200, rdn: 2, rd: 2 });                       // adds r2, #200
+        assert_eq!((adds.reads(), adds.writes()), (0b0100, 0b0100));
+
+        let add = info(InstrThumb16::AddSpImm { imm: 16, rd: 13 });                              // add sp, #16
+        assert_eq!((add.reads(), add.writes()), (1 << 13, 1 << 13));
+        assert!(add.flags().is_empty());
+
+        let sub = info(InstrThumb16::SubSpImm { imm: 16 });                                       // sub sp, #16
+        assert_eq!((sub.reads(), sub.writes()), (1 << 13, 1 << 13));
+
+        let push = info(InstrThumb16::Push { list: 0x40F0 });                                    // push {r4-r7, lr}
+        assert!(push.is_store() && !push.is_branch());
+        assert_eq!((push.reads(), push.writes()), (0x60F0, 1 << 13));
+        assert_eq!(push.cycles(), 6);
+
+        let pop = info(InstrThumb16::Pop { list: 0x8010 });                                      // pop {r4, pc}
+        assert!(pop.is_load() && pop.is_branch());
+        assert_eq!(pop.cycles(), 1 + 2 + crate::decode::PIPELINE_REFILL);
+
+        let mov = info(InstrThumb16::MovReg { rm: 14, rd: 15 });                                 // mov pc, lr
+        assert!(mov.is_branch() && mov.reads_register(14));
+
+        let blx = info(InstrThumb16::BranchLx { rm: 3 });                                        // blx r3
+        assert_eq!(blx.class(), InstrClass::Branch);
+        assert_eq!(blx.writes(), (1 << 14) | (1 << 15));
+        assert!(blx.reads_register(3));
+
+        let ldr = info(InstrThumb16::LdrLit { rt: 1, imm: 8 });                                  // ldr r1, [pc, #8]
+        assert_eq!((ldr.reads(), ldr.writes(), ldr.cycles()), (1 << 15, 0b0010, 2));
+
+        assert_eq!(analyzer.info(InstrThumb16::Undefined), None);
     }
 }
@@ -0,0 +1,1316 @@
+//! Definition of 32 bit thumb2 instructions
+//!
+//! The 32 bit encoding space is far too large to decode through a lookup table, so instead the two halfwords are
+//! decoded by walking the encoding tables in section A5.3 of the ARMv7-M Architecture Reference Manual:
+//!
+//!   op1  op2      op  Instruction class
+//!   01   00xx0xx  x   Load/store multiple                              A5.3.5
+//!   01   00xx1xx  x   Load/store dual or exclusive, table branch       A5.3.6
+//!   01   01xxxxx  x   Data processing (shifted register)               A5.3.11
+//!   01   1xxxxxx  x   Coprocessor instructions                         A5.3.18
+//!   10   x0xxxxx  0   Data processing (modified immediate)             A5.3.1
+//!   10   x1xxxxx  0   Data processing (plain binary immediate)         A5.3.3
+//!   10   xxxxxxx  1   Branches and miscellaneous control               A5.3.4
+//!   11   000xxx0  x   Store single data item                           A5.3.10
+//!   11   00xx001  x   Load byte, memory hints                          A5.3.9
+//!   11   00xx011  x   Load halfword, memory hints                      A5.3.8
+//!   11   00xx101  x   Load word                                        A5.3.7
+//!   11   00xx111  x   UNDEFINED
+//!   11   010xxxx  x   Data processing (register)                       A5.3.12
+//!   11   0110xxx  x   Multiply, multiply accumulate, absolute diff.    A5.3.16
+//!   11   0111xxx  x   Long multiply, long multiply accumulate, divide  A5.3.17
+//!   11   1xxxxxx  x   Coprocessor instructions                         A5.3.18
+//!
+//! 32-bit instruction encoding:
+//! =================================================================================================
+//! |15 14 13|12 11|10 09 08 07 06 05 04|03 02 01 00|15|14 13 12 11 10 09 08 07 06 05 04 03 02 01 00|
+//! |1  1  1 |op1  |op2                 |           |op|                                            |
+//! =================================================================================================
+//!
+//! Encodings which belong to the optional DSP extension of ARMv7E-M are not modelled and decode as undefined.
+
+/// Shift types used by the shifted register forms of the data processing and load/store instructions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShiftType {
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+    Rrx,
+}
+
+/// A shift applied to a register operand by an immediate amount
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImmShift {
+    pub ty: ShiftType,
+    pub n: u8,
+}
+
+impl ImmShift {
+    /// DecodeImmShift() from the ARMv7-M Architecture Reference Manual
+    pub fn decode(ty: u8, imm5: u8) -> ImmShift {
+        match (ty & 0b11, imm5) {
+            (0b00, n) => ImmShift { ty: ShiftType::Lsl, n },
+            (0b01, 0) => ImmShift { ty: ShiftType::Lsr, n: 32 },
+            (0b01, n) => ImmShift { ty: ShiftType::Lsr, n },
+            (0b10, 0) => ImmShift { ty: ShiftType::Asr, n: 32 },
+            (0b10, n) => ImmShift { ty: ShiftType::Asr, n },
+            (_, 0) => ImmShift { ty: ShiftType::Rrx, n: 1 },
+            (_, n) => ImmShift { ty: ShiftType::Ror, n },
+        }
+    }
+
+    /// The inverse of DecodeImmShift(), returns the shift type and imm5 fields
+    pub fn encode(&self) -> (u8, u8) {
+        match (self.ty, self.n) {
+            (ShiftType::Lsl, n) => (0b00, n),
+            (ShiftType::Lsr, 32) => (0b01, 0),
+            (ShiftType::Lsr, n) => (0b01, n),
+            (ShiftType::Asr, 32) => (0b10, 0),
+            (ShiftType::Asr, n) => (0b10, n),
+            (ShiftType::Rrx, _) => (0b11, 0),
+            (ShiftType::Ror, n) => (0b11, n),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstrThumb32 {
+    // Data processing (modified immediate), imm12 is the i:imm3:imm8 value to be passed through ThumbExpandImm
+    AdcImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    AddImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    AndImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    BicImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    CmnImm { rn: u8, imm12: u16 },
+    CmpImm { rn: u8, imm12: u16 },
+    EorImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    MovImm { s: bool, rd: u8, imm12: u16 },
+    MvnImm { s: bool, rd: u8, imm12: u16 },
+    OrnImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    OrrImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    RsbImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    SbcImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    SubImm { s: bool, rn: u8, rd: u8, imm12: u16 },
+    TeqImm { rn: u8, imm12: u16 },
+    TstImm { rn: u8, imm12: u16 },
+
+    // Data processing (plain binary immediate)
+    Addw { rn: u8, rd: u8, imm: u16 },
+    Adr { rd: u8, imm: u16, add: bool },
+    Bfc { rd: u8, msb: u8, lsb: u8 },
+    Bfi { rn: u8, rd: u8, msb: u8, lsb: u8 },
+    Movt { rd: u8, imm: u16 },
+    Movw { rd: u8, imm: u16 },
+    Sbfx { rn: u8, rd: u8, lsb: u8, widthm1: u8 },
+    Ssat { rn: u8, rd: u8, sat_imm: u8, shift: ImmShift },
+    Subw { rn: u8, rd: u8, imm: u16 },
+    Ubfx { rn: u8, rd: u8, lsb: u8, widthm1: u8 },
+    Usat { rn: u8, rd: u8, sat_imm: u8, shift: ImmShift },
+
+    // Branches and miscellaneous control, branch offsets are the sign extended byte offset from the PC
+    BranchE3 { cond: u8, imm: i32 },
+    BranchE4 { imm: i32 },
+    BranchL { imm: i32 },
+    ClrEx,
+    Dbg { option: u8 },
+    Dmb { option: u8 },
+    Dsb { option: u8 },
+    Isb { option: u8 },
+    Mrs { rd: u8, sysm: u8 },
+    Msr { rn: u8, mask: u8, sysm: u8 },
+    Nop,
+    Sev,
+    Udf { imm: u16 },
+    Wfe,
+    Wfi,
+    Yield,
+
+    // Load/store multiple
+    Ldm { rn: u8, w: bool, list: u16 },
+    Ldmdb { rn: u8, w: bool, list: u16 },
+    Pop { list: u16 },
+    Push { list: u16 },
+    Stm { rn: u8, w: bool, list: u16 },
+    Stmdb { rn: u8, w: bool, list: u16 },
+
+    // Load/store dual or exclusive, table branch
+    LdrdImm { rn: u8, rt: u8, rt2: u8, imm: u16, index: bool, add: bool, wback: bool },
+    LdrdLit { rt: u8, rt2: u8, imm: u16, add: bool },
+    LdrEx { rn: u8, rt: u8, imm: u16 },
+    LdrExB { rn: u8, rt: u8 },
+    LdrExH { rn: u8, rt: u8 },
+    StrdImm { rn: u8, rt: u8, rt2: u8, imm: u16, index: bool, add: bool, wback: bool },
+    StrEx { rn: u8, rt: u8, rd: u8, imm: u16 },
+    StrExB { rn: u8, rt: u8, rd: u8 },
+    StrExH { rn: u8, rt: u8, rd: u8 },
+    Tbb { rn: u8, rm: u8 },
+    Tbh { rn: u8, rm: u8 },
+
+    // Data processing (shifted register)
+    AdcReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    AddReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    AndReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    AsrImm { s: bool, rd: u8, rm: u8, imm: u8 },
+    BicReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    CmnReg { rn: u8, rm: u8, shift: ImmShift },
+    CmpReg { rn: u8, rm: u8, shift: ImmShift },
+    EorReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    LslImm { s: bool, rd: u8, rm: u8, imm: u8 },
+    LsrImm { s: bool, rd: u8, rm: u8, imm: u8 },
+    MovReg { s: bool, rd: u8, rm: u8 },
+    MvnReg { s: bool, rd: u8, rm: u8, shift: ImmShift },
+    OrnReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    OrrReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    RorImm { s: bool, rd: u8, rm: u8, imm: u8 },
+    RsbReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    Rrx { s: bool, rd: u8, rm: u8 },
+    SbcReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    SubReg { s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift },
+    TeqReg { rn: u8, rm: u8, shift: ImmShift },
+    TstReg { rn: u8, rm: u8, shift: ImmShift },
+
+    // Data processing (register), extend rotations are in bits
+    AsrReg { s: bool, rn: u8, rd: u8, rm: u8 },
+    Clz { rd: u8, rm: u8 },
+    LslReg { s: bool, rn: u8, rd: u8, rm: u8 },
+    LsrReg { s: bool, rn: u8, rd: u8, rm: u8 },
+    Rbit { rd: u8, rm: u8 },
+    Rev { rd: u8, rm: u8 },
+    Rev16 { rd: u8, rm: u8 },
+    RevSh { rd: u8, rm: u8 },
+    RorReg { s: bool, rn: u8, rd: u8, rm: u8 },
+    Sxtb { rd: u8, rm: u8, rotation: u8 },
+    Sxth { rd: u8, rm: u8, rotation: u8 },
+    Uxtb { rd: u8, rm: u8, rotation: u8 },
+    Uxth { rd: u8, rm: u8, rotation: u8 },
+
+    // Multiply, multiply accumulate, long multiply and divide
+    Mla { rn: u8, rd: u8, rm: u8, ra: u8 },
+    Mls { rn: u8, rd: u8, rm: u8, ra: u8 },
+    Mul { rn: u8, rd: u8, rm: u8 },
+    Sdiv { rn: u8, rd: u8, rm: u8 },
+    SmlaL { rn: u8, rdlo: u8, rdhi: u8, rm: u8 },
+    SmulL { rn: u8, rdlo: u8, rdhi: u8, rm: u8 },
+    Udiv { rn: u8, rd: u8, rm: u8 },
+    UmlaL { rn: u8, rdlo: u8, rdhi: u8, rm: u8 },
+    UmulL { rn: u8, rdlo: u8, rdhi: u8, rm: u8 },
+
+    // Load/store single data item, register offsets are shifted left by `shift`
+    LdrImm { rn: u8, rt: u8, imm: u16, index: bool, add: bool, wback: bool },
+    LdrLit { rt: u8, imm: u16, add: bool },
+    LdrReg { rn: u8, rt: u8, rm: u8, shift: u8 },
+    Ldrt { rn: u8, rt: u8, imm: u8 },
+    LdrbImm { rn: u8, rt: u8, imm: u16, index: bool, add: bool, wback: bool },
+    LdrbLit { rt: u8, imm: u16, add: bool },
+    LdrbReg { rn: u8, rt: u8, rm: u8, shift: u8 },
+    Ldrbt { rn: u8, rt: u8, imm: u8 },
+    LdrhImm { rn: u8, rt: u8, imm: u16, index: bool, add: bool, wback: bool },
+    LdrhLit { rt: u8, imm: u16, add: bool },
+    LdrhReg { rn: u8, rt: u8, rm: u8, shift: u8 },
+    Ldrht { rn: u8, rt: u8, imm: u8 },
+    LdrsbImm { rn: u8, rt: u8, imm: u16, index: bool, add: bool, wback: bool },
+    LdrsbLit { rt: u8, imm: u16, add: bool },
+    LdrsbReg { rn: u8, rt: u8, rm: u8, shift: u8 },
+    Ldrsbt { rn: u8, rt: u8, imm: u8 },
+    LdrshImm { rn: u8, rt: u8, imm: u16, index: bool, add: bool, wback: bool },
+    LdrshLit { rt: u8, imm: u16, add: bool },
+    LdrshReg { rn: u8, rt: u8, rm: u8, shift: u8 },
+    Ldrsht { rn: u8, rt: u8, imm: u8 },
+    PldImm { rn: u8, imm: u16, add: bool },
+    PldLit { imm: u16, add: bool },
+    PldReg { rn: u8, rm: u8, shift: u8 },
+    PliImm { rn: u8, imm: u16, add: bool },
+    PliLit { imm: u16, add: bool },
+    PliReg { rn: u8, rm: u8, shift: u8 },
+    StrImm { rn: u8, rt: u8, imm: u16, index: bool, add: bool, wback: bool },
+    StrReg { rn: u8, rt: u8, rm: u8, shift: u8 },
+    Strt { rn: u8, rt: u8, imm: u8 },
+    StrbImm { rn: u8, rt: u8, imm: u16, index: bool, add: bool, wback: bool },
+    StrbReg { rn: u8, rt: u8, rm: u8, shift: u8 },
+    Strbt { rn: u8, rt: u8, imm: u8 },
+    StrhImm { rn: u8, rt: u8, imm: u16, index: bool, add: bool, wback: bool },
+    StrhReg { rn: u8, rt: u8, rm: u8, shift: u8 },
+    Strht { rn: u8, rt: u8, imm: u8 },
+
+    // Coprocessor instructions, the "2" forms are those with bit 12 of the first halfword set
+    Cdp { coproc: u8, opc1: u8, crd: u8, crn: u8, crm: u8, opc2: u8 },
+    Cdp2 { coproc: u8, opc1: u8, crd: u8, crn: u8, crm: u8, opc2: u8 },
+    LdcImm { coproc: u8, rn: u8, crd: u8, imm: u16, index: bool, add: bool, wback: bool, long: bool },
+    Ldc2Imm { coproc: u8, rn: u8, crd: u8, imm: u16, index: bool, add: bool, wback: bool, long: bool },
+    LdcLit { coproc: u8, crd: u8, imm: u16, index: bool, add: bool, long: bool },
+    Ldc2Lit { coproc: u8, crd: u8, imm: u16, index: bool, add: bool, long: bool },
+    Mcr { coproc: u8, opc1: u8, rt: u8, crn: u8, crm: u8, opc2: u8 },
+    Mcr2 { coproc: u8, opc1: u8, rt: u8, crn: u8, crm: u8, opc2: u8 },
+    Mcrr { coproc: u8, opc1: u8, rt: u8, rt2: u8, crm: u8 },
+    Mcrr2 { coproc: u8, opc1: u8, rt: u8, rt2: u8, crm: u8 },
+    Mrc { coproc: u8, opc1: u8, rt: u8, crn: u8, crm: u8, opc2: u8 },
+    Mrc2 { coproc: u8, opc1: u8, rt: u8, crn: u8, crm: u8, opc2: u8 },
+    Mrrc { coproc: u8, opc1: u8, rt: u8, rt2: u8, crm: u8 },
+    Mrrc2 { coproc: u8, opc1: u8, rt: u8, rt2: u8, crm: u8 },
+    Stc { coproc: u8, rn: u8, crd: u8, imm: u16, index: bool, add: bool, wback: bool, long: bool },
+    Stc2 { coproc: u8, rn: u8, crd: u8, imm: u16, index: bool, add: bool, wback: bool, long: bool },
+
+    Undefined,
+}
+
+/// Extracts the bits [hi:lo] of a halfword
+fn bits(hw: u16, hi: u32, lo: u32) -> u8 {
+    ((hw >> lo) & ((1 << (hi - lo + 1)) - 1)) as u8
+}
+
+fn bit(hw: u16, n: u32) -> bool {
+    (hw >> n) & 1 == 1
+}
+
+/// Sign extends the lowest `width` bits of a value
+fn sign_extend(value: u32, width: u32) -> i32 {
+    let shift = 32 - width;
+    ((value << shift) as i32) >> shift
+}
+
+/// ThumbExpandImm() from the ARMv7-M Architecture Reference Manual, expands a modified immediate i:imm3:imm8
+pub fn thumb_expand_imm(imm12: u16) -> u32 {
+    let imm8 = (imm12 & 0xFF) as u32;
+
+    match (imm12 >> 8) & 0b1111 {
+        0b0000 => imm8,
+        0b0001 => imm8 << 16 | imm8,
+        0b0010 => imm8 << 24 | imm8 << 8,
+        0b0011 => imm8 << 24 | imm8 << 16 | imm8 << 8 | imm8,
+        _ => (0x80 | imm8 & 0x7F).rotate_right((imm12 >> 7) as u32 & 0b11111),
+    }
+}
+
+/// Encodes the data processing (modified immediate) and (plain binary immediate) forms, imm12 is i:imm3:imm8
+fn data_processing_immediate(hw1: u16, rn: u8, rd: u8, imm12: u16) -> (u16, u16) {
+    let hw1 = hw1 | ((imm12 >> 11) & 1) << 10 | rn as u16;
+    let hw2 = ((imm12 >> 8) & 0b111) << 12 | (rd as u16) << 8 | (imm12 & 0xFF);
+    (hw1, hw2)
+}
+
+/// Packs the imm3:imm2 and 5 bit fields shared by the bitfield and saturate forms into the i:imm3:imm8 layout
+fn bitfield(imm5: u8, field: u8) -> u16 {
+    ((imm5 >> 2) as u16) << 8 | ((imm5 & 0b11) as u16) << 6 | field as u16
+}
+
+/// Encodes the data processing (shifted register) forms
+fn data_processing_shifted(hw1: u16, s: bool, rn: u8, rd: u8, rm: u8, shift: ImmShift) -> (u16, u16) {
+    let (ty, imm5) = shift.encode();
+    let hw1 = hw1 | (s as u16) << 4 | rn as u16;
+    let hw2 = ((imm5 >> 2) as u16) << 12 | (rd as u16) << 8 | ((imm5 & 0b11) as u16) << 6 | (ty as u16) << 4 | rm as u16;
+    (hw1, hw2)
+}
+
+/// Encodes the load/store single data item forms, `size` is 0, 1 or 2 for byte, halfword and word
+fn load_store_single(signed: bool, size: u16, load: bool, rn: u8, rt: u8, addressing: SingleAddressing) -> (u16, u16) {
+    let (hw1, hw2) = addressing.encode();
+    let hw1 = 0xF800 | hw1 | (signed as u16) << 8 | size << 5 | (load as u16) << 4 | rn as u16;
+    (hw1, hw2 | (rt as u16) << 12)
+}
+
+/// Chooses the addressing form for an immediate offset, the 12 bit form is the only positive offset without writeback
+fn immediate_addressing(imm: u16, index: bool, add: bool, wback: bool) -> SingleAddressing {
+    if index && add && !wback {
+        SingleAddressing::Imm12(imm)
+    } else {
+        SingleAddressing::Imm8 { imm, index, add, wback }
+    }
+}
+
+/// Encodes the LDC and STC forms
+#[allow(clippy::too_many_arguments)]
+fn coprocessor_load_store(two: bool, load: bool, coproc: u8, rn: u8, crd: u8, imm: u16, index: bool, add: bool, wback: bool, long: bool) -> (u16, u16) {
+    let hw1 = 0xEC00 | (two as u16) << 12 | (index as u16) << 8 | (add as u16) << 7 | (long as u16) << 6
+        | (wback as u16) << 5 | (load as u16) << 4 | rn as u16;
+    let hw2 = (crd as u16) << 12 | (coproc as u16) << 8 | (imm >> 2);
+    (hw1, hw2)
+}
+
+/// Encodes the MCR and MRC forms
+#[allow(clippy::too_many_arguments)]
+fn coprocessor_transfer(two: bool, load: bool, coproc: u8, opc1: u8, rt: u8, crn: u8, crm: u8, opc2: u8) -> (u16, u16) {
+    let hw1 = 0xEE00 | (two as u16) << 12 | (opc1 as u16) << 5 | (load as u16) << 4 | crn as u16;
+    let hw2 = (rt as u16) << 12 | (coproc as u16) << 8 | (opc2 as u16) << 5 | 0x10 | crm as u16;
+    (hw1, hw2)
+}
+
+/// Encodes the MCRR and MRRC forms
+fn coprocessor_transfer_dual(two: bool, load: bool, coproc: u8, opc1: u8, rt: u8, rt2: u8, crm: u8) -> (u16, u16) {
+    let hw1 = 0xEC40 | (two as u16) << 12 | (load as u16) << 4 | rt2 as u16;
+    let hw2 = (rt as u16) << 12 | (coproc as u16) << 8 | (opc1 as u16) << 4 | crm as u16;
+    (hw1, hw2)
+}
+
+/// Encodes the CDP forms
+fn coprocessor_data(two: bool, coproc: u8, opc1: u8, crd: u8, crn: u8, crm: u8, opc2: u8) -> (u16, u16) {
+    let hw1 = 0xEE00 | (two as u16) << 12 | (opc1 as u16) << 4 | crn as u16;
+    let hw2 = (crd as u16) << 12 | (coproc as u16) << 8 | (opc2 as u16) << 5 | crm as u16;
+    (hw1, hw2)
+}
+
+/// Encodes a branch offset as S:I1:I2:imm10:imm11, returning the first halfword and the J1/J2/imm11 bits of the second
+fn branch_offset(imm: i32) -> (u16, u16) {
+    let offset = imm as u32;
+    let s = (offset >> 24) & 1;
+    let j1 = !((offset >> 23) & 1 ^ s) & 1;
+    let j2 = !((offset >> 22) & 1 ^ s) & 1;
+    let hw1 = 0xF000 | (s << 10) as u16 | ((offset >> 12) & 0x3FF) as u16;
+    let hw2 = (j1 << 13 | j2 << 11) as u16 | ((offset >> 1) & 0x7FF) as u16;
+    (hw1, hw2)
+}
+
+impl InstrThumb32 {
+    /// Returns true when a halfword is the first half of a 32 bit instruction
+    pub fn is_prefix(hw1: u16) -> bool {
+        matches!(hw1 >> 11, 0b11101..=0b11111)
+    }
+
+    /// Decodes a 32 bit instruction from its first and second halfwords
+    pub fn decode(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let op1 = bits(hw1, 12, 11);
+        let op2 = bits(hw1, 10, 4);
+        let op = bit(hw2, 15);
+
+        match op1 {
+            0b01 => {
+                if op2 & 0b1100100 == 0b0000000 {
+                    InstrThumb32::decode_load_store_multiple(hw1, hw2)
+                } else if op2 & 0b1100100 == 0b0000100 {
+                    InstrThumb32::decode_load_store_dual_exclusive(hw1, hw2)
+                } else if op2 & 0b1100000 == 0b0100000 {
+                    InstrThumb32::decode_data_processing_shifted_register(hw1, hw2)
+                } else {
+                    InstrThumb32::decode_coprocessor(hw1, hw2)
+                }
+            },
+
+            0b10 => {
+                if op {
+                    InstrThumb32::decode_branch_misc_control(hw1, hw2)
+                } else if op2 & 0b0100000 == 0 {
+                    InstrThumb32::decode_data_processing_modified_immediate(hw1, hw2)
+                } else {
+                    InstrThumb32::decode_data_processing_plain_immediate(hw1, hw2)
+                }
+            },
+
+            0b11 => {
+                if op2 & 0b1110001 == 0b0000000 {
+                    InstrThumb32::decode_store_single(hw1, hw2)
+                } else if op2 & 0b1100111 == 0b0000001 {
+                    InstrThumb32::decode_load_byte(hw1, hw2)
+                } else if op2 & 0b1100111 == 0b0000011 {
+                    InstrThumb32::decode_load_halfword(hw1, hw2)
+                } else if op2 & 0b1100111 == 0b0000101 {
+                    InstrThumb32::decode_load_word(hw1, hw2)
+                } else if op2 & 0b1110000 == 0b0100000 {
+                    InstrThumb32::decode_data_processing_register(hw1, hw2)
+                } else if op2 & 0b1111000 == 0b0110000 {
+                    InstrThumb32::decode_multiply(hw1, hw2)
+                } else if op2 & 0b1111000 == 0b0111000 {
+                    InstrThumb32::decode_long_multiply_divide(hw1, hw2)
+                } else if op2 & 0b1000000 == 0b1000000 {
+                    InstrThumb32::decode_coprocessor(hw1, hw2)
+                } else {
+                    InstrThumb32::Undefined
+                }
+            },
+
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Encodes a 32 bit instruction into its first and second halfwords, the inverse of `decode`
+    /// 
+    /// Where several encodings decode to the same instruction the canonical one is produced. Returns `None` if the
+    /// instruction has no encoding, for example because an immediate is out of range or misaligned.
+    pub fn encode(&self) -> Option<(u16, u16)> {
+        use InstrThumb32::*;
+
+        let (hw1, hw2) = match *self {
+            AndImm { s, rn, rd, imm12 } => data_processing_immediate(0xF000 | (s as u16) << 4, rn, rd, imm12),
+            TstImm { rn, imm12 } => data_processing_immediate(0xF010, rn, 0xF, imm12),
+            BicImm { s, rn, rd, imm12 } => data_processing_immediate(0xF020 | (s as u16) << 4, rn, rd, imm12),
+            OrrImm { s, rn, rd, imm12 } => data_processing_immediate(0xF040 | (s as u16) << 4, rn, rd, imm12),
+            MovImm { s, rd, imm12 } => data_processing_immediate(0xF040 | (s as u16) << 4, 0xF, rd, imm12),
+            OrnImm { s, rn, rd, imm12 } => data_processing_immediate(0xF060 | (s as u16) << 4, rn, rd, imm12),
+            MvnImm { s, rd, imm12 } => data_processing_immediate(0xF060 | (s as u16) << 4, 0xF, rd, imm12),
+            EorImm { s, rn, rd, imm12 } => data_processing_immediate(0xF080 | (s as u16) << 4, rn, rd, imm12),
+            TeqImm { rn, imm12 } => data_processing_immediate(0xF090, rn, 0xF, imm12),
+            AddImm { s, rn, rd, imm12 } => data_processing_immediate(0xF100 | (s as u16) << 4, rn, rd, imm12),
+            CmnImm { rn, imm12 } => data_processing_immediate(0xF110, rn, 0xF, imm12),
+            AdcImm { s, rn, rd, imm12 } => data_processing_immediate(0xF140 | (s as u16) << 4, rn, rd, imm12),
+            SbcImm { s, rn, rd, imm12 } => data_processing_immediate(0xF160 | (s as u16) << 4, rn, rd, imm12),
+            SubImm { s, rn, rd, imm12 } => data_processing_immediate(0xF1A0 | (s as u16) << 4, rn, rd, imm12),
+            CmpImm { rn, imm12 } => data_processing_immediate(0xF1B0, rn, 0xF, imm12),
+            RsbImm { s, rn, rd, imm12 } => data_processing_immediate(0xF1C0 | (s as u16) << 4, rn, rd, imm12),
+
+            Addw { rn, rd, imm } => data_processing_immediate(0xF200, rn, rd, imm),
+            Adr { rd, imm, add: true } => data_processing_immediate(0xF200, 0xF, rd, imm),
+            Adr { rd, imm, add: false } => data_processing_immediate(0xF2A0, 0xF, rd, imm),
+            Subw { rn, rd, imm } => data_processing_immediate(0xF2A0, rn, rd, imm),
+            Movw { rd, imm } => data_processing_immediate(0xF240, (imm >> 12) as u8, rd, imm & 0xFFF),
+            Movt { rd, imm } => data_processing_immediate(0xF2C0, (imm >> 12) as u8, rd, imm & 0xFFF),
+            Ssat { rn, rd, sat_imm, shift } | Usat { rn, rd, sat_imm, shift } => {
+                let (ty, imm5) = shift.encode();
+                let (op, field) = match self {
+                    Ssat { .. } => (0xF300, sat_imm.wrapping_sub(1)),
+                    _ => (0xF380, sat_imm),
+                };
+                data_processing_immediate(op | ((ty >> 1) as u16) << 5, rn, rd, bitfield(imm5, field))
+            },
+            Sbfx { rn, rd, lsb, widthm1 } => data_processing_immediate(0xF340, rn, rd, bitfield(lsb, widthm1)),
+            Bfi { rn, rd, msb, lsb } => data_processing_immediate(0xF360, rn, rd, bitfield(lsb, msb)),
+            Bfc { rd, msb, lsb } => data_processing_immediate(0xF360, 0xF, rd, bitfield(lsb, msb)),
+            Ubfx { rn, rd, lsb, widthm1 } => data_processing_immediate(0xF3C0, rn, rd, bitfield(lsb, widthm1)),
+
+            BranchE3 { cond, imm } => {
+                let offset = imm as u32;
+                let hw1 = 0xF000 | ((offset >> 20) & 1) << 10 | (cond as u32) << 6 | ((offset >> 12) & 0x3F);
+                let hw2 = 0x8000 | ((offset >> 18) & 1) << 13 | ((offset >> 19) & 1) << 11 | ((offset >> 1) & 0x7FF);
+                (hw1 as u16, hw2 as u16)
+            },
+            BranchE4 { imm } => {
+                let (hw1, hw2) = branch_offset(imm);
+                (hw1, 0x9000 | hw2)
+            },
+            BranchL { imm } => {
+                let (hw1, hw2) = branch_offset(imm);
+                (hw1, 0xD000 | hw2)
+            },
+            Msr { rn, mask, sysm } => (0xF380 | rn as u16, 0x8000 | (mask as u16) << 10 | sysm as u16),
+            Mrs { rd, sysm } => (0xF3EF, 0x8000 | (rd as u16) << 8 | sysm as u16),
+            Nop => (0xF3AF, 0x8000),
+            Yield => (0xF3AF, 0x8001),
+            Wfe => (0xF3AF, 0x8002),
+            Wfi => (0xF3AF, 0x8003),
+            Sev => (0xF3AF, 0x8004),
+            Dbg { option } => (0xF3AF, 0x80F0 | option as u16),
+            ClrEx => (0xF3BF, 0x8F2F),
+            Dsb { option } => (0xF3BF, 0x8F40 | option as u16),
+            Dmb { option } => (0xF3BF, 0x8F50 | option as u16),
+            Isb { option } => (0xF3BF, 0x8F60 | option as u16),
+            Udf { imm } => (0xF7F0 | imm >> 12, 0xA000 | (imm & 0xFFF)),
+
+            Stm { rn, w, list } => (0xE880 | (w as u16) << 5 | rn as u16, list),
+            Ldm { rn, w, list } => (0xE890 | (w as u16) << 5 | rn as u16, list),
+            Pop { list } => (0xE8BD, list),
+            Stmdb { rn, w, list } => (0xE900 | (w as u16) << 5 | rn as u16, list),
+            Ldmdb { rn, w, list } => (0xE910 | (w as u16) << 5 | rn as u16, list),
+            Push { list } => (0xE92D, list),
+
+            StrdImm { rn, rt, rt2, imm, index, add, wback } | LdrdImm { rn, rt, rt2, imm, index, add, wback } => {
+                let load = matches!(self, LdrdImm { .. });
+                let hw1 = 0xE840 | (index as u16) << 8 | (add as u16) << 7 | (wback as u16) << 5 | (load as u16) << 4 | rn as u16;
+                (hw1, (rt as u16) << 12 | (rt2 as u16) << 8 | (imm >> 2))
+            },
+            LdrdLit { rt, rt2, imm, add } => (0xE95F | (add as u16) << 7, (rt as u16) << 12 | (rt2 as u16) << 8 | (imm >> 2)),
+            StrEx { rn, rt, rd, imm } => (0xE840 | rn as u16, (rt as u16) << 12 | (rd as u16) << 8 | (imm >> 2)),
+            LdrEx { rn, rt, imm } => (0xE850 | rn as u16, (rt as u16) << 12 | 0xF00 | (imm >> 2)),
+            StrExB { rn, rt, rd } => (0xE8C0 | rn as u16, (rt as u16) << 12 | 0xF40 | rd as u16),
+            StrExH { rn, rt, rd } => (0xE8C0 | rn as u16, (rt as u16) << 12 | 0xF50 | rd as u16),
+            Tbb { rn, rm } => (0xE8D0 | rn as u16, 0xF000 | rm as u16),
+            Tbh { rn, rm } => (0xE8D0 | rn as u16, 0xF010 | rm as u16),
+            LdrExB { rn, rt } => (0xE8D0 | rn as u16, (rt as u16) << 12 | 0xF4F),
+            LdrExH { rn, rt } => (0xE8D0 | rn as u16, (rt as u16) << 12 | 0xF5F),
+
+            AndReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEA00, s, rn, rd, rm, shift),
+            TstReg { rn, rm, shift } => data_processing_shifted(0xEA00, true, rn, 0xF, rm, shift),
+            BicReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEA20, s, rn, rd, rm, shift),
+            OrrReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEA40, s, rn, rd, rm, shift),
+            MovReg { s, rd, rm } => data_processing_shifted(0xEA40, s, 0xF, rd, rm, ImmShift { ty: ShiftType::Lsl, n: 0 }),
+            LslImm { s, rd, rm, imm } => data_processing_shifted(0xEA40, s, 0xF, rd, rm, ImmShift { ty: ShiftType::Lsl, n: imm }),
+            LsrImm { s, rd, rm, imm } => data_processing_shifted(0xEA40, s, 0xF, rd, rm, ImmShift { ty: ShiftType::Lsr, n: imm }),
+            AsrImm { s, rd, rm, imm } => data_processing_shifted(0xEA40, s, 0xF, rd, rm, ImmShift { ty: ShiftType::Asr, n: imm }),
+            RorImm { s, rd, rm, imm } => data_processing_shifted(0xEA40, s, 0xF, rd, rm, ImmShift { ty: ShiftType::Ror, n: imm }),
+            Rrx { s, rd, rm } => data_processing_shifted(0xEA40, s, 0xF, rd, rm, ImmShift { ty: ShiftType::Rrx, n: 1 }),
+            OrnReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEA60, s, rn, rd, rm, shift),
+            MvnReg { s, rd, rm, shift } => data_processing_shifted(0xEA60, s, 0xF, rd, rm, shift),
+            EorReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEA80, s, rn, rd, rm, shift),
+            TeqReg { rn, rm, shift } => data_processing_shifted(0xEA80, true, rn, 0xF, rm, shift),
+            AddReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEB00, s, rn, rd, rm, shift),
+            CmnReg { rn, rm, shift } => data_processing_shifted(0xEB00, true, rn, 0xF, rm, shift),
+            AdcReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEB40, s, rn, rd, rm, shift),
+            SbcReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEB60, s, rn, rd, rm, shift),
+            SubReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEBA0, s, rn, rd, rm, shift),
+            CmpReg { rn, rm, shift } => data_processing_shifted(0xEBA0, true, rn, 0xF, rm, shift),
+            RsbReg { s, rn, rd, rm, shift } => data_processing_shifted(0xEBC0, s, rn, rd, rm, shift),
+
+            LslReg { s, rn, rd, rm } => (0xFA00 | (s as u16) << 4 | rn as u16, 0xF000 | (rd as u16) << 8 | rm as u16),
+            LsrReg { s, rn, rd, rm } => (0xFA20 | (s as u16) << 4 | rn as u16, 0xF000 | (rd as u16) << 8 | rm as u16),
+            AsrReg { s, rn, rd, rm } => (0xFA40 | (s as u16) << 4 | rn as u16, 0xF000 | (rd as u16) << 8 | rm as u16),
+            RorReg { s, rn, rd, rm } => (0xFA60 | (s as u16) << 4 | rn as u16, 0xF000 | (rd as u16) << 8 | rm as u16),
+            Sxth { rd, rm, rotation } => (0xFA0F, 0xF080 | (rd as u16) << 8 | ((rotation >> 3) as u16) << 4 | rm as u16),
+            Uxth { rd, rm, rotation } => (0xFA1F, 0xF080 | (rd as u16) << 8 | ((rotation >> 3) as u16) << 4 | rm as u16),
+            Sxtb { rd, rm, rotation } => (0xFA4F, 0xF080 | (rd as u16) << 8 | ((rotation >> 3) as u16) << 4 | rm as u16),
+            Uxtb { rd, rm, rotation } => (0xFA5F, 0xF080 | (rd as u16) << 8 | ((rotation >> 3) as u16) << 4 | rm as u16),
+            // The miscellaneous operations repeat Rm in the Rn position
+            Rev { rd, rm } => (0xFA90 | rm as u16, 0xF080 | (rd as u16) << 8 | rm as u16),
+            Rev16 { rd, rm } => (0xFA90 | rm as u16, 0xF090 | (rd as u16) << 8 | rm as u16),
+            Rbit { rd, rm } => (0xFA90 | rm as u16, 0xF0A0 | (rd as u16) << 8 | rm as u16),
+            RevSh { rd, rm } => (0xFA90 | rm as u16, 0xF0B0 | (rd as u16) << 8 | rm as u16),
+            Clz { rd, rm } => (0xFAB0 | rm as u16, 0xF080 | (rd as u16) << 8 | rm as u16),
+
+            Mul { rn, rd, rm } => (0xFB00 | rn as u16, 0xF000 | (rd as u16) << 8 | rm as u16),
+            Mla { rn, rd, rm, ra } => (0xFB00 | rn as u16, (ra as u16) << 12 | (rd as u16) << 8 | rm as u16),
+            Mls { rn, rd, rm, ra } => (0xFB00 | rn as u16, (ra as u16) << 12 | (rd as u16) << 8 | 0x10 | rm as u16),
+            SmulL { rn, rdlo, rdhi, rm } => (0xFB80 | rn as u16, (rdlo as u16) << 12 | (rdhi as u16) << 8 | rm as u16),
+            Sdiv { rn, rd, rm } => (0xFB90 | rn as u16, 0xF0F0 | (rd as u16) << 8 | rm as u16),
+            UmulL { rn, rdlo, rdhi, rm } => (0xFBA0 | rn as u16, (rdlo as u16) << 12 | (rdhi as u16) << 8 | rm as u16),
+            Udiv { rn, rd, rm } => (0xFBB0 | rn as u16, 0xF0F0 | (rd as u16) << 8 | rm as u16),
+            SmlaL { rn, rdlo, rdhi, rm } => (0xFBC0 | rn as u16, (rdlo as u16) << 12 | (rdhi as u16) << 8 | rm as u16),
+            UmlaL { rn, rdlo, rdhi, rm } => (0xFBE0 | rn as u16, (rdlo as u16) << 12 | (rdhi as u16) << 8 | rm as u16),
+
+            StrbImm { rn, rt, imm, index, add, wback } => load_store_single(false, 0, false, rn, rt, immediate_addressing(imm, index, add, wback)),
+            StrhImm { rn, rt, imm, index, add, wback } => load_store_single(false, 1, false, rn, rt, immediate_addressing(imm, index, add, wback)),
+            StrImm { rn, rt, imm, index, add, wback } => load_store_single(false, 2, false, rn, rt, immediate_addressing(imm, index, add, wback)),
+            LdrbImm { rn, rt, imm, index, add, wback } => load_store_single(false, 0, true, rn, rt, immediate_addressing(imm, index, add, wback)),
+            LdrsbImm { rn, rt, imm, index, add, wback } => load_store_single(true, 0, true, rn, rt, immediate_addressing(imm, index, add, wback)),
+            LdrhImm { rn, rt, imm, index, add, wback } => load_store_single(false, 1, true, rn, rt, immediate_addressing(imm, index, add, wback)),
+            LdrshImm { rn, rt, imm, index, add, wback } => load_store_single(true, 1, true, rn, rt, immediate_addressing(imm, index, add, wback)),
+            LdrImm { rn, rt, imm, index, add, wback } => load_store_single(false, 2, true, rn, rt, immediate_addressing(imm, index, add, wback)),
+            Strbt { rn, rt, imm } => load_store_single(false, 0, false, rn, rt, SingleAddressing::Unprivileged(imm)),
+            Strht { rn, rt, imm } => load_store_single(false, 1, false, rn, rt, SingleAddressing::Unprivileged(imm)),
+            Strt { rn, rt, imm } => load_store_single(false, 2, false, rn, rt, SingleAddressing::Unprivileged(imm)),
+            Ldrbt { rn, rt, imm } => load_store_single(false, 0, true, rn, rt, SingleAddressing::Unprivileged(imm)),
+            Ldrsbt { rn, rt, imm } => load_store_single(true, 0, true, rn, rt, SingleAddressing::Unprivileged(imm)),
+            Ldrht { rn, rt, imm } => load_store_single(false, 1, true, rn, rt, SingleAddressing::Unprivileged(imm)),
+            Ldrsht { rn, rt, imm } => load_store_single(true, 1, true, rn, rt, SingleAddressing::Unprivileged(imm)),
+            Ldrt { rn, rt, imm } => load_store_single(false, 2, true, rn, rt, SingleAddressing::Unprivileged(imm)),
+            LdrbLit { rt, imm, add } => load_store_single(false, 0, true, 0xF, rt, SingleAddressing::Literal { imm, add }),
+            LdrsbLit { rt, imm, add } => load_store_single(true, 0, true, 0xF, rt, SingleAddressing::Literal { imm, add }),
+            LdrhLit { rt, imm, add } => load_store_single(false, 1, true, 0xF, rt, SingleAddressing::Literal { imm, add }),
+            LdrshLit { rt, imm, add } => load_store_single(true, 1, true, 0xF, rt, SingleAddressing::Literal { imm, add }),
+            LdrLit { rt, imm, add } => load_store_single(false, 2, true, 0xF, rt, SingleAddressing::Literal { imm, add }),
+            StrbReg { rn, rt, rm, shift } => load_store_single(false, 0, false, rn, rt, SingleAddressing::Register { rm, shift }),
+            StrhReg { rn, rt, rm, shift } => load_store_single(false, 1, false, rn, rt, SingleAddressing::Register { rm, shift }),
+            StrReg { rn, rt, rm, shift } => load_store_single(false, 2, false, rn, rt, SingleAddressing::Register { rm, shift }),
+            LdrbReg { rn, rt, rm, shift } => load_store_single(false, 0, true, rn, rt, SingleAddressing::Register { rm, shift }),
+            LdrsbReg { rn, rt, rm, shift } => load_store_single(true, 0, true, rn, rt, SingleAddressing::Register { rm, shift }),
+            LdrhReg { rn, rt, rm, shift } => load_store_single(false, 1, true, rn, rt, SingleAddressing::Register { rm, shift }),
+            LdrshReg { rn, rt, rm, shift } => load_store_single(true, 1, true, rn, rt, SingleAddressing::Register { rm, shift }),
+            LdrReg { rn, rt, rm, shift } => load_store_single(false, 2, true, rn, rt, SingleAddressing::Register { rm, shift }),
+            PldImm { rn, imm, add } => load_store_single(false, 0, true, rn, 0xF, immediate_addressing(imm, true, add, false)),
+            PliImm { rn, imm, add } => load_store_single(true, 0, true, rn, 0xF, immediate_addressing(imm, true, add, false)),
+            PldLit { imm, add } => load_store_single(false, 0, true, 0xF, 0xF, SingleAddressing::Literal { imm, add }),
+            PliLit { imm, add } => load_store_single(true, 0, true, 0xF, 0xF, SingleAddressing::Literal { imm, add }),
+            PldReg { rn, rm, shift } => load_store_single(false, 0, true, rn, 0xF, SingleAddressing::Register { rm, shift }),
+            PliReg { rn, rm, shift } => load_store_single(true, 0, true, rn, 0xF, SingleAddressing::Register { rm, shift }),
+
+            Stc { coproc, rn, crd, imm, index, add, wback, long } => coprocessor_load_store(false, false, coproc, rn, crd, imm, index, add, wback, long),
+            Stc2 { coproc, rn, crd, imm, index, add, wback, long } => coprocessor_load_store(true, false, coproc, rn, crd, imm, index, add, wback, long),
+            LdcImm { coproc, rn, crd, imm, index, add, wback, long } => coprocessor_load_store(false, true, coproc, rn, crd, imm, index, add, wback, long),
+            Ldc2Imm { coproc, rn, crd, imm, index, add, wback, long } => coprocessor_load_store(true, true, coproc, rn, crd, imm, index, add, wback, long),
+            // P, U and W all clear is UNDEFINED, so the post-indexed subtracting literal form can only be written with W set
+            LdcLit { coproc, crd, imm, index, add, long } => coprocessor_load_store(false, true, coproc, 0xF, crd, imm, index, add, !index && !add, long),
+            Ldc2Lit { coproc, crd, imm, index, add, long } => coprocessor_load_store(true, true, coproc, 0xF, crd, imm, index, add, !index && !add, long),
+            Mcrr { coproc, opc1, rt, rt2, crm } => coprocessor_transfer_dual(false, false, coproc, opc1, rt, rt2, crm),
+            Mcrr2 { coproc, opc1, rt, rt2, crm } => coprocessor_transfer_dual(true, false, coproc, opc1, rt, rt2, crm),
+            Mrrc { coproc, opc1, rt, rt2, crm } => coprocessor_transfer_dual(false, true, coproc, opc1, rt, rt2, crm),
+            Mrrc2 { coproc, opc1, rt, rt2, crm } => coprocessor_transfer_dual(true, true, coproc, opc1, rt, rt2, crm),
+            Cdp { coproc, opc1, crd, crn, crm, opc2 } => coprocessor_data(false, coproc, opc1, crd, crn, crm, opc2),
+            Cdp2 { coproc, opc1, crd, crn, crm, opc2 } => coprocessor_data(true, coproc, opc1, crd, crn, crm, opc2),
+            Mcr { coproc, opc1, rt, crn, crm, opc2 } => coprocessor_transfer(false, false, coproc, opc1, rt, crn, crm, opc2),
+            Mcr2 { coproc, opc1, rt, crn, crm, opc2 } => coprocessor_transfer(true, false, coproc, opc1, rt, crn, crm, opc2),
+            Mrc { coproc, opc1, rt, crn, crm, opc2 } => coprocessor_transfer(false, true, coproc, opc1, rt, crn, crm, opc2),
+            Mrc2 { coproc, opc1, rt, crn, crm, opc2 } => coprocessor_transfer(true, true, coproc, opc1, rt, crn, crm, opc2),
+
+            Undefined => return None,
+        };
+
+        // Out of range fields spill into their neighbours, so anything which does not decode back has no encoding
+        if InstrThumb32::decode(hw1, hw2) == *self {
+            Some((hw1, hw2))
+        } else {
+            None
+        }
+    }
+
+    /// Data processing (modified immediate), A5.3.1
+    fn decode_data_processing_modified_immediate(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let op = bits(hw1, 8, 5);
+        let s = bit(hw1, 4);
+        let rn = bits(hw1, 3, 0);
+        let rd = bits(hw2, 11, 8);
+        let imm12 = ((bit(hw1, 10) as u16) << 11) | ((bits(hw2, 14, 12) as u16) << 8) | bits(hw2, 7, 0) as u16;
+
+        match op {
+            0b0000 if rd == 0xF && s => InstrThumb32::TstImm { rn, imm12 },
+            0b0000 => InstrThumb32::AndImm { s, rn, rd, imm12 },
+            0b0001 => InstrThumb32::BicImm { s, rn, rd, imm12 },
+            0b0010 if rn == 0xF => InstrThumb32::MovImm { s, rd, imm12 },
+            0b0010 => InstrThumb32::OrrImm { s, rn, rd, imm12 },
+            0b0011 if rn == 0xF => InstrThumb32::MvnImm { s, rd, imm12 },
+            0b0011 => InstrThumb32::OrnImm { s, rn, rd, imm12 },
+            0b0100 if rd == 0xF && s => InstrThumb32::TeqImm { rn, imm12 },
+            0b0100 => InstrThumb32::EorImm { s, rn, rd, imm12 },
+            0b1000 if rd == 0xF && s => InstrThumb32::CmnImm { rn, imm12 },
+            0b1000 => InstrThumb32::AddImm { s, rn, rd, imm12 },
+            0b1010 => InstrThumb32::AdcImm { s, rn, rd, imm12 },
+            0b1011 => InstrThumb32::SbcImm { s, rn, rd, imm12 },
+            0b1101 if rd == 0xF && s => InstrThumb32::CmpImm { rn, imm12 },
+            0b1101 => InstrThumb32::SubImm { s, rn, rd, imm12 },
+            0b1110 => InstrThumb32::RsbImm { s, rn, rd, imm12 },
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Data processing (plain binary immediate), A5.3.3
+    fn decode_data_processing_plain_immediate(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let op = bits(hw1, 8, 4);
+        let rn = bits(hw1, 3, 0);
+        let rd = bits(hw2, 11, 8);
+        let imm3 = bits(hw2, 14, 12);
+        let imm2 = bits(hw2, 7, 6);
+        let imm12 = ((bit(hw1, 10) as u16) << 11) | ((imm3 as u16) << 8) | bits(hw2, 7, 0) as u16;
+        let imm16 = ((rn as u16) << 12) | imm12;
+        let lsb = (imm3 << 2) | imm2;
+        let field = bits(hw2, 4, 0);
+        let sh = bit(hw1, 5) as u8;
+
+        match op {
+            0b00000 if rn == 0xF => InstrThumb32::Adr { rd, imm: imm12, add: true },
+            0b00000 => InstrThumb32::Addw { rn, rd, imm: imm12 },
+            0b00100 => InstrThumb32::Movw { rd, imm: imm16 },
+            0b01010 if rn == 0xF => InstrThumb32::Adr { rd, imm: imm12, add: false },
+            0b01010 => InstrThumb32::Subw { rn, rd, imm: imm12 },
+            0b01100 => InstrThumb32::Movt { rd, imm: imm16 },
+            // SSAT16 and USAT16 occupy the encodings with a zero arithmetic shift
+            0b10010 | 0b11010 if lsb == 0 => InstrThumb32::Undefined,
+            0b10000 | 0b10010 => InstrThumb32::Ssat { rn, rd, sat_imm: field + 1, shift: ImmShift::decode(sh << 1, lsb) },
+            0b11000 | 0b11010 => InstrThumb32::Usat { rn, rd, sat_imm: field, shift: ImmShift::decode(sh << 1, lsb) },
+            0b10100 => InstrThumb32::Sbfx { rn, rd, lsb, widthm1: field },
+            0b10110 if rn == 0xF => InstrThumb32::Bfc { rd, msb: field, lsb },
+            0b10110 => InstrThumb32::Bfi { rn, rd, msb: field, lsb },
+            0b11100 => InstrThumb32::Ubfx { rn, rd, lsb, widthm1: field },
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Branches and miscellaneous control, A5.3.4
+    fn decode_branch_misc_control(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let op = bits(hw1, 10, 4);
+        let op1 = bits(hw2, 14, 12);
+
+        let s = bit(hw1, 10) as u32;
+        let j1 = bit(hw2, 13) as u32;
+        let j2 = bit(hw2, 11) as u32;
+        let imm11 = (hw2 & 0x7FF) as u32;
+
+        match op1 {
+            0b000 | 0b010 if op & 0b0111000 != 0b0111000 => {
+                // Conditional branch, S:J2:J1:imm6:imm11:'0'
+                let imm6 = bits(hw1, 5, 0) as u32;
+                let offset = (s << 20) | (j2 << 19) | (j1 << 18) | (imm6 << 12) | (imm11 << 1);
+                InstrThumb32::BranchE3 { cond: bits(hw1, 9, 6), imm: sign_extend(offset, 21) }
+            },
+
+            0b000 | 0b010 => {
+                match op {
+                    0b0111000 | 0b0111001 => InstrThumb32::Msr { rn: bits(hw1, 3, 0), mask: bits(hw2, 11, 10), sysm: bits(hw2, 7, 0) },
+                    0b0111010 => InstrThumb32::decode_hint(hw2),
+                    0b0111011 => {
+                        let option = bits(hw2, 3, 0);
+                        match bits(hw2, 7, 4) {
+                            0b0010 => InstrThumb32::ClrEx,
+                            0b0100 => InstrThumb32::Dsb { option },
+                            0b0101 => InstrThumb32::Dmb { option },
+                            0b0110 => InstrThumb32::Isb { option },
+                            _ => InstrThumb32::Undefined
+                        }
+                    },
+                    0b0111110 | 0b0111111 => InstrThumb32::Mrs { rd: bits(hw2, 11, 8), sysm: bits(hw2, 7, 0) },
+                    0b1111111 if op1 == 0b010 => {
+                        InstrThumb32::Udf { imm: ((bits(hw1, 3, 0) as u16) << 12) | (hw2 & 0xFFF) }
+                    },
+                    _ => InstrThumb32::Undefined
+                }
+            },
+
+            0b001 | 0b011 | 0b101 | 0b111 => {
+                // S:I1:I2:imm10:imm11:'0' where I1 = NOT(J1 EOR S) and I2 = NOT(J2 EOR S)
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let imm10 = (hw1 & 0x3FF) as u32;
+                let offset = (s << 24) | (i1 << 23) | (i2 << 22) | (imm10 << 12) | (imm11 << 1);
+                let imm = sign_extend(offset, 25);
+
+                if op1 & 0b100 == 0 {
+                    InstrThumb32::BranchE4 { imm }
+                } else {
+                    InstrThumb32::BranchL { imm }
+                }
+            },
+
+            // BLX (immediate) switches to the ARM instruction set, which ARMv7-M does not have
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Change processor state and hints, A5.3.4
+    fn decode_hint(hw2: u16) -> InstrThumb32 {
+        if bits(hw2, 10, 8) != 0 {
+            return InstrThumb32::Undefined;
+        }
+
+        match bits(hw2, 7, 0) {
+            0x00 => InstrThumb32::Nop,
+            0x01 => InstrThumb32::Yield,
+            0x02 => InstrThumb32::Wfe,
+            0x03 => InstrThumb32::Wfi,
+            0x04 => InstrThumb32::Sev,
+            op if op & 0xF0 == 0xF0 => InstrThumb32::Dbg { option: op & 0xF },
+            // Unallocated hints execute as NOPs
+            _ => InstrThumb32::Nop
+        }
+    }
+
+    /// Load/store multiple, A5.3.5
+    fn decode_load_store_multiple(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let op = bits(hw1, 8, 7);
+        let l = bit(hw1, 4);
+        let w = bit(hw1, 5);
+        let rn = bits(hw1, 3, 0);
+        let list = hw2;
+
+        match (op, l) {
+            (0b01, false) => InstrThumb32::Stm { rn, w, list },
+            (0b01, true) if w && rn == 13 => InstrThumb32::Pop { list },
+            (0b01, true) => InstrThumb32::Ldm { rn, w, list },
+            (0b10, false) if w && rn == 13 => InstrThumb32::Push { list },
+            (0b10, false) => InstrThumb32::Stmdb { rn, w, list },
+            (0b10, true) => InstrThumb32::Ldmdb { rn, w, list },
+            // SRS and RFE are not part of ARMv7-M
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Load/store dual or exclusive, table branch, A5.3.6
+    fn decode_load_store_dual_exclusive(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let op1 = bits(hw1, 8, 7);
+        let op2 = bits(hw1, 5, 4);
+        let op3 = bits(hw2, 7, 4);
+        let rn = bits(hw1, 3, 0);
+        let rt = bits(hw2, 15, 12);
+        let rd = bits(hw2, 11, 8);
+        let imm8 = bits(hw2, 7, 0) as u16;
+
+        let index = bit(hw1, 8);
+        let add = bit(hw1, 7);
+        let wback = bit(hw1, 5);
+
+        if op1 & 0b10 == 0b10 || op2 & 0b10 == 0b10 {
+            // Load/store dual, the second register is held where single loads keep Rd
+            let imm = imm8 << 2;
+            return match op2 & 0b01 {
+                0 => InstrThumb32::StrdImm { rn, rt, rt2: rd, imm, index, add, wback },
+                _ if rn == 0xF => InstrThumb32::LdrdLit { rt, rt2: rd, imm, add },
+                _ => InstrThumb32::LdrdImm { rn, rt, rt2: rd, imm, index, add, wback },
+            };
+        }
+
+        match (op1, op2, op3) {
+            (0b00, 0b00, _) => InstrThumb32::StrEx { rn, rt, rd, imm: imm8 << 2 },
+            (0b00, 0b01, _) => InstrThumb32::LdrEx { rn, rt, imm: imm8 << 2 },
+            (0b01, 0b00, 0b0100) => InstrThumb32::StrExB { rn, rt, rd: bits(hw2, 3, 0) },
+            (0b01, 0b00, 0b0101) => InstrThumb32::StrExH { rn, rt, rd: bits(hw2, 3, 0) },
+            (0b01, 0b01, 0b0000) => InstrThumb32::Tbb { rn, rm: bits(hw2, 3, 0) },
+            (0b01, 0b01, 0b0001) => InstrThumb32::Tbh { rn, rm: bits(hw2, 3, 0) },
+            (0b01, 0b01, 0b0100) => InstrThumb32::LdrExB { rn, rt },
+            (0b01, 0b01, 0b0101) => InstrThumb32::LdrExH { rn, rt },
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Data processing (shifted register), A5.3.11
+    fn decode_data_processing_shifted_register(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let op = bits(hw1, 8, 5);
+        let s = bit(hw1, 4);
+        let rn = bits(hw1, 3, 0);
+        let rd = bits(hw2, 11, 8);
+        let rm = bits(hw2, 3, 0);
+        let ty = bits(hw2, 5, 4);
+        let imm5 = (bits(hw2, 14, 12) << 2) | bits(hw2, 7, 6);
+        let shift = ImmShift::decode(ty, imm5);
+
+        match op {
+            0b0000 if rd == 0xF && s => InstrThumb32::TstReg { rn, rm, shift },
+            0b0000 => InstrThumb32::AndReg { s, rn, rd, rm, shift },
+            0b0001 => InstrThumb32::BicReg { s, rn, rd, rm, shift },
+            0b0010 if rn == 0xF => {
+                // Move register and immediate shifts
+                match shift {
+                    ImmShift { ty: ShiftType::Lsl, n: 0 } => InstrThumb32::MovReg { s, rd, rm },
+                    ImmShift { ty: ShiftType::Lsl, n } => InstrThumb32::LslImm { s, rd, rm, imm: n },
+                    ImmShift { ty: ShiftType::Lsr, n } => InstrThumb32::LsrImm { s, rd, rm, imm: n },
+                    ImmShift { ty: ShiftType::Asr, n } => InstrThumb32::AsrImm { s, rd, rm, imm: n },
+                    ImmShift { ty: ShiftType::Ror, n } => InstrThumb32::RorImm { s, rd, rm, imm: n },
+                    ImmShift { ty: ShiftType::Rrx, .. } => InstrThumb32::Rrx { s, rd, rm },
+                }
+            },
+            0b0010 => InstrThumb32::OrrReg { s, rn, rd, rm, shift },
+            0b0011 if rn == 0xF => InstrThumb32::MvnReg { s, rd, rm, shift },
+            0b0011 => InstrThumb32::OrnReg { s, rn, rd, rm, shift },
+            0b0100 if rd == 0xF && s => InstrThumb32::TeqReg { rn, rm, shift },
+            0b0100 => InstrThumb32::EorReg { s, rn, rd, rm, shift },
+            0b1000 if rd == 0xF && s => InstrThumb32::CmnReg { rn, rm, shift },
+            0b1000 => InstrThumb32::AddReg { s, rn, rd, rm, shift },
+            0b1010 => InstrThumb32::AdcReg { s, rn, rd, rm, shift },
+            0b1011 => InstrThumb32::SbcReg { s, rn, rd, rm, shift },
+            0b1101 if rd == 0xF && s => InstrThumb32::CmpReg { rn, rm, shift },
+            0b1101 => InstrThumb32::SubReg { s, rn, rd, rm, shift },
+            0b1110 => InstrThumb32::RsbReg { s, rn, rd, rm, shift },
+            // PKHBT and PKHTB (0b0110) belong to the DSP extension
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Data processing (register) and miscellaneous operations, A5.3.12 and A5.3.15
+    fn decode_data_processing_register(hw1: u16, hw2: u16) -> InstrThumb32 {
+        if bits(hw2, 15, 12) != 0xF {
+            return InstrThumb32::Undefined;
+        }
+
+        let op1 = bits(hw1, 7, 4);
+        let op2 = bits(hw2, 7, 4);
+        let s = bit(hw1, 4);
+        let rn = bits(hw1, 3, 0);
+        let rd = bits(hw2, 11, 8);
+        let rm = bits(hw2, 3, 0);
+        let rotation = bits(hw2, 5, 4) << 3;
+
+        if op2 == 0 {
+            return match op1 >> 1 {
+                0b000 => InstrThumb32::LslReg { s, rn, rd, rm },
+                0b001 => InstrThumb32::LsrReg { s, rn, rd, rm },
+                0b010 => InstrThumb32::AsrReg { s, rn, rd, rm },
+                0b011 => InstrThumb32::RorReg { s, rn, rd, rm },
+                _ => InstrThumb32::Undefined
+            };
+        }
+
+        if op1 & 0b1000 == 0 && op2 & 0b1000 == 0b1000 && rn == 0xF {
+            // The extend and add forms (Rn != 0b1111) belong to the DSP extension
+            return match op1 {
+                0b0000 => InstrThumb32::Sxth { rd, rm, rotation },
+                0b0001 => InstrThumb32::Uxth { rd, rm, rotation },
+                0b0100 => InstrThumb32::Sxtb { rd, rm, rotation },
+                0b0101 => InstrThumb32::Uxtb { rd, rm, rotation },
+                _ => InstrThumb32::Undefined
+            };
+        }
+
+        if op1 & 0b1100 == 0b1000 && op2 & 0b1100 == 0b1000 {
+            return match (bits(hw1, 5, 4), bits(hw2, 5, 4)) {
+                (0b01, 0b00) => InstrThumb32::Rev { rd, rm },
+                (0b01, 0b01) => InstrThumb32::Rev16 { rd, rm },
+                (0b01, 0b10) => InstrThumb32::Rbit { rd, rm },
+                (0b01, 0b11) => InstrThumb32::RevSh { rd, rm },
+                (0b11, 0b00) => InstrThumb32::Clz { rd, rm },
+                _ => InstrThumb32::Undefined
+            };
+        }
+
+        InstrThumb32::Undefined
+    }
+
+    /// Multiply, multiply accumulate, and absolute difference, A5.3.16
+    fn decode_multiply(hw1: u16, hw2: u16) -> InstrThumb32 {
+        if bits(hw2, 7, 6) != 0 {
+            return InstrThumb32::Undefined;
+        }
+
+        let rn = bits(hw1, 3, 0);
+        let ra = bits(hw2, 15, 12);
+        let rd = bits(hw2, 11, 8);
+        let rm = bits(hw2, 3, 0);
+
+        match (bits(hw1, 6, 4), bits(hw2, 5, 4)) {
+            (0b000, 0b00) if ra == 0xF => InstrThumb32::Mul { rn, rd, rm },
+            (0b000, 0b00) => InstrThumb32::Mla { rn, rd, rm, ra },
+            (0b000, 0b01) => InstrThumb32::Mls { rn, rd, rm, ra },
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Long multiply, long multiply accumulate, and divide, A5.3.17
+    fn decode_long_multiply_divide(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let rn = bits(hw1, 3, 0);
+        let rdlo = bits(hw2, 15, 12);
+        let rdhi = bits(hw2, 11, 8);
+        let rm = bits(hw2, 3, 0);
+
+        match (bits(hw1, 6, 4), bits(hw2, 7, 4)) {
+            (0b000, 0b0000) => InstrThumb32::SmulL { rn, rdlo, rdhi, rm },
+            (0b001, 0b1111) => InstrThumb32::Sdiv { rn, rd: rdhi, rm },
+            (0b010, 0b0000) => InstrThumb32::UmulL { rn, rdlo, rdhi, rm },
+            (0b011, 0b1111) => InstrThumb32::Udiv { rn, rd: rdhi, rm },
+            (0b100, 0b0000) => InstrThumb32::SmlaL { rn, rdlo, rdhi, rm },
+            (0b110, 0b0000) => InstrThumb32::UmlaL { rn, rdlo, rdhi, rm },
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Store single data item, A5.3.10
+    fn decode_store_single(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let op1 = bits(hw1, 7, 5);
+        let rn = bits(hw1, 3, 0);
+        let rt = bits(hw2, 15, 12);
+
+        match SingleAddressing::decode(hw1, hw2) {
+            SingleAddressing::Imm12(imm) => match op1 {
+                0b100 => InstrThumb32::StrbImm { rn, rt, imm, index: true, add: true, wback: false },
+                0b101 => InstrThumb32::StrhImm { rn, rt, imm, index: true, add: true, wback: false },
+                0b110 => InstrThumb32::StrImm { rn, rt, imm, index: true, add: true, wback: false },
+                _ => InstrThumb32::Undefined
+            },
+            SingleAddressing::Imm8 { imm, index, add, wback } => match op1 {
+                0b000 => InstrThumb32::StrbImm { rn, rt, imm, index, add, wback },
+                0b001 => InstrThumb32::StrhImm { rn, rt, imm, index, add, wback },
+                0b010 => InstrThumb32::StrImm { rn, rt, imm, index, add, wback },
+                _ => InstrThumb32::Undefined
+            },
+            SingleAddressing::Unprivileged(imm) => match op1 {
+                0b000 => InstrThumb32::Strbt { rn, rt, imm },
+                0b001 => InstrThumb32::Strht { rn, rt, imm },
+                0b010 => InstrThumb32::Strt { rn, rt, imm },
+                _ => InstrThumb32::Undefined
+            },
+            SingleAddressing::Register { rm, shift } => match op1 {
+                0b000 => InstrThumb32::StrbReg { rn, rt, rm, shift },
+                0b001 => InstrThumb32::StrhReg { rn, rt, rm, shift },
+                0b010 => InstrThumb32::StrReg { rn, rt, rm, shift },
+                _ => InstrThumb32::Undefined
+            },
+            SingleAddressing::Literal { .. } | SingleAddressing::Undefined => InstrThumb32::Undefined,
+        }
+    }
+
+    /// Load byte, memory hints, A5.3.9
+    fn decode_load_byte(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let signed = bit(hw1, 8);
+        let rn = bits(hw1, 3, 0);
+        let rt = bits(hw2, 15, 12);
+        let addressing = SingleAddressing::decode(hw1, hw2);
+
+        if rt == 0xF {
+            // Preload hints, only the negative immediate form is allocated among the 8 bit immediates
+            return match (signed, addressing) {
+                (false, SingleAddressing::Imm12(imm)) => InstrThumb32::PldImm { rn, imm, add: true },
+                (false, SingleAddressing::Imm8 { imm, index: true, add: false, wback: false }) => InstrThumb32::PldImm { rn, imm, add: false },
+                (false, SingleAddressing::Literal { imm, add }) => InstrThumb32::PldLit { imm, add },
+                (false, SingleAddressing::Register { rm, shift }) => InstrThumb32::PldReg { rn, rm, shift },
+                (true, SingleAddressing::Imm12(imm)) => InstrThumb32::PliImm { rn, imm, add: true },
+                (true, SingleAddressing::Imm8 { imm, index: true, add: false, wback: false }) => InstrThumb32::PliImm { rn, imm, add: false },
+                (true, SingleAddressing::Literal { imm, add }) => InstrThumb32::PliLit { imm, add },
+                (true, SingleAddressing::Register { rm, shift }) => InstrThumb32::PliReg { rn, rm, shift },
+                _ => InstrThumb32::Undefined
+            };
+        }
+
+        match (signed, addressing) {
+            (false, SingleAddressing::Imm12(imm)) => InstrThumb32::LdrbImm { rn, rt, imm, index: true, add: true, wback: false },
+            (false, SingleAddressing::Imm8 { imm, index, add, wback }) => InstrThumb32::LdrbImm { rn, rt, imm, index, add, wback },
+            (false, SingleAddressing::Unprivileged(imm)) => InstrThumb32::Ldrbt { rn, rt, imm },
+            (false, SingleAddressing::Literal { imm, add }) => InstrThumb32::LdrbLit { rt, imm, add },
+            (false, SingleAddressing::Register { rm, shift }) => InstrThumb32::LdrbReg { rn, rt, rm, shift },
+            (true, SingleAddressing::Imm12(imm)) => InstrThumb32::LdrsbImm { rn, rt, imm, index: true, add: true, wback: false },
+            (true, SingleAddressing::Imm8 { imm, index, add, wback }) => InstrThumb32::LdrsbImm { rn, rt, imm, index, add, wback },
+            (true, SingleAddressing::Unprivileged(imm)) => InstrThumb32::Ldrsbt { rn, rt, imm },
+            (true, SingleAddressing::Literal { imm, add }) => InstrThumb32::LdrsbLit { rt, imm, add },
+            (true, SingleAddressing::Register { rm, shift }) => InstrThumb32::LdrsbReg { rn, rt, rm, shift },
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Load halfword, memory hints, A5.3.8
+    fn decode_load_halfword(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let signed = bit(hw1, 8);
+        let rn = bits(hw1, 3, 0);
+        let rt = bits(hw2, 15, 12);
+        let addressing = SingleAddressing::decode(hw1, hw2);
+
+        if rt == 0xF {
+            // Unallocated memory hints, treated as NOPs
+            return match addressing {
+                SingleAddressing::Undefined => InstrThumb32::Undefined,
+                _ => InstrThumb32::Nop
+            };
+        }
+
+        match (signed, addressing) {
+            (false, SingleAddressing::Imm12(imm)) => InstrThumb32::LdrhImm { rn, rt, imm, index: true, add: true, wback: false },
+            (false, SingleAddressing::Imm8 { imm, index, add, wback }) => InstrThumb32::LdrhImm { rn, rt, imm, index, add, wback },
+            (false, SingleAddressing::Unprivileged(imm)) => InstrThumb32::Ldrht { rn, rt, imm },
+            (false, SingleAddressing::Literal { imm, add }) => InstrThumb32::LdrhLit { rt, imm, add },
+            (false, SingleAddressing::Register { rm, shift }) => InstrThumb32::LdrhReg { rn, rt, rm, shift },
+            (true, SingleAddressing::Imm12(imm)) => InstrThumb32::LdrshImm { rn, rt, imm, index: true, add: true, wback: false },
+            (true, SingleAddressing::Imm8 { imm, index, add, wback }) => InstrThumb32::LdrshImm { rn, rt, imm, index, add, wback },
+            (true, SingleAddressing::Unprivileged(imm)) => InstrThumb32::Ldrsht { rn, rt, imm },
+            (true, SingleAddressing::Literal { imm, add }) => InstrThumb32::LdrshLit { rt, imm, add },
+            (true, SingleAddressing::Register { rm, shift }) => InstrThumb32::LdrshReg { rn, rt, rm, shift },
+            _ => InstrThumb32::Undefined
+        }
+    }
+
+    /// Load word, A5.3.7
+    fn decode_load_word(hw1: u16, hw2: u16) -> InstrThumb32 {
+        if bit(hw1, 8) {
+            return InstrThumb32::Undefined;
+        }
+
+        let rn = bits(hw1, 3, 0);
+        let rt = bits(hw2, 15, 12);
+
+        match SingleAddressing::decode(hw1, hw2) {
+            SingleAddressing::Imm12(imm) => InstrThumb32::LdrImm { rn, rt, imm, index: true, add: true, wback: false },
+            SingleAddressing::Imm8 { imm, index, add, wback } => InstrThumb32::LdrImm { rn, rt, imm, index, add, wback },
+            SingleAddressing::Unprivileged(imm) => InstrThumb32::Ldrt { rn, rt, imm },
+            SingleAddressing::Literal { imm, add } => InstrThumb32::LdrLit { rt, imm, add },
+            SingleAddressing::Register { rm, shift } => InstrThumb32::LdrReg { rn, rt, rm, shift },
+            SingleAddressing::Undefined => InstrThumb32::Undefined,
+        }
+    }
+
+    /// Coprocessor instructions, A5.3.18
+    fn decode_coprocessor(hw1: u16, hw2: u16) -> InstrThumb32 {
+        let two = bit(hw1, 12);
+        let op1 = bits(hw1, 9, 4);
+        let op = bit(hw2, 4);
+
+        let coproc = bits(hw2, 11, 8);
+        let rn = bits(hw1, 3, 0);
+        let crd = bits(hw2, 15, 12);
+        let crm = bits(hw2, 3, 0);
+
+        if op1 & 0b111110 == 0b000000 {
+            return InstrThumb32::Undefined;
+        }
+
+        if op1 & 0b100000 == 0 {
+            if op1 & 0b111110 == 0b000100 {
+                // Two register transfers, Rt2 is held in the Rn position
+                let opc1 = bits(hw2, 7, 4);
+                let rt = crd;
+                let rt2 = rn;
+                return match (op1 & 1 == 1, two) {
+                    (false, false) => InstrThumb32::Mcrr { coproc, opc1, rt, rt2, crm },
+                    (false, true) => InstrThumb32::Mcrr2 { coproc, opc1, rt, rt2, crm },
+                    (true, false) => InstrThumb32::Mrrc { coproc, opc1, rt, rt2, crm },
+                    (true, true) => InstrThumb32::Mrrc2 { coproc, opc1, rt, rt2, crm },
+                };
+            }
+
+            let index = bit(hw1, 8);
+            let add = bit(hw1, 7);
+            let long = bit(hw1, 6);
+            let wback = bit(hw1, 5);
+            let imm = (bits(hw2, 7, 0) as u16) << 2;
+
+            return match (op1 & 1 == 1, rn == 0xF, two) {
+                (false, _, false) => InstrThumb32::Stc { coproc, rn, crd, imm, index, add, wback, long },
+                (false, _, true) => InstrThumb32::Stc2 { coproc, rn, crd, imm, index, add, wback, long },
+                (true, false, false) => InstrThumb32::LdcImm { coproc, rn, crd, imm, index, add, wback, long },
+                (true, false, true) => InstrThumb32::Ldc2Imm { coproc, rn, crd, imm, index, add, wback, long },
+                (true, true, false) => InstrThumb32::LdcLit { coproc, crd, imm, index, add, long },
+                (true, true, true) => InstrThumb32::Ldc2Lit { coproc, crd, imm, index, add, long },
+            };
+        }
+
+        if op1 & 0b110000 != 0b100000 {
+            return InstrThumb32::Undefined;
+        }
+
+        let crn = rn;
+        let opc2 = bits(hw2, 7, 5);
+
+        if !op {
+            let opc1 = bits(hw1, 7, 4);
+            return match two {
+                false => InstrThumb32::Cdp { coproc, opc1, crd, crn, crm, opc2 },
+                true => InstrThumb32::Cdp2 { coproc, opc1, crd, crn, crm, opc2 },
+            };
+        }
+
+        let opc1 = bits(hw1, 7, 5);
+        let rt = crd;
+        match (op1 & 1 == 1, two) {
+            (false, false) => InstrThumb32::Mcr { coproc, opc1, rt, crn, crm, opc2 },
+            (false, true) => InstrThumb32::Mcr2 { coproc, opc1, rt, crn, crm, opc2 },
+            (true, false) => InstrThumb32::Mrc { coproc, opc1, rt, crn, crm, opc2 },
+            (true, true) => InstrThumb32::Mrc2 { coproc, opc1, rt, crn, crm, opc2 },
+        }
+    }
+}
+
+/// The addressing forms shared by the single data item loads and stores
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SingleAddressing {
+    Imm12(u16),
+    Imm8 { imm: u16, index: bool, add: bool, wback: bool },
+    Unprivileged(u8),
+    // Only loads have a literal form, a store with a base of PC is UNDEFINED
+    Literal { imm: u16, add: bool },
+    Register { rm: u8, shift: u8 },
+    Undefined,
+}
+
+impl SingleAddressing {
+    fn decode(hw1: u16, hw2: u16) -> SingleAddressing {
+        let rn = bits(hw1, 3, 0);
+        let is_load = bit(hw1, 4);
+
+        if rn == 0xF {
+            return match is_load {
+                true => SingleAddressing::Literal { imm: hw2 & 0xFFF, add: bit(hw1, 7) },
+                false => SingleAddressing::Undefined,
+            };
+        }
+
+        if bit(hw1, 7) {
+            return SingleAddressing::Imm12(hw2 & 0xFFF);
+        }
+
+        let op2 = bits(hw2, 11, 6);
+        if op2 == 0 {
+            return SingleAddressing::Register { rm: bits(hw2, 3, 0), shift: bits(hw2, 5, 4) };
+        }
+
+        if op2 & 0b100000 == 0 {
+            return SingleAddressing::Undefined;
+        }
+
+        let index = bit(hw2, 10);
+        let add = bit(hw2, 9);
+        let wback = bit(hw2, 8);
+        let imm8 = bits(hw2, 7, 0);
+
+        match (index, add, wback) {
+            (true, true, false) => SingleAddressing::Unprivileged(imm8),
+            (false, _, false) => SingleAddressing::Undefined,
+            _ => SingleAddressing::Imm8 { imm: imm8 as u16, index, add, wback },
+        }
+    }
+
+    /// Returns the bits this addressing form contributes to the first and second halfwords
+    fn encode(&self) -> (u16, u16) {
+        match *self {
+            SingleAddressing::Imm12(imm) => (1 << 7, imm),
+            SingleAddressing::Imm8 { imm, index, add, wback } => {
+                (0, 0x800 | (index as u16) << 10 | (add as u16) << 9 | (wback as u16) << 8 | imm)
+            },
+            SingleAddressing::Unprivileged(imm) => (0, 0xE00 | imm as u16),
+            SingleAddressing::Literal { imm, add } => ((add as u16) << 7, imm),
+            SingleAddressing::Register { rm, shift } => (0, (shift as u16) << 4 | rm as u16),
+            SingleAddressing::Undefined => (0, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_matches_assembler_output() {
+        // Halfword pairs as emitted by the assembler, paired with the expected decoding
+        let expected = [
+            (0xF002, 0x01FF, InstrThumb32::AndImm { s: false, rn: 2, rd: 1, imm12: 0x0FF }),           // and r1, r2, #0xff
+            (0xF012, 0x11FF, InstrThumb32::AndImm { s: true, rn: 2, rd: 1, imm12: 0x1FF }),            // ands r1, r2, #0x00ff00ff
+            (0xF013, 0x0F01, InstrThumb32::TstImm { rn: 3, imm12: 0x001 }),                            // tst.w r3, #1
+            (0xF04F, 0x5090, InstrThumb32::MovImm { s: false, rd: 0, imm12: 0x590 }),                  // mov.w r0, #0x12000000
+            (0xF5B5, 0x7F80, InstrThumb32::CmpImm { rn: 5, imm12: 0xF80 }),                            // cmp.w r5, #256
+            (0xF602, 0x71FF, InstrThumb32::Addw { rn: 2, rd: 1, imm: 0xFFF }),                         // addw r1, r2, #4095
+            (0xF2AD, 0x0D08, InstrThumb32::Subw { rn: 13, rd: 13, imm: 8 }),                           // subw sp, sp, #8
+            (0xF64B, 0x63EF, InstrThumb32::Movw { rd: 3, imm: 0xBEEF }),                               // movw r3, #0xbeef
+            (0xF6CD, 0x63AD, InstrThumb32::Movt { rd: 3, imm: 0xDEAD }),                               // movt r3, #0xdead
+            (0xF302, 0x01C7, InstrThumb32::Ssat { rn: 2, rd: 1, sat_imm: 8, shift: ImmShift { ty: ShiftType::Lsl, n: 3 } }),
+            (0xF3A2, 0x0188, InstrThumb32::Usat { rn: 2, rd: 1, sat_imm: 8, shift: ImmShift { ty: ShiftType::Asr, n: 2 } }),
+            (0xF342, 0x01C4, InstrThumb32::Sbfx { rn: 2, rd: 1, lsb: 3, widthm1: 4 }),                 // sbfx r1, r2, #3, #5
+            (0xF362, 0x110B, InstrThumb32::Bfi { rn: 2, rd: 1, msb: 11, lsb: 4 }),                     // bfi r1, r2, #4, #8
+            (0xF36F, 0x110B, InstrThumb32::Bfc { rd: 1, msb: 11, lsb: 4 }),                            // bfc r1, #4, #8
+            (0xF383, 0x8811, InstrThumb32::Msr { rn: 3, mask: 2, sysm: 0x11 }),                        // msr basepri, r3
+            (0xF3EF, 0x8210, InstrThumb32::Mrs { rd: 2, sysm: 0x10 }),                                 // mrs r2, primask
+            (0xF3AF, 0x8003, InstrThumb32::Wfi),                                                       // wfi.w
+            (0xF3BF, 0x8F5F, InstrThumb32::Dmb { option: 0xF }),                                       // dmb sy
+            (0xF3BF, 0x8F2F, InstrThumb32::ClrEx),                                                     // clrex
+            (0xF7F1, 0xA234, InstrThumb32::Udf { imm: 0x1234 }),                                       // udf.w #0x1234
+            (0xF001, 0xF809, InstrThumb32::BranchL { imm: 4114 }),                                     // bl .+4118
+            (0xF001, 0xB807, InstrThumb32::BranchE4 { imm: 4110 }),                                    // b.w .+4114
+            (0xF000, 0x8004, InstrThumb32::BranchE3 { cond: 0, imm: 8 }),                              // beq.w .+12
+            (0xF7FD, 0xFFFD, InstrThumb32::BranchL { imm: -8198 }),                                    // bl .-8194
+            (0xF73D, 0xAFF9, InstrThumb32::BranchE3 { cond: 12, imm: -8206 }),                         // bgt.w .-8202
+            (0xE8B1, 0x010C, InstrThumb32::Ldm { rn: 1, w: true, list: 0x010C }),                      // ldm.w r1!, {r2, r3, r8}
+            (0xE8BD, 0x8FF0, InstrThumb32::Pop { list: 0x8FF0 }),                                      // pop.w {r4-r11, pc}
+            (0xE92D, 0x4FF0, InstrThumb32::Push { list: 0x4FF0 }),                                     // push.w {r4-r11, lr}
+            (0xE900, 0x0006, InstrThumb32::Stmdb { rn: 0, w: false, list: 0x0006 }),                   // stmdb r0, {r1, r2}
+            (0xE96D, 0x2302, InstrThumb32::StrdImm { rn: 13, rt: 2, rt2: 3, imm: 8, index: true, add: false, wback: true }),
+            (0xE9D0, 0x2304, InstrThumb32::LdrdImm { rn: 0, rt: 2, rt2: 3, imm: 16, index: true, add: true, wback: false }),
+            (0xE852, 0x1F01, InstrThumb32::LdrEx { rn: 2, rt: 1, imm: 4 }),                            // ldrex r1, [r2, #4]
+            (0xE842, 0x1000, InstrThumb32::StrEx { rn: 2, rt: 1, rd: 0, imm: 0 }),                     // strex r0, r1, [r2]
+            (0xE8C2, 0x1F50, InstrThumb32::StrExH { rn: 2, rt: 1, rd: 0 }),                            // strexh r0, r1, [r2]
+            (0xE8D2, 0xF011, InstrThumb32::Tbh { rn: 2, rm: 1 }),                                      // tbh [r2, r1, lsl #1]
+            (0xEB02, 0x0183, InstrThumb32::AddReg { s: false, rn: 2, rd: 1, rm: 3, shift: ImmShift { ty: ShiftType::Lsl, n: 2 } }),
+            (0xEB12, 0x0123, InstrThumb32::AddReg { s: true, rn: 2, rd: 1, rm: 3, shift: ImmShift { ty: ShiftType::Asr, n: 32 } }),
+            (0xEBB1, 0x0F52, InstrThumb32::CmpReg { rn: 1, rm: 2, shift: ImmShift { ty: ShiftType::Lsr, n: 1 } }),
+            (0xEA4F, 0x0102, InstrThumb32::MovReg { s: false, rd: 1, rm: 2 }),                         // mov.w r1, r2
+            (0xEA5F, 0x0112, InstrThumb32::LsrImm { s: true, rd: 1, rm: 2, imm: 32 }),                 // lsrs.w r1, r2, #32
+            (0xEA4F, 0x0132, InstrThumb32::Rrx { s: false, rd: 1, rm: 2 }),                            // rrx r1, r2
+            (0xEA6F, 0x1132, InstrThumb32::MvnReg { s: false, rd: 1, rm: 2, shift: ImmShift { ty: ShiftType::Ror, n: 4 } }),
+            (0xFA52, 0xF103, InstrThumb32::AsrReg { s: true, rn: 2, rd: 1, rm: 3 }),                   // asrs.w r1, r2, r3
+            (0xFA0F, 0xF192, InstrThumb32::Sxth { rd: 1, rm: 2, rotation: 8 }),                        // sxth.w r1, r2, ror #8
+            (0xFA92, 0xF1A2, InstrThumb32::Rbit { rd: 1, rm: 2 }),                                     // rbit r1, r2
+            (0xFAB2, 0xF182, InstrThumb32::Clz { rd: 1, rm: 2 }),                                      // clz r1, r2
+            (0xFB02, 0xF103, InstrThumb32::Mul { rn: 2, rd: 1, rm: 3 }),                               // mul r1, r2, r3
+            (0xFB02, 0x4113, InstrThumb32::Mls { rn: 2, rd: 1, rm: 3, ra: 4 }),                        // mls r1, r2, r3, r4
+            (0xFB83, 0x1204, InstrThumb32::SmulL { rn: 3, rdlo: 1, rdhi: 2, rm: 4 }),                  // smull r1, r2, r3, r4
+            (0xFBB2, 0xF1F3, InstrThumb32::Udiv { rn: 2, rd: 1, rm: 3 }),                              // udiv r1, r2, r3
+            (0xF8D2, 0x1123, InstrThumb32::LdrImm { rn: 2, rt: 1, imm: 0x123, index: true, add: true, wback: false }),
+            (0xF852, 0x1B04, InstrThumb32::LdrImm { rn: 2, rt: 1, imm: 4, index: false, add: true, wback: true }),
+            (0xF852, 0x1E04, InstrThumb32::Ldrt { rn: 2, rt: 1, imm: 4 }),                             // ldrt r1, [r2, #4]
+            (0xF852, 0x1023, InstrThumb32::LdrReg { rn: 2, rt: 1, rm: 3, shift: 2 }),                  // ldr.w r1, [r2, r3, lsl #2]
+            (0xF85F, 0x1008, InstrThumb32::LdrLit { rt: 1, imm: 8, add: false }),                      // ldr.w r1, [pc, #-8]
+            (0xF832, 0x1003, InstrThumb32::LdrhReg { rn: 2, rt: 1, rm: 3, shift: 0 }),                 // ldrh.w r1, [r2, r3]
+            (0xF9BF, 0x1004, InstrThumb32::LdrshLit { rt: 1, imm: 4, add: true }),                     // ldrsh.w r1, [pc, #4]
+            (0xF822, 0x1D02, InstrThumb32::StrhImm { rn: 2, rt: 1, imm: 2, index: true, add: false, wback: true }),
+            (0xF842, 0x1E00, InstrThumb32::Strt { rn: 2, rt: 1, imm: 0 }),                             // strt r1, [r2]
+            (0xF891, 0xF004, InstrThumb32::PldImm { rn: 1, imm: 4, add: true }),                       // pld [r1, #4]
+            (0xF911, 0xFC04, InstrThumb32::PliImm { rn: 1, imm: 4, add: false }),                      // pli [r1, #-4]
+            (0xEE07, 0x0F95, InstrThumb32::Mcr { coproc: 15, opc1: 0, rt: 0, crn: 7, crm: 5, opc2: 4 }),
+            (0xEE33, 0x2EB4, InstrThumb32::Mrc { coproc: 14, opc1: 1, rt: 2, crn: 3, crm: 4, opc2: 5 }),
+            (0xEC42, 0x1734, InstrThumb32::Mcrr { coproc: 7, opc1: 3, rt: 1, rt2: 2, crm: 4 }),
+            (0xEE22, 0x1583, InstrThumb32::Cdp { coproc: 5, opc1: 2, crd: 1, crn: 2, crm: 3, opc2: 4 }),
+            (0xEDB1, 0x2402, InstrThumb32::LdcImm { coproc: 4, rn: 1, crd: 2, imm: 8, index: true, add: true, wback: true, long: false }),
+        ];
+
+        for (hw1, hw2, decoded) in expected.iter() {
+            assert!(InstrThumb32::is_prefix(*hw1));
+            assert_eq!(InstrThumb32::decode(*hw1, *hw2), *decoded, "decoding {:#06X} {:#06X}", hw1, hw2);
+            assert_eq!(decoded.encode(), Some((*hw1, *hw2)), "encoding {:?}", decoded);
+        }
+    }
+
+    #[test]
+    fn encode_round_trips_decode() {
+        // Every first halfword, each paired with a spread of second halfwords from a simple LCG
+        let mut seed = 0x2545_F491u32;
+        for hw1 in 0xE800..=0xFFFF {
+            for _ in 0..64 {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let hw2 = (seed >> 16) as u16;
+
+                let decoded = InstrThumb32::decode(hw1, hw2);
+                if decoded == InstrThumb32::Undefined {
+                    continue;
+                }
+
+                let (e1, e2) = decoded.encode().unwrap_or_else(|| panic!("no encoding for {:?} ({:#06X} {:#06X})", decoded, hw1, hw2));
+                assert_eq!(InstrThumb32::decode(e1, e2), decoded, "{:#06X} {:#06X} encoded as {:#06X} {:#06X}", hw1, hw2, e1, e2);
+            }
+        }
+
+        assert_eq!(InstrThumb32::BranchL { imm: 3 }.encode(), None);
+        assert_eq!(InstrThumb32::LdrImm { rn: 1, rt: 2, imm: 0x1000, index: true, add: true, wback: false }.encode(), None);
+        assert_eq!(InstrThumb32::Undefined.encode(), None);
+    }
+}
@@ -0,0 +1,262 @@
+//! Detection of UNPREDICTABLE decodings
+//!
+//! Many encodings are UNPREDICTABLE for some of their operand values, such as an empty register list or a write to the
+//! PC from within an IT block. The decoder accepts these, as real cores do, and the checks here tag them with the
+//! reason so that the processor can apply its policy. Some of the conditions depend on the position of the
+//! instruction within an IT block, which the caller supplies.
+//!
+//! Conditions which depend on run time values, such as the alignment of an address loaded into the PC, are checked
+//! during execution instead.
+
+use std::fmt;
+
+use crate::instructions::{ InstrThumb16, InstrThumb32 };
+
+/// The position of an instruction relative to an IT block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItPosition {
+    Outside,
+    Inside,
+    Last,
+}
+
+impl ItPosition {
+    /// InITBlock() from the ARMv7-M Architecture Reference Manual
+    pub fn in_it_block(self) -> bool {
+        self != ItPosition::Outside
+    }
+
+    /// InITBlock() && !LastInITBlock(), the position at which writes to the PC are UNPREDICTABLE
+    pub fn inside_not_last(self) -> bool {
+        self == ItPosition::Inside
+    }
+}
+
+/// The reason a decoded instruction is UNPREDICTABLE
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unpredictable {
+    /// The instruction is not permitted within an IT block
+    InItBlock,
+
+    /// The instruction writes the PC and is within an IT block, but not its last instruction
+    NotLastInItBlock,
+
+    /// The register list holds fewer registers than the instruction requires
+    RegisterCount,
+
+    /// The register list holds a register which is not permitted, such as the SP
+    RegisterList,
+
+    /// The base register is written back and also appears in the register list
+    BaseInList,
+
+    /// A register operand is the PC where that is not permitted
+    PcOperand,
+
+    /// A register operand is the SP or the PC, BadReg() from the ARMv7-M Architecture Reference Manual
+    BadReg,
+
+    /// The special register of an MRS or MSR is not implemented, or the fields written are not permitted
+    SpecialRegister,
+
+    /// The high register encoding is used with only low registers
+    LowRegisters,
+
+    /// The first condition of an IT instruction is not permitted
+    ItCondition,
+
+    /// The instruction has no effect
+    NoEffect,
+}
+
+impl fmt::Display for Unpredictable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            Unpredictable::InItBlock => "not permitted in an IT block",
+            Unpredictable::NotLastInItBlock => "writes the PC in an IT block but is not the last instruction of the block",
+            Unpredictable::RegisterCount => "too few registers in the register list",
+            Unpredictable::RegisterList => "register not permitted in the register list",
+            Unpredictable::BaseInList => "written back base register is in the register list",
+            Unpredictable::PcOperand => "PC not permitted as an operand",
+            Unpredictable::BadReg => "SP or PC not permitted as an operand",
+            Unpredictable::SpecialRegister => "special register or fields not permitted",
+            Unpredictable::LowRegisters => "high register encoding with only low registers",
+            Unpredictable::ItCondition => "first condition not permitted",
+            Unpredictable::NoEffect => "has no effect",
+        };
+
+        write!(f, "{}", reason)
+    }
+}
+
+const SP_BIT: u16 = 1 << 13;
+const LR_BIT: u16 = 1 << 14;
+const PC_BIT: u16 = 1 << 15;
+
+impl InstrThumb16 {
+    /// Checks whether the instruction, decoded from the halfword `encoded`, is UNPREDICTABLE at the given IT position
+    ///
+    /// The halfword is needed because some instructions decode identically from more than one encoding, and only one
+    /// of those encodings is constrained.
+    pub fn unpredictable(&self, encoded: u16, it: ItPosition) -> Option<Unpredictable> {
+        use InstrThumb16::*;
+
+        match *self {
+            AddRegHigh { rm: 15, rdn: 15 } => Some(Unpredictable::PcOperand),
+            AddRegHigh { rdn: 15, .. } | MovReg { rd: 15, .. } if it.inside_not_last() => Some(Unpredictable::NotLastInItBlock),
+
+            BranchE1 { .. } | Cbz { .. } | Cbnz { .. } | Cps { .. } if it.in_it_block() => Some(Unpredictable::InItBlock),
+            BranchE2 { .. } | BranchX { .. } | BranchLx { .. } if it.inside_not_last() => Some(Unpredictable::NotLastInItBlock),
+            BranchLx { rm: 15 } => Some(Unpredictable::PcOperand),
+
+            CmpReg { rm, rn } if encoded & 0xFF00 == 0x4500 => {
+                if rm < 8 && rn < 8 {
+                    Some(Unpredictable::LowRegisters)
+                } else if rm == 15 || rn == 15 {
+                    Some(Unpredictable::PcOperand)
+                } else {
+                    None
+                }
+            },
+
+            Cps { fi: 0, ff: 0, .. } => Some(Unpredictable::NoEffect),
+
+            IfThen { .. } if it.in_it_block() => Some(Unpredictable::InItBlock),
+            IfThen { cond: 0b1111, .. } => Some(Unpredictable::ItCondition),
+            IfThen { cond: 0b1110, mask } if mask.count_ones() != 1 => Some(Unpredictable::ItCondition),
+
+            Ldm { list: 0, .. } | Stm { list: 0, .. } | Push { list: 0 } | Pop { list: 0 } => Some(Unpredictable::RegisterCount),
+            Pop { list } if list & PC_BIT != 0 && it.inside_not_last() => Some(Unpredictable::NotLastInItBlock),
+
+            _ => None,
+        }
+    }
+}
+
+impl InstrThumb32 {
+    /// Checks whether the instruction is UNPREDICTABLE at the given IT position
+    pub fn unpredictable(&self, it: ItPosition) -> Option<Unpredictable> {
+        use InstrThumb32::*;
+
+        match *self {
+            BranchE3 { .. } if it.in_it_block() => Some(Unpredictable::InItBlock),
+            BranchE4 { .. } | BranchL { .. } if it.inside_not_last() => Some(Unpredictable::NotLastInItBlock),
+
+            Ldm { rn, w, list } | Ldmdb { rn, w, list } => {
+                if rn == 15 {
+                    Some(Unpredictable::PcOperand)
+                } else {
+                    load_multiple(list, it).or_else(|| writeback(rn, w, list))
+                }
+            },
+
+            Pop { list } => load_multiple(list, it),
+
+            Stm { rn, w, list } | Stmdb { rn, w, list } => {
+                if rn == 15 {
+                    Some(Unpredictable::PcOperand)
+                } else {
+                    store_multiple(list).or_else(|| writeback(rn, w, list))
+                }
+            },
+
+            Push { list } => store_multiple(list),
+
+            Mrs { rd: 13, .. } | Mrs { rd: 15, .. } | Msr { rn: 13, .. } | Msr { rn: 15, .. } => Some(Unpredictable::BadReg),
+
+            // Only the APSR forms may write other than the NZCVQ fields
+            Mrs { sysm, .. } if !special_register(sysm) => Some(Unpredictable::SpecialRegister),
+            Msr { sysm, mask, .. } if !special_register(sysm) || mask == 0 || (mask != 0b10 && sysm > 3) => {
+                Some(Unpredictable::SpecialRegister)
+            },
+
+            _ => None,
+        }
+    }
+}
+
+/// Whether a SYSm value names one of the special registers of MRS and MSR
+fn special_register(sysm: u8) -> bool {
+    matches!(sysm, 0..=3 | 5..=9 | 16..=20)
+}
+
+fn load_multiple(list: u16, it: ItPosition) -> Option<Unpredictable> {
+    if list.count_ones() < 2 {
+        Some(Unpredictable::RegisterCount)
+    } else if list & SP_BIT != 0 || list & (PC_BIT | LR_BIT) == PC_BIT | LR_BIT {
+        Some(Unpredictable::RegisterList)
+    } else if list & PC_BIT != 0 && it.inside_not_last() {
+        Some(Unpredictable::NotLastInItBlock)
+    } else {
+        None
+    }
+}
+
+fn store_multiple(list: u16) -> Option<Unpredictable> {
+    if list.count_ones() < 2 {
+        Some(Unpredictable::RegisterCount)
+    } else if list & (SP_BIT | PC_BIT) != 0 {
+        Some(Unpredictable::RegisterList)
+    } else {
+        None
+    }
+}
+
+fn writeback(rn: u8, w: bool, list: u16) -> Option<Unpredictable> {
+    if w && list & (1 << rn) != 0 {
+        Some(Unpredictable::BaseInList)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unpredictable_thumb16() {
+        let table = InstrThumb16::generate_decode_table();
+        let check = |encoded: u16, it: ItPosition| table[encoded as usize].unpredictable(encoded, it);
+
+        assert_eq!(check(0x44FF, ItPosition::Outside), Some(Unpredictable::PcOperand));             // add pc, pc
+        assert_eq!(check(0x4487, ItPosition::Outside), None);                                       // add pc, r0
+        assert_eq!(check(0x4487, ItPosition::Inside), Some(Unpredictable::NotLastInItBlock));
+        assert_eq!(check(0x4487, ItPosition::Last), None);
+        assert_eq!(check(0x4687, ItPosition::Inside), Some(Unpredictable::NotLastInItBlock));       // mov pc, r0
+        assert_eq!(check(0xB400, ItPosition::Outside), Some(Unpredictable::RegisterCount));         // push {}
+        assert_eq!(check(0xB500, ItPosition::Outside), None);                                       // push {lr}
+        assert_eq!(check(0xBD00, ItPosition::Inside), Some(Unpredictable::NotLastInItBlock));       // pop {pc}
+        assert_eq!(check(0x4291, ItPosition::Outside), None);                                       // cmp r1, r2
+        assert_eq!(check(0x4511, ItPosition::Outside), Some(Unpredictable::LowRegisters));          // cmp r1, r2 (high form)
+        assert_eq!(check(0x45F9, ItPosition::Outside), Some(Unpredictable::PcOperand));             // cmp r9, pc
+        assert_eq!(check(0xD0FE, ItPosition::Last), Some(Unpredictable::InItBlock));                // beq .
+        assert_eq!(check(0xE7FE, ItPosition::Last), None);                                          // b .
+        assert_eq!(check(0xB1E9, ItPosition::Inside), Some(Unpredictable::InItBlock));              // cbz r1, .+62
+        assert_eq!(check(0xBFF8, ItPosition::Outside), Some(Unpredictable::ItCondition));           // it nv
+        assert_eq!(check(0xBFEC, ItPosition::Outside), Some(Unpredictable::ItCondition));           // ite al
+        assert_eq!(check(0xBFE4, ItPosition::Outside), None);                                       // itt al
+        assert_eq!(check(0xB660, ItPosition::Outside), Some(Unpredictable::NoEffect));              // cpsie
+        assert_eq!(check(0x47F8, ItPosition::Outside), Some(Unpredictable::PcOperand));             // blx pc
+    }
+
+    #[test]
+    fn unpredictable_thumb32() {
+        let check = |hw1: u16, hw2: u16, it: ItPosition| InstrThumb32::decode(hw1, hw2).unpredictable(it);
+
+        assert_eq!(check(0xE8BD, 0x8FF0, ItPosition::Outside), None);                               // pop.w {r4-r11, pc}
+        assert_eq!(check(0xE8BD, 0x8FF0, ItPosition::Inside), Some(Unpredictable::NotLastInItBlock));
+        assert_eq!(check(0xE8BD, 0xC000, ItPosition::Outside), Some(Unpredictable::RegisterList));  // pop.w {lr, pc}
+        assert_eq!(check(0xE92D, 0x4FF0, ItPosition::Outside), None);                               // push.w {r4-r11, lr}
+        assert_eq!(check(0xE92D, 0x2010, ItPosition::Outside), Some(Unpredictable::RegisterList));  // push.w {r4, sp}
+        assert_eq!(check(0xE8B1, 0x0006, ItPosition::Outside), Some(Unpredictable::BaseInList));    // ldm.w r1!, {r1, r2}
+        assert_eq!(check(0xE891, 0x0002, ItPosition::Outside), Some(Unpredictable::RegisterCount)); // ldm.w r1, {r1}
+        assert_eq!(check(0xF000, 0xF800, ItPosition::Inside), Some(Unpredictable::NotLastInItBlock)); // bl
+        assert_eq!(check(0xF000, 0x8000, ItPosition::Last), Some(Unpredictable::InItBlock));        // beq.w
+        assert_eq!(check(0xF383, 0x8811, ItPosition::Outside), None);                               // msr basepri, r3
+        assert_eq!(check(0xF38D, 0x8811, ItPosition::Outside), Some(Unpredictable::BadReg));        // msr basepri, sp
+        assert_eq!(check(0xF383, 0x8411, ItPosition::Outside), Some(Unpredictable::SpecialRegister)); // msr basepri (g), r3
+        assert_eq!(check(0xF3EF, 0x8004, ItPosition::Outside), Some(Unpredictable::SpecialRegister)); // mrs r0, #4
+        assert_eq!(check(0xF3EF, 0x8F10, ItPosition::Outside), Some(Unpredictable::BadReg));        // mrs pc, primask
+    }
+}
@@ -5,6 +5,10 @@
 #[macro_use]
 pub mod decode;
 pub mod instructions;
+pub mod exception;
 pub mod memory;
+pub mod nvic;
+pub mod scs;
+pub mod systick;
 pub mod loader;
 pub mod processor;
\ No newline at end of file
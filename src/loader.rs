@@ -2,7 +2,7 @@ use std::path::Path;
 
 extern crate elfy;
 use elfy::{ Elf, ParseElfResult };
-use elfy::types::{ Segment, ProgramHeaderType, ProgramHeaderFlags };
+use elfy::types::Segment;
 
 use crate::memory::Memory;
 
@@ -15,12 +15,16 @@ impl ProgramLoader {
     pub fn from_elf<P: AsRef<Path>>(path: P) -> ParseElfResult<ProgramLoader> {
         let elf = Elf::load(path)?;
         let loader = ProgramLoader {
-            elf: elf
+            elf
         };
 
         Ok(loader)
     }
 
+    pub fn elf(&self) -> &Elf {
+        &self.elf
+    }
+
     pub fn load<P: AsRef<Path>>(path: P) -> ParseElfResult<ProgramImage> {
         let elf = Elf::load(path)?;
         
@@ -33,7 +37,7 @@ impl ProgramLoader {
         
         let mut mem = Memory::alloc(required_memory);
         for segment in elf.segments() {
-            ProgramLoader::map_segment(&mut mem, &segment);
+            ProgramLoader::map_segment(&mut mem, segment);
         }
 
         let entry = elf.header().entry(); 
@@ -51,7 +55,8 @@ impl ProgramLoader {
         let bytes = seg.data().as_slice();
         assert_eq!(bytes.len(), seg.header().memory_size());
 
-        mem.write_bytes(address, bytes); // todo: error handling using "?"
+        // The memory is allocated to hold every segment
+        mem.write_bytes(address, bytes).expect("segment is within the allocated memory");
     }
 }
 
@@ -1,11 +1,13 @@
 use armchair::loader::ProgramLoader;
-use armchair::processor::Processor;
+use armchair::processor::{ Processor, ResetSource };
 
 fn main() {
     let image = ProgramLoader::load("thumbv7m-test-binary").unwrap();
     let mut processor = Processor::new();
-    processor.init();
     processor.load(image);
+
+    // The test binary has no vector table
+    processor.set_reset_source(ResetSource::ImageEntry);
     processor.reset();
     processor.run();
 }
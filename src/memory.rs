@@ -1,8 +1,11 @@
 // ARMv7M-M Memory Model
 
 use std::ops::{ Index, IndexMut };
- 
+
+use crate::instructions::{ ConditionFlags, ItState };
+
 /// Addressable registers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Register {
     // Thumb16 addressable
     R0, R1, R2, R3,
@@ -12,8 +15,11 @@ pub enum Register {
     R8, R9, R10, R11, R12,
 
     // Stack Pointer
-    SPM,
-    SPP,
+    //   SP is whichever of the banked stack pointers is current, selected by CONTROL.SPSEL in Thread mode.
+    //   Handler mode always uses the main stack pointer.
+    SP,
+    MSP,
+    PSP,
 
     // Link Register
     LR,
@@ -27,33 +33,261 @@ pub enum Register {
     APSR,
     IPSR,
     EPSR,
+
+    // Special purpose registers
+    //   The exception mask registers raise the execution priority, PRIMASK to 0, FAULTMASK to -1 and BASEPRI to its
+    //   own value when that is not zero.
+    PRIMASK,
+    BASEPRI,
+    FAULTMASK,
+    CONTROL,
+}
+
+impl Register {
+    /// The core register with the given number, as encoded in an instruction
+    pub fn from_number(n: u8) -> Register {
+        use Register::*;
+
+        match n & 0b1111 {
+            0 => R0, 1 => R1, 2 => R2, 3 => R3,
+            4 => R4, 5 => R5, 6 => R6, 7 => R7,
+            8 => R8, 9 => R9, 10 => R10, 11 => R11, 12 => R12,
+            13 => SP,
+            14 => LR,
+            _ => PC,
+        }
+    }
 }
 
+/// The execution mode of the processor
+///
+/// Execution starts in Thread mode out of reset and enters Handler mode to take an exception, returning to Thread mode
+/// once the last active exception returns. Handler mode is always privileged and always uses the main stack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Thread,
+    Handler,
+}
+
+/// The bits of the xPSR held by each of the program status registers
+const APSR_MASK: u32 = 0xF80F_0000;
+const IPSR_MASK: u32 = 0x0000_01FF;
+const EPSR_MASK: u32 = 0x0700_FC00;
+
+const APSR_Q: u32 = 1 << 27;
+const APSR_GE_SHIFT: u32 = 16;
+const EPSR_T: u32 = 1 << 24;
+
+const CONTROL_NPRIV: u32 = 1 << 0;
+const CONTROL_SPSEL: u32 = 1 << 1;
+
+/// The core registers, R0-R15, along with the program status and special purpose registers
+/// 
+/// Registers may be accessed by name through indexing, which gives the raw value of the register, or by the register
+/// number of a decoded instruction through `read` and `write`, which apply the architectural rules for R13 and R15.
 pub struct RegisterBank {
-    registers: [u32; ::std::u8::MAX as usize],
+    r: [u32; 13],
+    msp: u32,
+    psp: u32,
+    lr: u32,
+    pc: u32,
+    apsr: u32,
+    ipsr: u32,
+    epsr: u32,
+    primask: u32,
+    basepri: u32,
+    faultmask: u32,
+    control: u32,
 }
 
 impl RegisterBank {
     pub fn new() -> RegisterBank {
         RegisterBank {
-            registers: [0; ::std::u8::MAX as usize]
+            r: [0; 13],
+            msp: 0,
+            psp: 0,
+            lr: 0,
+            pc: 0,
+            apsr: 0,
+            ipsr: 0,
+            epsr: 0,
+            primask: 0,
+            basepri: 0,
+            faultmask: 0,
+            control: 0,
         }
     }
+
+    /// Reads a core register by number, reads of the PC return the address of the current instruction plus 4
+    pub fn read(&self, n: u8) -> u32 {
+        match Register::from_number(n) {
+            Register::PC => self.pc.wrapping_add(4),
+            register => self[register],
+        }
+    }
+
+    /// Writes a core register by number
+    /// 
+    /// Bits [1:0] of the SP always read as zero. Writes to the PC set the address of the next instruction directly,
+    /// interworking branches are the responsibility of the caller.
+    pub fn write(&mut self, n: u8, value: u32) {
+        match Register::from_number(n) {
+            Register::SP => self[Register::SP] = value & !0b11,
+            register => self[register] = value,
+        }
+    }
+
+    /// The current execution mode, given by the exception number in the IPSR
+    pub fn mode(&self) -> Mode {
+        if self.handler_mode() { Mode::Handler } else { Mode::Thread }
+    }
+
+    /// Whether the processor is in Handler mode, which is the case whenever an exception is active
+    pub fn handler_mode(&self) -> bool {
+        self.ipsr & IPSR_MASK != 0
+    }
+
+    /// Whether execution is privileged, Handler mode is always privileged and Thread mode is unless CONTROL.nPRIV is set
+    pub fn privileged(&self) -> bool {
+        self.handler_mode() || self.control & CONTROL_NPRIV == 0
+    }
+
+    /// Whether R13 currently refers to the process stack pointer
+    pub fn process_stack(&self) -> bool {
+        self.spsel() && !self.handler_mode()
+    }
+
+    /// The stack pointer select bit of the CONTROL register, which selects the process stack in Thread mode
+    pub fn spsel(&self) -> bool {
+        self.control & CONTROL_SPSEL != 0
+    }
+
+    pub fn set_spsel(&mut self, spsel: bool) {
+        self.control = self.control & !CONTROL_SPSEL | (spsel as u32) << 1;
+    }
+
+    /// The combined program status register, as stacked on exception entry
+    pub fn xpsr(&self) -> u32 {
+        self.apsr & APSR_MASK | self.ipsr & IPSR_MASK | self.epsr & EPSR_MASK
+    }
+
+    /// Writes the combined program status register, as unstacked on exception return
+    pub fn set_xpsr(&mut self, xpsr: u32) {
+        self.apsr = xpsr & APSR_MASK;
+        self.ipsr = xpsr & IPSR_MASK;
+        self.epsr = xpsr & EPSR_MASK;
+    }
+
+    /// The N, Z, C and V condition flags of the APSR
+    pub fn flags(&self) -> ConditionFlags {
+        ConditionFlags::from_apsr(self.apsr)
+    }
+
+    pub fn set_flags(&mut self, flags: ConditionFlags) {
+        self.apsr = flags.into_apsr(self.apsr);
+    }
+
+    /// The saturation flag of the APSR
+    pub fn q(&self) -> bool {
+        self.apsr & APSR_Q != 0
+    }
+
+    pub fn set_q(&mut self, q: bool) {
+        self.apsr = self.apsr & !APSR_Q | (q as u32) << 27;
+    }
+
+    /// The greater than or equal flags of the APSR, GE[3:0]
+    pub fn ge(&self) -> u8 {
+        (self.apsr >> APSR_GE_SHIFT) as u8 & 0b1111
+    }
+
+    pub fn set_ge(&mut self, ge: u8) {
+        self.apsr = self.apsr & !(0b1111 << APSR_GE_SHIFT) | ((ge & 0b1111) as u32) << APSR_GE_SHIFT;
+    }
+
+    /// The IT execution state held in the EPSR
+    pub fn itstate(&self) -> ItState {
+        ItState::from_epsr(self.epsr)
+    }
+
+    pub fn set_itstate(&mut self, itstate: ItState) {
+        self.epsr = itstate.into_epsr(self.epsr);
+    }
+
+    /// The Thumb execution state bit of the EPSR
+    pub fn thumb(&self) -> bool {
+        self.epsr & EPSR_T != 0
+    }
+
+    pub fn set_thumb(&mut self, thumb: bool) {
+        self.epsr = self.epsr & !EPSR_T | (thumb as u32) << 24;
+    }
+}
+
+impl Default for RegisterBank {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Index<Register> for RegisterBank {
     type Output = u32;
     fn index(&self, idx: Register) -> &Self::Output {
-        &self.registers[idx as usize]
+        match idx {
+            Register::SP if self.process_stack() => &self.psp,
+            Register::SP | Register::MSP => &self.msp,
+            Register::PSP => &self.psp,
+            Register::LR => &self.lr,
+            Register::PC => &self.pc,
+            Register::APSR => &self.apsr,
+            Register::IPSR => &self.ipsr,
+            Register::EPSR => &self.epsr,
+            Register::PRIMASK => &self.primask,
+            Register::BASEPRI => &self.basepri,
+            Register::FAULTMASK => &self.faultmask,
+            Register::CONTROL => &self.control,
+            r => &self.r[r as usize],
+        }
     }
 }
 
 impl IndexMut<Register> for RegisterBank {
     fn index_mut(&mut self, idx: Register) -> &mut Self::Output {
-        &mut self.registers[idx as usize]
+        match idx {
+            Register::SP if self.process_stack() => &mut self.psp,
+            Register::SP | Register::MSP => &mut self.msp,
+            Register::PSP => &mut self.psp,
+            Register::LR => &mut self.lr,
+            Register::PC => &mut self.pc,
+            Register::APSR => &mut self.apsr,
+            Register::IPSR => &mut self.ipsr,
+            Register::EPSR => &mut self.epsr,
+            Register::PRIMASK => &mut self.primask,
+            Register::BASEPRI => &mut self.basepri,
+            Register::FAULTMASK => &mut self.faultmask,
+            Register::CONTROL => &mut self.control,
+            r => &mut self.r[r as usize],
+        }
     }
 }
 
+/// An access to an address outside of the allocated memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BusError {
+    pub address: usize,
+}
+
+/// Whether the system address map gives an address the Execute Never attribute, instruction fetches from which fault
+pub fn execute_never(address: u32) -> bool {
+    matches!(address, 0x4000_0000..=0x5FFF_FFFF | 0xA000_0000..=0xFFFF_FFFF)
+}
+
+macro_rules! kb {
+    ($v:expr) => {
+        ($v as usize) * 1024usize
+    };
+}
+
 /// Memory
 /// 
 /// 
@@ -91,13 +325,11 @@ impl IndexMut<Register> for RegisterBank {
 /// | [0x00000000 -> 0x1FFFFFFF] | Code       | Normal      | -   | WT    | Typically ROM or flash memory.              |
 /// | [0x20000000 -> 0x3FFFFFFF] | SRAM       | Normal      | -   | WBWA  | SRAM region typically used for on-chip RAM. |
 /// | [0x40000000 -> 0x5FFFFFFF] | Peripheral | Device      | XN  | -     | On-chip peripheral address space.           |
-
-macro_rules! kb {
-    ($v:expr) => {
-        ($v as usize) * 1024usize
-    };
-}
-
+/// | [0x60000000 -> 0x9FFFFFFF] | RAM        | Normal      | -   | WBWA  | Memory with write-back, write allocate.     |
+/// | [0xA0000000 -> 0xDFFFFFFF] | Device     | Device      | XN  | -     | Shareable and non-shareable device memory.  |
+/// | [0xE0000000 -> 0xFFFFFFFF] | System     | -           | XN  | -     | The PPB, including the SCS, and vendor use. |
+///
+/// Accesses outside of the allocated memory are reported as a `BusError`, which the processor raises as a BusFault.
 #[derive(Debug)]
 pub struct Memory {
     raw_pinned: Pin<Box<[u8]>>,
@@ -119,24 +351,63 @@ impl Memory {
         }
     }
 
+    pub fn read_u8(&self, address: usize) -> u8 {
+        self.raw_pinned[address]
+    }
+
     pub fn read_u16(&self, address: usize) -> u16 {
-        unsafe {
-            std::mem::transmute::<[u8; 2], u16>([self.raw_pinned[address], self.raw_pinned[address+1]]).to_le()
-        }
+        u16::from_le_bytes([self.raw_pinned[address], self.raw_pinned[address+1]])
+    }
+
+    pub fn read_u32(&self, address: usize) -> u32 {
+        u32::from_le_bytes([
+            self.raw_pinned[address], self.raw_pinned[address+1],
+            self.raw_pinned[address+2], self.raw_pinned[address+3],
+        ])
+    }
+
+    pub fn write_u8(&mut self, address: usize, value: u8) {
+        self.raw_pinned.as_mut()[address] = value;
+    }
+
+    pub fn write_u16(&mut self, address: usize, value: u16) {
+        self.raw_pinned.as_mut()[address..address+2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, address: usize, value: u32) {
+        self.raw_pinned.as_mut()[address..address+4].copy_from_slice(&value.to_le_bytes());
     }
     
-    // todo: return result type for error handling
-    pub fn write_bytes(&mut self, address: usize, bytes: &[u8]) {
+    /// Reads a little endian item of `size` bytes, failing if any of its bytes is outside of the allocated memory
+    pub fn read(&self, address: usize, size: usize) -> Result<u32, BusError> {
+        let bytes = self.range(address, size)?;
+        Ok(self.raw_pinned[bytes].iter().rev().fold(0, |item, byte| item << 8 | *byte as u32))
+    }
+
+    /// Writes a little endian item of `size` bytes, failing if any of its bytes is outside of the allocated memory
+    pub fn write(&mut self, address: usize, size: usize, value: u32) -> Result<(), BusError> {
+        let bytes = self.range(address, size)?;
+        self.raw_pinned.as_mut()[bytes].copy_from_slice(&value.to_le_bytes()[..size]);
+        Ok(())
+    }
+
+    pub fn write_bytes(&mut self, address: usize, bytes: &[u8]) -> Result<(), BusError> {
         let len = bytes.len();
-        let total_len = address + len;
-        let allocated_len = self.raw_pinned.as_ref().len();
         println!("[Memory] Write {} bytes beginning at address {:#X}", len, address);
-        //println!("[Memory] Total/Allocated length: {}/{}", total_len, allocated_len);
 
-        assert!(total_len <= allocated_len);
+        let range = self.range(address, len)?;
+        self.raw_pinned.as_mut()[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// The range of bytes of an access, or the address of its first byte outside of the allocated memory
+    fn range(&self, address: usize, size: usize) -> Result<std::ops::Range<usize>, BusError> {
+        let allocated_len = self.allocated_bytes();
 
-        for (i, byte) in bytes.iter().enumerate() {
-            self.raw_pinned.as_mut()[address + i] = *byte;
+        if address.saturating_add(size) <= allocated_len {
+            Ok(address..address + size)
+        } else {
+            Err(BusError { address: address.max(allocated_len) })
         }
     }
 
@@ -147,8 +418,94 @@ impl Memory {
     fn align_with(value: usize, align: usize) -> usize {
         if align == 0 {
             value
-        } else {    
-            ((value + align - 1) / align) * align
+        } else {
+            value.next_multiple_of(align)
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn register_bank_access() {
+        let mut reg = RegisterBank::new();
+
+        for n in 0..15 {
+            reg.write(n, n as u32 * 4);
+        }
+        reg[Register::PC] = 0x100;
+
+        assert_eq!((0..16).map(|n| reg.read(n)).collect::<Vec<_>>(), [
+            0, 4, 8, 12, 16, 20, 24, 28, 32, 36, 40, 44, 48, 52, 56, 0x104
+        ]);
+        assert_eq!(reg[Register::R12], 48);
+        assert_eq!(reg[Register::LR], 56);
+
+        reg.write(13, 0x2000_0003);
+        assert_eq!(reg.read(13), 0x2000_0000, "the SP is word aligned");
+    }
+
+    #[test]
+    fn register_bank_stack_pointers() {
+        let mut reg = RegisterBank::new();
+        reg[Register::MSP] = 0x2000_1000;
+        reg[Register::PSP] = 0x2000_0800;
+        assert_eq!(reg.read(13), 0x2000_1000);
+
+        reg[Register::CONTROL] = CONTROL_SPSEL;
+        assert_eq!(reg.read(13), 0x2000_0800, "Thread mode uses the SP selected by CONTROL.SPSEL");
+
+        reg.write(13, 0x2000_07F0);
+        assert_eq!(reg[Register::PSP], 0x2000_07F0);
+
+        reg[Register::IPSR] = 11;
+        assert!(reg.handler_mode());
+        assert_eq!(reg.read(13), 0x2000_1000, "Handler mode always uses the MSP");
+    }
+
+    #[test]
+    fn register_bank_program_status() {
+        let mut reg = RegisterBank::new();
+
+        reg.set_flags(ConditionFlags { n: true, z: false, c: true, v: false });
+        reg.set_q(true);
+        reg.set_ge(0b1010);
+        reg.set_thumb(true);
+        reg.set_itstate(ItState::new(0b0000, 0b1000));
+        reg[Register::IPSR] = 15;
+
+        assert_eq!(reg.xpsr(), 0xA90A_080F);
+        assert_eq!(reg.flags(), ConditionFlags { n: true, z: false, c: true, v: false });
+        assert!(reg.q());
+        assert_eq!(reg.ge(), 0b1010);
+
+        let xpsr = reg.xpsr();
+        let mut other = RegisterBank::new();
+        other.set_xpsr(xpsr | 0x0000_0200);
+        assert_eq!(other.xpsr(), xpsr, "reserved bits are not written");
+        assert!(other.thumb());
+        assert_eq!(other.itstate(), ItState::new(0b0000, 0b1000));
+        assert_eq!(other[Register::IPSR], 15);
+    }
+
+    #[test]
+    fn memory_bus_errors() {
+        let mut mem = Memory::alloc(0x1000);
+
+        assert_eq!(mem.write(0xFFE, 2, 0xBEEF), Ok(()));
+        assert_eq!(mem.read(0xFFE, 2), Ok(0xBEEF));
+        assert_eq!(mem.write(0xFFE, 4, 0), Err(BusError { address: 0x1000 }), "the access fails at its first missing byte");
+        assert_eq!(mem.read(0x2000_0000, 1), Err(BusError { address: 0x2000_0000 }));
+        assert_eq!(mem.read(usize::MAX, 4), Err(BusError { address: usize::MAX }));
+        assert_eq!(mem.read_u16(0xFFE), 0xBEEF, "a failed write leaves memory unchanged");
+
+        assert_eq!(mem.write_bytes(0xFFC, &[1, 2, 3, 4]), Ok(()));
+        assert_eq!(mem.read(0xFFD, 3), Ok(0x04_03_02));
+        assert!(mem.write_bytes(0xFFC, &[0; 5]).is_err());
+
+        assert!(execute_never(0xE000_E000) && execute_never(0x4000_0000) && execute_never(0xFFFF_FFF9));
+        assert!(!execute_never(0x0800_0000) && !execute_never(0x2000_0000) && !execute_never(0x6000_0000));
+    }
+}
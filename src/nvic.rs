@@ -0,0 +1,384 @@
+// ARMv7-M Nested Vectored Interrupt Controller
+
+use crate::exception::{ Exception, NUM_EXCEPTIONS };
+
+/// The execution priority of Thread mode with no active exceptions, lower than that of any exception
+pub const BASE_PRIORITY: i16 = 256;
+
+/// Nested Vectored Interrupt Controller
+///
+/// Holds the enabled, pending and active state and the priority of every exception, along with the external
+/// interrupt lines which pend them.
+///
+/// NVIC REGISTERS (B3.4.2 pg. 681)
+///
+/// | Address                    | Name          | Description                                                    |
+/// | [0xE000E100 -> 0xE000E13C] | NVIC_ISER0-15 | Interrupt Set-Enable, writing 1 enables an interrupt           |
+/// | [0xE000E180 -> 0xE000E1BC] | NVIC_ICER0-15 | Interrupt Clear-Enable, writing 1 disables an interrupt        |
+/// | [0xE000E200 -> 0xE000E23C] | NVIC_ISPR0-15 | Interrupt Set-Pending, writing 1 pends an interrupt            |
+/// | [0xE000E280 -> 0xE000E2BC] | NVIC_ICPR0-15 | Interrupt Clear-Pending, writing 1 clears a pending interrupt  |
+/// | [0xE000E300 -> 0xE000E33C] | NVIC_IABR0-15 | Interrupt Active Bit, read only                                |
+/// | [0xE000E400 -> 0xE000E5EC] | NVIC_IPR0-123 | Interrupt Priority, one byte per interrupt                     |
+///
+/// Priorities are held in the upper `priority_bits` of each priority byte, the remaining bits read as zero. The
+/// priority grouping, AIRCR.PRIGROUP, splits each priority into a group priority, which decides preemption, and a
+/// subpriority, which only orders pending exceptions of the same group priority.
+pub struct Nvic {
+    irqs: usize,
+    priority_bits: u8,
+    prigroup: u8,
+    enabled: [bool; NUM_EXCEPTIONS],
+    pending: [bool; NUM_EXCEPTIONS],
+    active: [bool; NUM_EXCEPTIONS],
+    priority: [u8; NUM_EXCEPTIONS],
+    lines: [bool; NUM_EXCEPTIONS - 16],
+}
+
+impl Nvic {
+    /// An NVIC with `irqs` external interrupts and `priority_bits` implemented priority bits, from 3 to 8
+    pub fn new(irqs: usize, priority_bits: u8) -> Nvic {
+        assert!(irqs <= NUM_EXCEPTIONS - 16, "at most {} external interrupts are supported", NUM_EXCEPTIONS - 16);
+        assert!((3..=8).contains(&priority_bits), "between 3 and 8 priority bits must be implemented");
+
+        let mut enabled = [false; NUM_EXCEPTIONS];
+
+        // The system exceptions are always enabled, except for the configurable faults which SHCSR enables
+        enabled[1..16].iter_mut().for_each(|enabled| *enabled = true);
+        enabled[4..=6].iter_mut().for_each(|enabled| *enabled = false);
+
+        Nvic {
+            irqs,
+            priority_bits,
+            prigroup: 0,
+            enabled,
+            pending: [false; NUM_EXCEPTIONS],
+            active: [false; NUM_EXCEPTIONS],
+            priority: [0; NUM_EXCEPTIONS],
+            lines: [false; NUM_EXCEPTIONS - 16],
+        }
+    }
+
+    /// Returns every exception to its reset state, keeping the configuration and the state of the interrupt lines
+    pub fn reset(&mut self) {
+        *self = Nvic { lines: self.lines, ..Nvic::new(self.irqs, self.priority_bits) };
+
+        for irq in 0..self.irqs as u16 {
+            if self.lines[irq as usize] {
+                self.set_pending(16 + irq);
+            }
+        }
+    }
+
+    /// The number of external interrupts
+    pub fn irqs(&self) -> usize {
+        self.irqs
+    }
+
+    /// The number of implemented priority bits
+    pub fn priority_bits(&self) -> u8 {
+        self.priority_bits
+    }
+
+    fn implemented(&self, number: u16) -> bool {
+        Exception::from_number(number).is_some() && (number as usize) < 16 + self.irqs
+    }
+
+    pub fn is_enabled(&self, number: u16) -> bool {
+        self.enabled[number as usize]
+    }
+
+    pub fn set_enabled(&mut self, number: u16, enabled: bool) {
+        if self.implemented(number) {
+            self.enabled[number as usize] = enabled;
+        }
+    }
+
+    pub fn is_pending(&self, number: u16) -> bool {
+        self.pending[number as usize]
+    }
+
+    pub fn set_pending(&mut self, number: u16) {
+        if self.implemented(number) {
+            self.pending[number as usize] = true;
+        }
+    }
+
+    pub fn clear_pending(&mut self, number: u16) {
+        self.pending[number as usize] = false;
+    }
+
+    pub fn is_active(&self, number: u16) -> bool {
+        self.active.get(number as usize).copied().unwrap_or(false)
+    }
+
+    pub fn activate(&mut self, number: u16) {
+        self.active[number as usize] = true;
+    }
+
+    /// Clears the active state of an exception
+    ///
+    /// Interrupt lines are level sensitive, an interrupt whose line is still asserted is pended again.
+    pub fn deactivate(&mut self, number: u16) {
+        self.active[number as usize] = false;
+
+        if self.line_asserted(number) {
+            self.set_pending(number);
+        }
+    }
+
+    fn line_asserted(&self, number: u16) -> bool {
+        match Exception::from_number(number) {
+            Some(Exception::Interrupt(irq)) => self.lines[irq as usize],
+            _ => false,
+        }
+    }
+
+    /// The number of active exceptions, NestedActivation in the ARMv7-M Architecture Reference Manual
+    pub fn nested_activation(&self) -> usize {
+        self.active.iter().filter(|&&active| active).count()
+    }
+
+    /// Drives an external interrupt line, a rising edge pends the interrupt
+    pub fn set_line(&mut self, irq: u16, asserted: bool) {
+        if (irq as usize) >= self.irqs {
+            return
+        }
+
+        if asserted && !self.lines[irq as usize] {
+            self.set_pending(16 + irq);
+        }
+
+        self.lines[irq as usize] = asserted;
+    }
+
+    /// The priority of an exception, Reset, NMI and HardFault have fixed negative priorities
+    pub fn priority(&self, number: u16) -> i16 {
+        match Exception::from_number(number) {
+            Some(Exception::Reset) => -3,
+            Some(Exception::Nmi) => -2,
+            Some(Exception::HardFault) => -1,
+            _ => self.priority[number as usize] as i16,
+        }
+    }
+
+    /// Sets the configurable priority of an exception, keeping only the implemented priority bits
+    pub fn set_priority(&mut self, number: u16, priority: u8) {
+        if number >= 4 && self.implemented(number) {
+            self.priority[number as usize] = priority & (0xFFu8 << (8 - self.priority_bits));
+        }
+    }
+
+    /// The priority grouping, AIRCR.PRIGROUP
+    pub fn prigroup(&self) -> u8 {
+        self.prigroup
+    }
+
+    pub fn set_prigroup(&mut self, prigroup: u8) {
+        self.prigroup = prigroup & 0b111;
+    }
+
+    /// The group priority of a priority value, which has the subpriority bits PRIGROUP:0 cleared
+    pub fn group_priority(&self, priority: i16) -> i16 {
+        if priority < 0 {
+            priority
+        } else {
+            priority & !((2 << self.prigroup) - 1)
+        }
+    }
+
+    /// The execution priority, the highest group priority of the active exceptions
+    pub fn execution_priority(&self) -> i16 {
+        (1..NUM_EXCEPTIONS as u16)
+            .filter(|&number| self.is_active(number))
+            .map(|number| self.group_priority(self.priority(number)))
+            .min()
+            .unwrap_or(BASE_PRIORITY)
+    }
+
+    /// The active exception with the highest priority, which is the exception being handled
+    ///
+    /// Exceptions only preempt those of a lower group priority, so the handler running is always that of the highest
+    /// priority active exception.
+    pub fn current_exception(&self) -> Option<Exception> {
+        (1..NUM_EXCEPTIONS as u16)
+            .filter(|&number| self.is_active(number))
+            .min_by_key(|&number| (self.group_priority(self.priority(number)), number))
+            .and_then(Exception::from_number)
+    }
+
+    /// The highest priority enabled and pending exception, ties are won by the lowest exception number
+    pub fn pending_exception(&self) -> Option<Exception> {
+        (1..(16 + self.irqs) as u16)
+            .filter(|&number| self.pending[number as usize] && self.enabled[number as usize])
+            .min_by_key(|&number| (self.priority(number), number))
+            .and_then(Exception::from_number)
+    }
+
+    /// The pending exception which preempts execution at the given priority, if any
+    pub fn preempting(&self, execution_priority: i16) -> Option<Exception> {
+        self.pending_exception()
+            .filter(|exception| self.group_priority(self.priority(exception.number())) < execution_priority)
+    }
+
+    /// Reads an NVIC register, at an offset from 0xE000E000
+    pub fn read_register(&self, offset: u32) -> u32 {
+        match offset {
+            0x100..=0x13C => self.read_bits(&self.enabled, offset - 0x100),
+            0x180..=0x1BC => self.read_bits(&self.enabled, offset - 0x180),
+            0x200..=0x23C => self.read_bits(&self.pending, offset - 0x200),
+            0x280..=0x2BC => self.read_bits(&self.pending, offset - 0x280),
+            0x300..=0x33C => self.read_bits(&self.active, offset - 0x300),
+            0x400..=0x5EC => (0..4).fold(0, |word, byte| word | (self.read_priority_byte(offset + byte) as u32) << (8 * byte)),
+            _ => 0,
+        }
+    }
+
+    /// Writes an NVIC register, at an offset from 0xE000E000
+    pub fn write_register(&mut self, offset: u32, value: u32) {
+        if let 0x400..=0x5EC = offset {
+            return (0..4).for_each(|byte| self.write_priority_byte(offset + byte, (value >> (8 * byte)) as u8))
+        }
+
+        // Each bit per interrupt register is 0x80 bytes from the next, with the word at (offset & 0x7F)
+        let first = 16 + (offset & 0x7F) * 8;
+        let numbers = (0..32).filter(|bit| value & (1 << bit) != 0).map(|bit| (first + bit) as u16);
+
+        for number in numbers {
+            if !self.implemented(number) {
+                continue
+            }
+
+            match offset & !0x7F {
+                0x100 => self.enabled[number as usize] = true,
+                0x180 => self.enabled[number as usize] = false,
+                0x200 => self.set_pending(number),
+                0x280 if !self.line_asserted(number) => self.clear_pending(number),
+                _ => (),
+            }
+        }
+    }
+
+    /// Reads a single byte of the interrupt priority registers, which are byte accessible
+    pub fn read_priority_byte(&self, offset: u32) -> u8 {
+        let number = (16 + offset - 0x400) as u16;
+
+        if self.implemented(number) {
+            self.priority[number as usize]
+        } else {
+            0
+        }
+    }
+
+    pub fn write_priority_byte(&mut self, offset: u32, priority: u8) {
+        self.set_priority((16 + offset - 0x400) as u16, priority);
+    }
+
+    /// Reads 32 interrupts' worth of state from one of the bit per interrupt registers
+    fn read_bits(&self, state: &[bool; NUM_EXCEPTIONS], offset: u32) -> u32 {
+        (0..32u32)
+            .map(|bit| (16 + offset * 8 + bit) as u16)
+            .enumerate()
+            .filter(|&(_, number)| self.implemented(number) && state[number as usize])
+            .fold(0, |word, (bit, _)| word | 1 << bit)
+    }
+}
+
+impl Default for Nvic {
+    fn default() -> Self {
+        Self::new(32, 4)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nvic_registers() {
+        let mut nvic = Nvic::new(40, 4);
+
+        nvic.write_register(0x100, 1 << 3 | 1 << 31);
+        nvic.write_register(0x104, 0xFFFF_FFFF);
+        assert_eq!(nvic.read_register(0x100), 1 << 3 | 1 << 31);
+        assert_eq!(nvic.read_register(0x104), 0xFF, "only implemented interrupts can be enabled");
+
+        nvic.write_register(0x180, 1 << 31);
+        assert_eq!(nvic.read_register(0x100), 1 << 3);
+        assert!(nvic.is_enabled(16 + 3));
+
+        nvic.write_register(0x200, 1 << 3 | 1 << 5);
+        nvic.write_register(0x280, 1 << 5);
+        assert_eq!(nvic.read_register(0x200), 1 << 3);
+        assert_eq!(nvic.read_register(0x280), 1 << 3);
+
+        nvic.write_register(0x404, 0x12_34_56_78);
+        assert_eq!(nvic.read_register(0x404), 0x10_30_50_70, "unimplemented priority bits read as zero");
+        assert_eq!(nvic.priority(16 + 5), 0x50);
+        assert_eq!(nvic.read_priority_byte(0x406), 0x30);
+
+        nvic.activate(16 + 3);
+        assert_eq!(nvic.read_register(0x300), 1 << 3);
+    }
+
+    #[test]
+    fn nvic_priorities() {
+        let mut nvic = Nvic::new(8, 8);
+        let irq = |n: u16| 16 + n;
+
+        (0..4).for_each(|n| nvic.write_register(0x100, 1 << n));
+        nvic.set_priority(irq(0), 0x40);
+        nvic.set_priority(irq(1), 0x20);
+        nvic.set_priority(irq(2), 0x21);
+        nvic.set_priority(irq(3), 0x20);
+
+        nvic.set_pending(irq(0));
+        nvic.set_pending(irq(2));
+        nvic.set_pending(irq(3));
+        assert_eq!(nvic.pending_exception(), Some(Exception::Interrupt(3)));
+
+        nvic.set_pending(irq(1));
+        assert_eq!(nvic.pending_exception(), Some(Exception::Interrupt(1)), "ties go to the lower exception number");
+
+        nvic.set_pending(Exception::Nmi.number());
+        assert_eq!(nvic.pending_exception(), Some(Exception::Nmi));
+        nvic.clear_pending(Exception::Nmi.number());
+
+        nvic.activate(irq(1));
+        assert_eq!(nvic.execution_priority(), 0x20);
+        assert_eq!(nvic.preempting(nvic.execution_priority()), None);
+
+        // With PRIGROUP 5, priority bits [5:0] are subpriority and 0x40 is the next group up from 0x20
+        nvic.set_prigroup(5);
+        assert_eq!(nvic.group_priority(0x21), 0);
+        assert_eq!(nvic.execution_priority(), 0);
+
+        nvic.set_prigroup(4);
+        nvic.deactivate(irq(1));
+        nvic.activate(irq(0));
+        assert_eq!(nvic.execution_priority(), 0x40);
+        assert_eq!(nvic.preempting(0x40), Some(Exception::Interrupt(1)));
+    }
+
+    #[test]
+    fn nvic_interrupt_lines() {
+        let mut nvic = Nvic::new(8, 3);
+        nvic.write_register(0x100, 1 << 2);
+
+        nvic.set_line(2, true);
+        assert!(nvic.is_pending(16 + 2));
+
+        nvic.clear_pending(16 + 2);
+        nvic.activate(16 + 2);
+        nvic.set_line(2, true);
+        assert!(!nvic.is_pending(16 + 2), "only a rising edge pends the interrupt");
+
+        nvic.deactivate(16 + 2);
+        assert!(nvic.is_pending(16 + 2), "an asserted line pends the interrupt again");
+
+        nvic.clear_pending(16 + 2);
+        nvic.set_line(2, false);
+        nvic.activate(16 + 2);
+        nvic.deactivate(16 + 2);
+        assert!(!nvic.is_pending(16 + 2));
+    }
+}
@@ -1,8 +1,66 @@
+use crate::exception::{ Exception, Fault };
+use crate::exception::{ EXC_RETURN_HANDLER, EXC_RETURN_THREAD_MSP, EXC_RETURN_THREAD_PSP };
 use crate::instructions;
-use crate::instructions::{ InstrThumb16 };
-use crate::memory::{ Register, RegisterBank, Memory };
+use crate::decode::PIPELINE_REFILL;
+use crate::instructions::{ Analyzer, InstrThumb16, InstrThumb32, ItPosition, ItState, Unpredictable };
+use crate::instructions::{ DataSize, Direction, Transfer, MultipleTransfer };
+use crate::memory;
+use crate::memory::{ Mode, Register, RegisterBank, Memory };
+use crate::nvic::{ Nvic, BASE_PRIORITY };
+use crate::scs::{ Scs, SCS_BASE };
 use crate::loader::ProgramImage;
 
+use std::collections::HashMap;
+
+/// The 16 bit decode table, generated at build time from the instruction descriptions
+static DECODE_TABLE: [InstrThumb16; instructions::NUM_TH16_INSTRUCTIONS] = include!(concat!(env!("OUT_DIR"), "/decode_table.rs"));
+
+/// How the processor treats instructions which are UNPREDICTABLE
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnpredictablePolicy {
+    /// Stop execution with a diagnostic
+    Strict,
+
+    /// Report the instruction and execute it as a Cortex-M3 does, which follows the architectural pseudocode as though
+    /// the constraint did not exist
+    Lenient,
+}
+
+/// Where the processor takes its initial stack pointer and program counter from on reset
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetSource {
+    /// The first two words of the vector table at VTOR, as a Cortex-M core does
+    VectorTable,
+
+    /// The entry point of the loaded image, for images without a vector table
+    /// 
+    /// The main stack pointer starts at the top of the image's memory.
+    ImageEntry,
+}
+
+/// The cycles taken to enter an exception handler on a Cortex-M3, from the first stacking access to the first instruction
+/// of the handler
+const EXCEPTION_ENTRY_CYCLES: u32 = 12;
+
+/// Why an instruction did not complete
+enum Stop {
+    /// The instruction raised a fault, which is taken with the instruction as its return address
+    Fault(Fault),
+
+    /// The instruction is defined but not implemented by the processor, which stops execution rather than continue
+    /// from a state the instruction should have changed
+    Unimplemented,
+}
+
+impl From<Fault> for Stop {
+    fn from(fault: Fault) -> Self {
+        Stop::Fault(fault)
+    }
+}
+
+/// A host side service for an SVC number, given r0-r3 and the memory of the processor, returns the value of r0
+pub type SvcHandler = Box<dyn FnMut([u32; 4], &mut Memory) -> u32>;
+
 /// ARMv7-M virtual processor
 /// 
 /// Registers:
@@ -22,55 +80,437 @@ use crate::loader::ProgramImage;
 /// [ R13 ]: Stack Pointer
 /// [ R14 ]: Link Register
 /// [ R15 ]: Program Counter
+///
+/// Execution is in Thread mode until an exception is taken, and handlers run in Handler mode on the main stack. Thread
+/// mode runs on the main or process stack as selected by CONTROL.SPSEL, and is unprivileged when CONTROL.nPRIV is set,
+/// in which case the System Control Space and the special registers are out of reach. The EXC_RETURN value of an
+/// exception return selects the mode and stack execution returns to.
 pub struct Processor {
-    dct: [InstrThumb16; instructions::NUM_TH16_INSTRUCTIONS],
     reg: RegisterBank,
     mem: Memory,
-    reset: usize,
+    scs: Scs,
+    locked_up: bool,
+    entry: usize,
+    policy: UnpredictablePolicy,
+    reset_source: ResetSource,
+    svc_handlers: HashMap<u8, SvcHandler>,
+    analyzer: Analyzer,
+}
+
+impl Default for Processor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Processor {
     pub fn new() -> Processor {
         Processor {
-            dct: [InstrThumb16::Undefined; instructions::NUM_TH16_INSTRUCTIONS],
             reg: RegisterBank::new(),
             mem: Memory::alloc(0),
-            reset: 0,
+            scs: Scs::new(Nvic::default()),
+            locked_up: false,
+            entry: 0,
+            policy: UnpredictablePolicy::Lenient,
+            reset_source: ResetSource::VectorTable,
+            svc_handlers: HashMap::new(),
+            analyzer: Analyzer::new(),
         }
     }
 
-    pub fn init(&mut self) {
-        self.dct = InstrThumb16::generate_decode_table();
+    pub fn set_unpredictable_policy(&mut self, policy: UnpredictablePolicy) {
+        self.policy = policy;
+    }
 
-        use std::io::prelude::*;
-        let mut file = std::fs::File::create("decode_table_new.rs").unwrap();
+    pub fn set_reset_source(&mut self, source: ResetSource) {
+        self.reset_source = source;
+    }
 
-        for (idx, item) in self.dct.iter().enumerate() {
-            writeln!(file, "{:#06x} // {:?} {:#018b}", idx, item, idx).unwrap();
-        }
+    /// Replaces the NVIC with one implementing `irqs` external interrupts and `priority_bits` bits of priority
+    pub fn configure_nvic(&mut self, irqs: usize, priority_bits: u8) {
+        self.scs.nvic = Nvic::new(irqs, priority_bits);
+    }
+
+    pub fn nvic(&self) -> &Nvic {
+        &self.scs.nvic
+    }
+
+    /// The core registers, along with the program status and special purpose registers
+    pub fn registers(&self) -> &RegisterBank {
+        &self.reg
+    }
+
+    /// The current execution mode, Thread or Handler
+    pub fn mode(&self) -> Mode {
+        self.reg.mode()
+    }
+
+    /// Whether execution is privileged, which it always is in Handler mode, and in Thread mode unless CONTROL.nPRIV
+    /// is set
+    pub fn privileged(&self) -> bool {
+        self.reg.privileged()
+    }
+
+    /// Drives the line of an external interrupt, as a peripheral would
+    ///
+    /// Interrupts are level sensitive. Asserting a line pends its interrupt, and an interrupt whose line is still
+    /// asserted when its handler returns is pended again.
+    pub fn set_irq_line(&mut self, irq: u16, asserted: bool) {
+        self.scs.nvic.set_line(irq, asserted);
+    }
+
+    /// Services an SVC number on the host in place of the SVCall exception
+    ///
+    /// The handler is called with r0-r3 when an `svc` with the number executes, and its result is written to r0.
+    /// Execution continues at the next instruction, no exception is taken and no state other than r0 changes. SVC
+    /// numbers without a handler are taken as the SVCall exception through the vector table.
+    pub fn set_svc_handler<F>(&mut self, number: u8, handler: F)
+    where
+        F: FnMut([u32; 4], &mut Memory) -> u32 + 'static,
+    {
+        self.svc_handlers.insert(number, Box::new(handler));
+    }
+
+    /// Removes the host side service for an SVC number, returning it to the SVCall exception
+    pub fn remove_svc_handler(&mut self, number: u8) -> Option<SvcHandler> {
+        self.svc_handlers.remove(&number)
     }
 
     pub fn load(&mut self, image: ProgramImage) {
-        self.reset = image.entry();
+        self.entry = image.entry();
         self.mem = image.into_raw_image();
     }
 
+    /// TakeReset() from the ARMv7-M Architecture Reference Manual
+    /// 
+    /// All registers take their reset values, leaving the processor privileged in Thread mode on the main stack. The
+    /// main stack pointer and the reset handler are read from the vector table, and bit 0 of the handler becomes the
+    /// Thumb bit, so a handler address with bit 0 clear faults on the first instruction.
     pub fn reset(&mut self) {
-        self.reg[Register::PC] = self.reset as u32 - 1;
+        self.reg = RegisterBank::new();
+        self.reg[Register::LR] = 0xFFFF_FFFF;
+        self.scs.reset();
+        self.locked_up = false;
+
+        let (sp, start) = match self.reset_source {
+            ResetSource::VectorTable => {
+                let table = self.scs.vtor() as usize;
+
+                match (self.mem.read(table, 4), self.mem.read(table + 4, 4)) {
+                    (Ok(sp), Ok(start)) => (sp, start),
+
+                    // A vector read during reset faults at the priority of Reset, which locks up the processor
+                    _ => {
+                        print!("[Reset] ({})", Fault::VectTbl);
+                        self.lock_up();
+                        println!();
+                        return
+                    },
+                }
+            },
+
+            ResetSource::ImageEntry => (self.mem.allocated_bytes() as u32, self.entry as u32),
+        };
+
+        self.reg[Register::MSP] = sp & !0b11;
+        self.reg[Register::PC] = self.blx_write_pc(start);
     }
 
     pub fn run(&mut self) {
         self.fde_loop()
     }
     
-    fn fetch(&self) -> u16 {
-        let at = self.reg[Register::PC] as usize;
-        self.mem.read_u16(at)
+    /// Fetches the instruction halfword at an address
+    ///
+    /// Instructions are fetched from memory only, the regions of the system address map marked Execute Never, which
+    /// include the System Control Space, fault on a fetch.
+    fn fetch(&self, address: u32) -> Result<u16, Fault> {
+        if memory::execute_never(address) {
+            return Err(Fault::IAccViol)
+        }
+
+        self.mem.read(address as usize, 2).map(|halfword| halfword as u16).map_err(|_| Fault::IBusErr)
     }
 
     fn decode(&self, instruction: u16) -> InstrThumb16 {
-        let decoded = self.dct[instruction as usize];
-        decoded
+        DECODE_TABLE[instruction as usize]
+    }
+
+    /// Reads a data item from the memory map, where the System Control Space is routed to its registers
+    ///
+    /// An access to an address with no memory is a precise bus error, as is an unprivileged access to the System
+    /// Control Space.
+    fn read_memory(&mut self, address: u32, size: DataSize) -> Result<u32, Fault> {
+        if Scs::contains(address) {
+            if !self.reg.privileged() {
+                return Err(Fault::PreciseErr { address })
+            }
+
+            return Ok(self.scs.read(address - SCS_BASE, size))
+        }
+
+        self.mem.read(address as usize, size.bytes()).map_err(|_| Fault::PreciseErr { address })
+    }
+
+    /// Writes a data item to the memory map, where the System Control Space is routed to its registers
+    ///
+    /// An access to an address with no memory is a precise bus error, as is an unprivileged access to the System
+    /// Control Space.
+    fn write_memory(&mut self, address: u32, size: DataSize, value: u32) -> Result<(), Fault> {
+        if Scs::contains(address) {
+            if !self.reg.privileged() {
+                return Err(Fault::PreciseErr { address })
+            }
+
+            self.scs.write(address - SCS_BASE, size, value);
+            return Ok(())
+        }
+
+        self.mem.write(address as usize, size.bytes(), value).map_err(|_| Fault::PreciseErr { address })
+    }
+
+    /// Applies the UNPREDICTABLE policy to a decoded instruction, returning whether execution may continue
+    fn permit(&self, unpredictable: Option<Unpredictable>) -> bool {
+        match unpredictable {
+            Some(reason) => {
+                print!(" (UNPREDICTABLE: {})", reason);
+                self.policy == UnpredictablePolicy::Lenient
+            },
+            None => true,
+        }
+    }
+
+    /// Raises a fault, returning the address execution continues at
+    ///
+    /// The faulting instruction is the return address of the fault exception, so that its handler may retry it.
+    fn raise(&mut self, fault: Fault, return_address: u32) -> u32 {
+        match self.escalate(fault) {
+            Some(exception) => self.exception_entry(exception, return_address),
+            None => return_address,
+        }
+    }
+
+    /// Records a fault and returns the exception it is taken as, or `None` if the processor locks up
+    ///
+    /// A configurable fault escalates to HardFault when its exception is disabled in SHCSR, or when its priority is not
+    /// high enough to preempt the current execution priority. The configurable faults are disabled out of reset, so
+    /// every fault escalates until they are enabled. A HardFault which cannot preempt, such as a fault within the NMI
+    /// or HardFault handlers, locks up the processor, which stops execution.
+    fn escalate(&mut self, fault: Fault) -> Option<Exception> {
+        print!(" ({})", fault);
+        self.scs.record_fault(fault);
+        self.synchronous(fault.exception())
+    }
+
+    /// Returns the exception a synchronous exception is taken as, or `None` if the processor locks up
+    ///
+    /// An exception raised by execution, a fault or SVCall, cannot wait for the execution priority to fall. One which
+    /// is disabled or cannot preempt is escalated to HardFault, with HFSR.FORCED set.
+    fn synchronous(&mut self, exception: Exception) -> Option<Exception> {
+        let execution_priority = self.execution_priority();
+        let nvic = &self.scs.nvic;
+        let number = exception.number();
+
+        let exception = if !nvic.is_enabled(number) || nvic.group_priority(nvic.priority(number)) >= execution_priority {
+            if exception != Exception::HardFault {
+                self.scs.record_forced();
+            }
+
+            Exception::HardFault
+        } else {
+            exception
+        };
+
+        if exception == Exception::HardFault && execution_priority <= -1 {
+            self.lock_up();
+            return None
+        }
+
+        Some(exception)
+    }
+
+    /// Enters the lockup state, which stops execution
+    fn lock_up(&mut self) {
+        print!(" (lockup)");
+        self.locked_up = true;
+    }
+
+    /// The priority below which pending exceptions preempt execution, ExecutionPriority() from the ARMv7-M
+    /// Architecture Reference Manual
+    ///
+    /// The exception mask registers boost the priority of the active exceptions, PRIMASK to 0, FAULTMASK to -1 and a
+    /// non-zero BASEPRI to its group priority, so that only the exceptions above the boosted priority may preempt.
+    fn execution_priority(&self) -> i16 {
+        let nvic = &self.scs.nvic;
+
+        let boosted = if self.reg[Register::FAULTMASK] & 1 != 0 {
+            -1
+        } else if self.reg[Register::PRIMASK] & 1 != 0 {
+            0
+        } else if self.reg[Register::BASEPRI] != 0 {
+            nvic.group_priority(self.reg[Register::BASEPRI] as i16)
+        } else {
+            BASE_PRIORITY
+        };
+
+        nvic.execution_priority().min(boosted)
+    }
+
+    /// Takes an exception, returning the address of its handler
+    ///
+    /// PushStack() and ExceptionTaken() from the ARMv7-M Architecture Reference Manual. The basic frame is pushed to
+    /// the current stack, which is aligned to 8 bytes with the adjustment recorded in bit 9 of the stacked xPSR.
+    ///
+    /// The handler is that of the highest priority pending exception once the frame is pushed. A higher priority
+    /// exception which arrives during stacking is taken first, a late arrival, and the exception which caused the
+    /// entry stays pending.
+    fn exception_entry(&mut self, exception: Exception, return_address: u32) -> u32 {
+        let sp = self.reg[Register::SP];
+        let frame = sp.wrapping_sub(0x20) & !0b100;
+        let xpsr = self.reg.xpsr() & !(1 << 9) | (sp & 0b100) << 7;
+
+        let words = [
+            self.reg.read(0), self.reg.read(1), self.reg.read(2), self.reg.read(3),
+            self.reg.read(12), self.reg[Register::LR], return_address, xpsr,
+        ];
+
+        // A failed push pends the BusFault, which is taken in place of the exception if it has the higher priority
+        for (i, word) in words.iter().enumerate() {
+            if self.write_memory(frame.wrapping_add(4 * i as u32), DataSize::Word, *word).is_err() {
+                if let Some(fault) = self.escalate(Fault::StkErr) {
+                    self.scs.nvic.set_pending(fault.number());
+                }
+
+                break
+            }
+        }
+
+        self.reg[Register::SP] = frame;
+        self.reg[Register::LR] = if self.reg.handler_mode() {
+            EXC_RETURN_HANDLER
+        } else if self.reg.spsel() {
+            EXC_RETURN_THREAD_PSP
+        } else {
+            EXC_RETURN_THREAD_MSP
+        };
+
+        self.scs.nvic.set_pending(exception.number());
+        let exception = self.scs.nvic.pending_exception().unwrap_or(exception);
+
+        self.exception_taken(exception)
+    }
+
+    /// ExceptionTaken() from the ARMv7-M Architecture Reference Manual, returns the address of the handler
+    ///
+    /// A vector which cannot be read raises a VECTTBL HardFault in place of the exception, and locks up the processor if
+    /// it is the HardFault vector itself.
+    fn exception_taken(&mut self, exception: Exception) -> u32 {
+        let number = exception.number();
+        let vector = match self.read_memory(self.scs.vtor().wrapping_add(4 * number as u32), DataSize::Word) {
+            Ok(vector) => vector,
+            Err(_) if exception == Exception::HardFault => {
+                print!(" ({})", Fault::VectTbl);
+                self.scs.record_fault(Fault::VectTbl);
+                self.lock_up();
+                return self.reg[Register::PC]
+            },
+            Err(_) => match self.escalate(Fault::VectTbl) {
+                Some(hardfault) => return self.exception_taken(hardfault),
+                None => return self.reg[Register::PC],
+            },
+        };
+
+        print!(" ({} taken)", exception);
+
+        self.reg[Register::IPSR] = number as u32;
+        self.reg.set_itstate(ItState::default());
+        self.reg.set_spsel(false);
+        self.scs.nvic.clear_pending(number);
+        self.scs.nvic.activate(number);
+
+        self.blx_write_pc(vector)
+    }
+
+    /// ExceptionReturn() and PopStack() from the ARMv7-M Architecture Reference Manual, returns the address execution
+    /// resumes at
+    ///
+    /// The EXC_RETURN value selects the mode and stack to return to. Returning to Thread mode is only permitted from
+    /// the last active exception.
+    ///
+    /// A pending exception which would preempt the execution being returned to is tail-chained, its handler is
+    /// entered directly, without popping and pushing the frame that is already on the stack.
+    fn exception_return(&mut self, exc_return: u32) -> u32 {
+        let returning = (self.reg[Register::IPSR] & 0x1FF) as u16;
+        let nested = self.scs.nvic.nested_activation();
+
+        let stack = match exc_return & 0b1111 {
+            _ if !self.scs.nvic.is_active(returning) => None,
+            0b0001 => Some((Register::MSP, false)),
+            0b1001 if nested == 1 => Some((Register::MSP, false)),
+            0b1101 if nested == 1 => Some((Register::PSP, true)),
+            _ => None,
+        };
+
+        self.scs.nvic.deactivate(returning);
+
+        // Returning from any exception but NMI clears FAULTMASK
+        if returning != Exception::Nmi.number() {
+            self.reg[Register::FAULTMASK] = 0;
+        }
+
+        let (sp, spsel) = match stack {
+            Some(stack) => stack,
+            None => {
+                // The fault is taken without stacking, from the state of the failed return
+                return match self.escalate(Fault::InvPc) {
+                    Some(fault) => {
+                        self.reg[Register::LR] = 0xF000_0000 | exc_return & 0x0FFF_FFFF;
+                        self.exception_taken(fault)
+                    },
+                    None => self.reg[Register::PC],
+                }
+            },
+        };
+
+        if let Some(exception) = self.scs.nvic.preempting(self.execution_priority()) {
+            print!(" (tail-chained)");
+            self.reg[Register::LR] = exc_return;
+            return self.exception_taken(exception)
+        }
+
+        let frame = self.reg[sp];
+        let mut words = [0; 8];
+
+        for (i, word) in words.iter_mut().enumerate() {
+            match self.read_memory(frame.wrapping_add(4 * i as u32), DataSize::Word) {
+                Ok(value) => *word = value,
+
+                // A failed pop abandons the return, and the fault is tail-chained from the frame left on the stack
+                Err(_) => return match self.escalate(Fault::UnstkErr) {
+                    Some(fault) => {
+                        self.reg[Register::LR] = exc_return;
+                        self.exception_taken(fault)
+                    },
+                    None => self.reg[Register::PC],
+                },
+            }
+        }
+
+        let [r0, r1, r2, r3, r12, lr, pc, xpsr] = words;
+
+        print!(" (exception return)");
+
+        self.reg.write(0, r0);
+        self.reg.write(1, r1);
+        self.reg.write(2, r2);
+        self.reg.write(3, r3);
+        self.reg.write(12, r12);
+        self.reg[Register::LR] = lr;
+        self.reg[sp] = frame.wrapping_add(0x20) | (xpsr >> 7) & 0b100;
+        self.reg.set_xpsr(xpsr);
+        self.reg.set_spsel(spsel);
+
+        pc & !1
     }
 
     /// Steps to execute an instruction
@@ -79,7 +519,7 @@ impl Processor {
     ///     -> Decode 16 bit instruction via DCT lookup
     ///         -> Match instruction to appropriate execution branch
     ///             -> IF Thumb2 extended instruction, fetch second part of instruction
-    ///                 -> Decode 32 bit instruction via tree search
+    ///                 -> Decode 32 bit instruction by its encoding class
     ///                     -> Execute 32 bit instruction
     ///             -> IF standard Thumb16 instruction, execute instruction
     /// 
@@ -90,31 +530,1580 @@ impl Processor {
         println!("Beginning execution...");
 
         // Core execution loop
-        loop {
-            print!("[PC: {:06X}] ", self.reg[Register::PC]);
-            let fetched = self.fetch();
-            let decoded = self.decode(fetched);
-
-            print!("{:016b} {:04X} ", fetched, fetched);
-
-            match decoded {
-                InstrThumb16::BranchE1{ cond, imm } => {
-                    let target = (imm as i8) as i32;
-                    print!("exec branch e1: [cond, target] = [{:04X}, {:#06X}] ({}:{})", cond, target, cond, imm);
-                    self.reg[Register::PC] = ((self.reg[Register::PC] as i32) + target) as u32;
-                },
+        while self.step() {
+            cycles += 1;
+            if cycles >= debug_cycle_limit {
+                break;
+            }
+        }
+    }
 
-                u => {
-                    print!("unhandled instruction: {:?}", u)
+    /// Fetches, decodes and executes a single instruction, returning false if execution must stop
+    ///
+    /// A pending exception which preempts the current execution priority is taken in place of the instruction. The
+    /// timers count the nominal cycles of the instruction executed, or of the exception entry taken in its place. A
+    /// system reset requested through AIRCR.SYSRESETREQ is taken once the requesting instruction completes.
+    pub fn step(&mut self) -> bool {
+        let cycles = match self.step_instruction() {
+            Some(cycles) => cycles,
+            None => return false,
+        };
+
+        self.scs.tick(cycles);
+
+        if self.scs.take_reset_request() {
+            println!("[Reset] (SYSRESETREQ)");
+            self.reset();
+        }
+
+        !self.locked_up
+    }
+
+    /// Executes a single instruction, returning the cycles it took, or `None` if execution must stop
+    ///
+    /// The 16 bit instructions take the nominal cycle count of their descriptions. The 32 bit instructions have no
+    /// descriptions and take a single cycle, plus the pipeline refill when they branch.
+    fn step_instruction(&mut self) -> Option<u32> {
+        let pc = self.reg[Register::PC];
+
+        if let Some(exception) = self.scs.nvic.preempting(self.execution_priority()) {
+            print!("[PC: {:06X}]", pc);
+            self.reg[Register::PC] = self.exception_entry(exception, pc);
+            println!();
+            return self.running(EXCEPTION_ENTRY_CYCLES);
+        }
+
+        let fetched = match self.fetch(pc) {
+            Ok(fetched) => fetched,
+            Err(fault) => {
+                print!("[PC: {:06X}]", pc);
+                return self.fault(fault, pc)
+            },
+        };
+
+        let decoded = self.decode(fetched);
+
+        print!("[PC: {:06X}] {:04X} ", pc, fetched);
+
+        // Executing any instruction while not in Thumb state faults
+        if !self.reg.thumb() {
+            return self.fault(Fault::InvState, pc)
+        }
+
+        // Instructions within an IT block execute only if the condition of their slot passes
+        let itstate = self.reg.itstate();
+        let it = itstate.position();
+        let passed = itstate.condition().is_none_or(|cond| self.reg.flags().condition_passed(cond));
+
+        // ITAdvance() follows every instruction within a block, whether or not its condition passed. It is applied
+        // ahead of execution so that an IT instruction or an exception return may replace ITSTATE, and undone if the
+        // instruction faults.
+        if it.in_it_block() {
+            self.reg.set_itstate(itstate.advance());
+        }
+
+        let next = match decoded {
+            InstrThumb16::Thumb2A { .. } | InstrThumb16::Thumb2B { .. } | InstrThumb16::Thumb2C { .. } => {
+                let second = match self.fetch(pc.wrapping_add(2)) {
+                    Ok(second) => second,
+                    Err(fault) => {
+                        self.reg.set_itstate(itstate);
+                        return self.fault(fault, pc)
+                    },
+                };
+
+                let wide = InstrThumb32::decode(fetched, second);
+                print!("{:04X} {}", second, wide.disassemble(pc));
+
+                if !self.permit(wide.unpredictable(it)) {
+                    println!();
+                    return None;
+                }
+
+                if passed {
+                    self.execute_thumb32(wide).map(|next| match next {
+                        Some(target) => (target, 1 + PIPELINE_REFILL),
+                        None => (pc.wrapping_add(4), 1),
+                    })
+                } else {
+                    print!(" (condition failed)");
+                    Ok((pc.wrapping_add(4), 1))
+                }
+            },
+
+            u => {
+                print!("     {}", u.disassemble(pc));
+
+                if !self.permit(u.unpredictable(fetched, it)) {
+                    println!();
+                    return None;
+                }
+
+                if passed {
+                    let cycles = self.analyzer.info(u).map_or(1, |info| info.cycles());
+                    self.execute_thumb16(u, it).map(|next| (next.unwrap_or_else(|| pc.wrapping_add(2)), cycles))
+                } else {
+                    print!(" (condition failed)");
+                    Ok((pc.wrapping_add(2), 1))
                 }
             }
+        };
 
-            print!("\n");
+        // A faulting instruction is the return address of the fault exception, so it is stacked with the ITSTATE it
+        // began with and is retried within its IT block. An unimplemented instruction stops execution in place.
+        let (next, cycles) = match next {
+            Ok(next) => next,
 
-            cycles = cycles + 1;
-            if cycles >= debug_cycle_limit {
-                break;
+            Err(Stop::Fault(fault)) => {
+                self.reg.set_itstate(itstate);
+                (self.raise(fault, pc), EXCEPTION_ENTRY_CYCLES)
+            },
+
+            Err(Stop::Unimplemented) => {
+                println!(" (unimplemented, stopping)");
+                self.reg.set_itstate(itstate);
+                return None
+            },
+        };
+
+        println!();
+
+        self.reg[Register::PC] = next;
+        self.running(cycles)
+    }
+
+    /// Raises a fault in place of the instruction at `pc`, returning the cycles taken if execution may continue
+    fn fault(&mut self, fault: Fault, pc: u32) -> Option<u32> {
+        self.reg[Register::PC] = self.raise(fault, pc);
+        println!();
+        self.running(EXCEPTION_ENTRY_CYCLES)
+    }
+
+    /// The cycles taken by a step, or `None` once the processor has locked up
+    fn running(&self, cycles: u32) -> Option<u32> {
+        if self.locked_up { None } else { Some(cycles) }
+    }
+
+    /// Executes a 32 bit instruction, returning the address of the next instruction if it branches
+    fn execute_thumb32(&mut self, instr: InstrThumb32) -> Result<Option<u32>, Stop> {
+        use InstrThumb32::*;
+
+        let pc = self.reg[Register::PC];
+
+        let next = match instr {
+            BranchE3 { cond, imm } => {
+                if self.reg.flags().condition_passed(cond) {
+                    Some(self.branch_target(imm))
+                } else {
+                    None
+                }
+            },
+
+            BranchE4 { imm } => Some(self.branch_target(imm)),
+
+            BranchL { imm } => {
+                self.reg[Register::LR] = pc.wrapping_add(4) | 1;
+                Some(self.branch_target(imm))
+            },
+
+            // Division by zero gives zero, unless it is trapped
+            Sdiv { rn, rd, rm } | Udiv { rn, rd, rm } => {
+                let (n, m) = (self.reg.read(rn), self.reg.read(rm));
+
+                let quotient = match instr {
+                    _ if m == 0 && self.scs.div_0_trp() => return Err(Fault::DivByZero.into()),
+                    _ if m == 0 => 0,
+                    Sdiv { .. } => (n as i32).wrapping_div(m as i32) as u32,
+                    _ => n / m,
+                };
+
+                self.reg.write(rd, quotient);
+                None
+            },
+
+            Mrs { rd, sysm } => {
+                let value = self.read_special_register(sysm);
+                self.reg.write(rd, value);
+                None
+            },
+
+            Msr { rn, mask, sysm } => {
+                self.write_special_register(sysm, mask, self.reg.read(rn));
+                None
+            },
+
+            Udf { .. } | Undefined => return Err(Fault::UndefInstr.into()),
+
+            // No coprocessors are implemented
+            Cdp { .. } | Cdp2 { .. } | LdcImm { .. } | Ldc2Imm { .. } | LdcLit { .. } | Ldc2Lit { .. }
+                | Mcr { .. } | Mcr2 { .. } | Mcrr { .. } | Mcrr2 { .. } | Mrc { .. } | Mrc2 { .. }
+                | Mrrc { .. } | Mrrc2 { .. } | Stc { .. } | Stc2 { .. } => return Err(Fault::NoCp.into()),
+
+            // Memory accesses complete in program order, so the barriers have nothing to wait for
+            Nop | Yield | Wfe | Wfi | Sev | Dbg { .. } | Dmb { .. } | Dsb { .. } | Isb { .. } => None,
+
+            _ => return Err(Stop::Unimplemented),
+        };
+
+        Ok(next)
+    }
+
+    /// Performs a single data item transfer between a register and memory
+    ///
+    /// Unaligned halfword and word accesses are permitted unless CCR.UNALIGN_TRP is set.
+    fn transfer(&mut self, transfer: Transfer) -> Result<(), Fault> {
+        if !(transfer.address as usize).is_multiple_of(transfer.size.bytes()) && self.scs.unalign_trp() {
+            return Err(Fault::Unaligned)
+        }
+
+        match transfer.direction {
+            Direction::Load { .. } => {
+                let item = self.read_memory(transfer.address, transfer.size)?;
+                self.reg.write(transfer.rt, transfer.extend(item));
+            },
+
+            Direction::Store => {
+                let value = self.reg.read(transfer.rt);
+                self.write_memory(transfer.address, transfer.size, value)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Performs a multiple register transfer, returning the branch target if the PC is loaded
+    ///
+    /// The address must be word aligned. A fault part way through the transfer leaves the base register unchanged.
+    fn transfer_multiple(&mut self, transfer: MultipleTransfer) -> Result<Option<u32>, Fault> {
+        let mut address = transfer.address;
+        let mut loaded_pc = None;
+
+        if address & 0b11 != 0 {
+            return Err(Fault::Unaligned)
+        }
+
+        for n in (0..16).filter(|n| transfer.list & (1 << n) != 0) {
+            if !transfer.load {
+                self.write_memory(address, DataSize::Word, self.reg.read(n))?;
+            } else if n == 15 {
+                loaded_pc = Some(self.read_memory(address, DataSize::Word)?);
+            } else {
+                let value = self.read_memory(address, DataSize::Word)?;
+                self.reg.write(n, value);
+            }
+
+            address = address.wrapping_add(4);
+        }
+
+        if let Some((rn, value)) = transfer.writeback {
+            self.reg.write(rn, value);
+        }
+
+        // The PC is written last, as an exception return replaces the stack pointer
+        Ok(loaded_pc.map(|pc| self.bx_write_pc(pc)))
+    }
+
+    /// BXWritePC() from the ARMv7-M Architecture Reference Manual, returns the branch target
+    ///
+    /// Bit 0 of the address is moved into EPSR.T, the Thumb bit, rather than the PC. In Handler mode an EXC_RETURN
+    /// value, with bits [31:28] set, performs an exception return instead.
+    fn bx_write_pc(&mut self, address: u32) -> u32 {
+        if self.reg.handler_mode() && address >> 28 == 0xF {
+            self.exception_return(address)
+        } else {
+            self.blx_write_pc(address)
+        }
+    }
+
+    /// BLXWritePC() from the ARMv7-M Architecture Reference Manual, returns the branch target
+    ///
+    /// A target with bit 0 clear leaves EPSR.T clear, and the next instruction raises an INVSTATE UsageFault.
+    fn blx_write_pc(&mut self, address: u32) -> u32 {
+        self.reg.set_thumb(address & 1 != 0);
+        address & !1
+    }
+
+    /// The target of a PC relative branch, BranchWritePC(PC + imm) where PC reads as the instruction address plus 4
+    fn branch_target(&self, imm: i32) -> u32 {
+        self.reg.read(15).wrapping_add(imm as u32) & !1
+    }
+
+    /// CallSupervisor() from the ARMv7-M Architecture Reference Manual, returns the address execution continues at
+    ///
+    /// The SVCall exception is taken with the following instruction as its return address, its handler finds the SVC
+    /// number in the instruction before the stacked PC. A number with a host side handler is serviced without taking
+    /// the exception.
+    fn supervisor_call(&mut self, number: u8, return_address: u32) -> u32 {
+        if let Some(handler) = self.svc_handlers.get_mut(&number) {
+            let args = [self.reg.read(0), self.reg.read(1), self.reg.read(2), self.reg.read(3)];
+            let result = handler(args, &mut self.mem);
+            self.reg.write(0, result);
+            print!(" (serviced on host)");
+            return return_address
+        }
+
+        match self.synchronous(Exception::SvCall) {
+            Some(exception) => self.exception_entry(exception, return_address),
+            None => return_address,
+        }
+    }
+
+    /// Reads a special register for MRS, by its SYSm encoding
+    ///
+    /// | SYSm | Register    | SYSm | Register    | SYSm | Register    |
+    /// | 0    | APSR        | 5    | IPSR        | 16   | PRIMASK     |
+    /// | 1    | IAPSR       | 6    | EPSR        | 17   | BASEPRI     |
+    /// | 2    | EAPSR       | 7    | IEPSR       | 18   | BASEPRI_MAX |
+    /// | 3    | XPSR        | 8    | MSP         | 19   | FAULTMASK   |
+    /// |      |             | 9    | PSP         | 20   | CONTROL     |
+    ///
+    /// The program status registers read as the combination of the APSR and IPSR they name, the execution state bits of
+    /// the EPSR always read as zero. The stack pointers and the exception mask registers read as zero when unprivileged.
+    fn read_special_register(&self, sysm: u8) -> u32 {
+        let privileged = self.reg.privileged();
+
+        match sysm {
+            0..=7 => {
+                let apsr = if sysm & 0b100 == 0 { self.reg[Register::APSR] } else { 0 };
+                let ipsr = if sysm & 0b001 != 0 { self.reg[Register::IPSR] } else { 0 };
+                apsr | ipsr
+            },
+
+            8 if privileged => self.reg[Register::MSP],
+            9 if privileged => self.reg[Register::PSP],
+            16 if privileged => self.reg[Register::PRIMASK],
+            17 | 18 if privileged => self.reg[Register::BASEPRI],
+            19 if privileged => self.reg[Register::FAULTMASK],
+            20 => self.reg[Register::CONTROL],
+            _ => 0,
+        }
+    }
+
+    /// Writes a special register for MSR, by its SYSm encoding, see `read_special_register`
+    ///
+    /// Only the APSR may be written when unprivileged, other writes are ignored. Bit 1 of the mask writes the NZCVQ
+    /// flags and bit 0 the GE flags. The IPSR and EPSR cannot be written.
+    ///
+    /// BASEPRI holds only the implemented priority bits. BASEPRI_MAX writes BASEPRI only when that raises the priority,
+    /// and FAULTMASK cannot be set from the NMI or HardFault handlers. CONTROL.SPSEL can only be written in Thread mode,
+    /// where it switches the stack pointer immediately.
+    fn write_special_register(&mut self, sysm: u8, mask: u8, value: u32) {
+        if sysm <= 7 {
+            if sysm & 0b100 == 0 {
+                let fields = if mask & 0b10 != 0 { 0xF800_0000 } else { 0 } | if mask & 0b01 != 0 { 0x000F_0000 } else { 0 };
+                self.reg[Register::APSR] = self.reg[Register::APSR] & !fields | value & fields;
             }
+
+            return
+        }
+
+        if !self.reg.privileged() {
+            return
+        }
+
+        let implemented = 0xFFu32 << (8 - self.scs.nvic.priority_bits()) & 0xFF;
+        let basepri = self.reg[Register::BASEPRI];
+
+        match sysm {
+            8 => self.reg[Register::MSP] = value & !0b11,
+            9 => self.reg[Register::PSP] = value & !0b11,
+            16 => self.reg[Register::PRIMASK] = value & 1,
+            17 => self.reg[Register::BASEPRI] = value & implemented,
+
+            18 => {
+                let value = value & implemented;
+
+                if value != 0 && (value < basepri || basepri == 0) {
+                    self.reg[Register::BASEPRI] = value;
+                }
+            },
+
+            19 if self.execution_priority() > -1 => self.reg[Register::FAULTMASK] = value & 1,
+
+            20 => {
+                let spsel = if self.reg.handler_mode() { self.reg.spsel() } else { value & 0b10 != 0 };
+                self.reg[Register::CONTROL] = value & 0b01;
+                self.reg.set_spsel(spsel);
+            },
+
+            _ => (),
+        }
+    }
+
+    /// Executes a 16 bit instruction, returning the address of the next instruction if it branches
+    fn execute_thumb16(&mut self, instr: InstrThumb16, it: ItPosition) -> Result<Option<u32>, Stop> {
+        let pc = self.reg[Register::PC];
+
+        if let Some(outcome) = instructions::execute_data_processing(instr, |n| self.reg.read(n), self.reg.flags(), !it.in_it_block()) {
+            self.reg.set_flags(outcome.flags);
+
+            return Ok(match outcome.write {
+                // ALUWritePC(), a branch which does not interwork
+                Some((15, value)) => Some(value & !1),
+
+                Some((rd, value)) => {
+                    self.reg.write(rd, value);
+                    None
+                },
+
+                None => None,
+            })
+        }
+
+        if let Some(transfer) = instructions::load_store_single(instr, |n| self.reg.read(n)) {
+            self.transfer(transfer)?;
+            return Ok(None)
+        }
+
+        if let Some(transfer) = instructions::load_store_multiple(instr, |n| self.reg.read(n)) {
+            return Ok(self.transfer_multiple(transfer)?)
         }
+
+        let next = match instr {
+            InstrThumb16::BranchE1 { cond, imm } => {
+                if self.reg.flags().condition_passed(cond) {
+                    Some(self.branch_target(imm as i32))
+                } else {
+                    None
+                }
+            },
+
+            InstrThumb16::BranchE2 { imm } => Some(self.branch_target(imm as i32)),
+
+            InstrThumb16::BranchX { rm } => Some(self.bx_write_pc(self.reg.read(rm))),
+
+            InstrThumb16::BranchLx { rm } => {
+                let target = self.reg.read(rm);
+                self.reg[Register::LR] = pc.wrapping_add(2) | 1;
+                Some(self.blx_write_pc(target))
+            },
+
+            InstrThumb16::Cbz { imm, rn } | InstrThumb16::Cbnz { imm, rn } => {
+                let nonzero = matches!(instr, InstrThumb16::Cbnz { .. });
+
+                if (self.reg.read(rn) != 0) == nonzero {
+                    Some(self.branch_target(imm as i32))
+                } else {
+                    None
+                }
+            },
+
+            InstrThumb16::IfThen { cond, mask } => {
+                self.reg.set_itstate(ItState::new(cond, mask));
+                None
+            },
+
+            // CPS has no effect when unprivileged, and FAULTMASK cannot be set from the NMI or HardFault handlers
+            InstrThumb16::Cps { imm, fi, ff } => {
+                if self.reg.privileged() {
+                    if fi != 0 {
+                        self.reg[Register::PRIMASK] = imm as u32;
+                    }
+
+                    if ff != 0 && (imm == 0 || self.execution_priority() > -1) {
+                        self.reg[Register::FAULTMASK] = imm as u32;
+                    }
+                }
+
+                None
+            },
+
+            InstrThumb16::Svc { imm } => Some(self.supervisor_call(imm, pc.wrapping_add(2))),
+
+            // Hints have no architectural effect on a single processor without events or low power states
+            InstrThumb16::Nop | InstrThumb16::Yield | InstrThumb16::Wfe | InstrThumb16::Wfi | InstrThumb16::Sev => None,
+
+            InstrThumb16::Udf { .. } | InstrThumb16::Undefined | InstrThumb16::UnknownInstruction => {
+                return Err(Fault::UndefInstr.into())
+            },
+
+            _ => return Err(Stop::Unimplemented),
+        };
+
+        Ok(next)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::ConditionFlags;
+
+    #[test]
+    fn static_decode_table_matches_descriptions() {
+        let generated = InstrThumb16::generate_decode_table();
+
+        for (encoded, decoded) in generated.iter().enumerate() {
+            assert_eq!(DECODE_TABLE[encoded], *decoded, "decoding {:#06X}", encoded);
+        }
+    }
+
+    fn processor_with_program(halfwords: &[u16]) -> Processor {
+        let bytes: Vec<u8> = halfwords.iter().flat_map(|hw| hw.to_le_bytes().to_vec()).collect();
+
+        let mut processor = Processor::new();
+        processor.mem = Memory::alloc(bytes.len());
+        processor.mem.write_bytes(0, &bytes).unwrap();
+        processor.reg.set_thumb(true);
+        processor
+    }
+
+    #[test]
+    fn reset_from_vector_table() {
+        let mut processor = processor_with_program(&[
+            0x1000, 0x0000, // initial sp
+            0x0011, 0x0000, // reset handler
+            0x0000, 0x0000,
+            0x0000, 0x0000,
+            0x2001,         // movs r0, #1
+        ]);
+
+        processor.reg.write(0, 0xFFFF);
+        processor.reset();
+
+        assert_eq!(processor.reg[Register::MSP], 0x1000);
+        assert_eq!(processor.reg.read(13), 0x1000);
+        assert_eq!(processor.reg[Register::PC], 16);
+        assert_eq!(processor.reg[Register::LR], 0xFFFF_FFFF);
+        assert_eq!(processor.reg[Register::CONTROL], 0);
+        assert_eq!(processor.reg.read(0), 0);
+        assert!(processor.reg.thumb());
+        assert!(!processor.reg.handler_mode());
+
+        assert!(processor.step());
+        assert_eq!(processor.reg.read(0), 1);
+
+        processor.mem.write_u32(4, 8);
+        processor.reset();
+        assert!(!processor.reg.thumb());
+        assert!(processor.step(), "a reset handler with bit 0 clear raises INVSTATE");
+        assert_eq!(processor.reg[Register::IPSR], 3);
+        assert!(!processor.step(), "a HardFault handler with bit 0 clear locks up");
+
+        processor.set_reset_source(ResetSource::ImageEntry);
+        processor.entry = 0x11;
+        processor.reset();
+        assert_eq!(processor.reg[Register::PC], 0x10);
+        assert_eq!(processor.reg.read(13), processor.mem.allocated_bytes() as u32);
+        assert!(processor.reg.thumb());
+    }
+
+    fn processor_with_vector_table() -> Processor {
+        let mut processor = processor_with_program(&[
+            0x1000, 0x0000, // initial sp
+            0x0041, 0x0000, // reset
+            0x0000, 0x0000, // nmi
+            0x0061, 0x0000, // hardfault
+            0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+            0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+            0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+            0x0051, 0x0000, // systick
+            0x2001,         // 0x40: movs r0, #1
+            0x2102,         //       movs r1, #2
+            0xE7FE,         //       b .
+            0xBF00, 0xBF00, 0xBF00, 0xBF00, 0xBF00,
+            0x2007,         // 0x50: movs r0, #7
+            0xB510,         //       push {r4, lr}
+            0xBD10,         //       pop {r4, pc}
+            0xBF00, 0xBF00, 0xBF00, 0xBF00, 0xBF00,
+            0x2203,         // 0x60: movs r2, #3
+            0x4770,         //       bx lr
+        ]);
+
+        processor.reset();
+        processor
+    }
+
+    fn take(processor: &mut Processor, exception: Exception) {
+        let return_address = processor.reg[Register::PC];
+        processor.reg[Register::PC] = processor.exception_entry(exception, return_address);
+    }
+
+    #[test]
+    fn exception_entry_and_return() {
+        let mut processor = processor_with_vector_table();
+        processor.step();
+        processor.step();
+        processor.reg.set_flags(ConditionFlags { n: true, z: false, c: false, v: true });
+
+        take(&mut processor, Exception::SysTick);
+        assert_eq!(processor.reg[Register::PC], 0x50);
+        assert_eq!(processor.reg[Register::IPSR], 15);
+        assert_eq!(processor.reg[Register::LR], EXC_RETURN_THREAD_MSP);
+        assert_eq!(processor.reg[Register::MSP], 0xFE0);
+        assert!(processor.reg.handler_mode());
+
+        let frame: Vec<u32> = (0..8).map(|i| processor.mem.read_u32(0xFE0 + 4 * i)).collect();
+        assert_eq!(frame, [1, 2, 0, 0, 0, 0xFFFF_FFFF, 0x44, 0x9100_0000]);
+
+        for _ in 0..3 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg[Register::PC], 0x44, "pop {{pc}} with an EXC_RETURN returns from the exception");
+        assert_eq!(processor.reg.read(0), 1);
+        assert_eq!(processor.reg[Register::MSP], 0x1000);
+        assert_eq!(processor.reg[Register::IPSR], 0);
+        assert_eq!(processor.reg.flags(), ConditionFlags { n: true, z: false, c: false, v: true });
+        assert_eq!(processor.scs.nvic.nested_activation(), 0);
+    }
+
+    #[test]
+    fn exception_nesting_and_process_stack() {
+        let mut processor = processor_with_vector_table();
+        processor.reg.set_spsel(true);
+        processor.reg[Register::PSP] = 0xF04;
+
+        take(&mut processor, Exception::SysTick);
+        assert_eq!(processor.reg[Register::LR], EXC_RETURN_THREAD_PSP);
+        assert_eq!(processor.reg[Register::PSP], 0xEE0, "the frame is aligned to 8 bytes");
+        assert_eq!(processor.mem.read_u32(0xEE0 + 0x1C) & 1 << 9, 1 << 9);
+        assert!(!processor.reg.spsel(), "handlers run on the main stack");
+
+        processor.step();
+        processor.step();
+        assert_eq!(processor.reg[Register::MSP], 0xFF8);
+
+        take(&mut processor, Exception::HardFault);
+        assert_eq!(processor.reg[Register::LR], EXC_RETURN_HANDLER);
+        assert_eq!(processor.reg[Register::MSP], 0xFD8);
+        assert_eq!(processor.scs.nvic.nested_activation(), 2);
+
+        processor.step();
+        processor.step();
+        assert_eq!(processor.reg[Register::PC], 0x54, "bx lr with an EXC_RETURN returns from the exception");
+        assert_eq!(processor.reg[Register::IPSR], 15);
+        assert_eq!(processor.reg[Register::MSP], 0xFF8);
+
+        processor.step();
+        assert_eq!(processor.reg[Register::PC], 0x40);
+        assert_eq!(processor.reg[Register::PSP], 0xF04, "the alignment adjustment is undone");
+        assert_eq!(processor.reg[Register::MSP], 0x1000);
+        assert!(processor.reg.spsel());
+        assert!(!processor.reg.handler_mode());
+        assert_eq!(processor.reg.read(0), 0);
+        assert_eq!(processor.reg.read(2), 0, "the caller saved registers are restored");
+    }
+
+    #[test]
+    fn exception_return_invalid() {
+        let mut processor = processor_with_vector_table();
+
+        take(&mut processor, Exception::SysTick);
+        processor.reg[Register::LR] = 0xFFFF_FFF5;
+
+        for _ in 0..3 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg[Register::PC], 0x60, "an invalid EXC_RETURN raises INVPC, escalated to HardFault");
+        assert_eq!(processor.reg[Register::IPSR], 3);
+        assert!(!processor.scs.nvic.is_active(15));
+    }
+
+    fn processor_with_interrupts() -> Processor {
+        let mut processor = Processor::new();
+        processor.mem = Memory::alloc(0x1000);
+        processor.configure_nvic(8, 4);
+
+        let vectors = [
+            (0, 0x1000), (1, 0x101), (3, 0x261), (4, 0x281), (5, 0x2A1), (6, 0x2C1),
+            (11, 0x2E1), (14, 0x381), (15, 0x241), (16, 0x201), (17, 0x221),
+        ];
+        for &(number, vector) in vectors.iter() {
+            processor.mem.write_u32(4 * number, vector);
+        }
+
+        let code: [(usize, &[u16]); 14] = [
+            (0x100, &[
+                0x4804,         // ldr r0, [pc, #16]
+                0x4A05,         // ldr r2, [pc, #20]
+                0x2103,         // movs r1, #3
+                0x6001,         // str r1, [r0]
+                0x2140,         // movs r1, #64
+                0x7051,         // strb r1, [r2, #1]
+                0x2180,         // movs r1, #128
+                0x7011,         // strb r1, [r2]
+                0xE7FE,         // b .
+                0xBF00,         // nop
+                0xE100, 0xE000, // .word 0xE000E100 (NVIC_ISER0)
+                0xE400, 0xE000, // .word 0xE000E400 (NVIC_IPR0)
+            ]),
+            (0x200, &[0x2401, 0x4770]), // IRQ0: movs r4, #1; bx lr
+            (0x220, &[0x2502, 0x4770]), // IRQ1: movs r5, #2; bx lr
+            (0x240, &[0x2603, 0x4770]), // SysTick: movs r6, #3; bx lr
+            (0x260, &[0xE7FE]),         // HardFault: b .
+            (0x280, &[0xE7FE]),         // MemManage: b .
+            (0x2A0, &[0xE7FE]),         // BusFault: b .
+            (0x2C0, &[0xE7FE]),         // UsageFault: b .
+            (0x2E0, &[0x270B, 0x4770]), // SVCall: movs r7, #11; bx lr
+            (0x300, &[
+                0xDE00,         // udf #0
+                0x6808,         // ldr r0, [r1]
+                0xCA09,         // ldm r2!, {r0, r3}
+                0xFB91, 0xF0F2, // sdiv r0, r1, r2
+                0xEE10, 0x0F10, // mrc p15, #0, r0, c0, c0, #0
+                0x4708,         // bx r1
+                0x6820,         // ldr r0, [r4]
+            ]),
+            (0x320, &[
+                0xDF2A,         // svc #42
+                0xDF01,         // svc #1
+                0xE7FE,         // b .
+            ]),
+            (0x340, &[
+                0xB672,         // cpsid i
+                0xB662,         // cpsie i
+                0xF380, 0x8811, // msr basepri, r0
+                0xF381, 0x8812, // msr basepri_max, r1
+                0xF3EF, 0x8211, // mrs r2, basepri
+                0xB671,         // cpsid f
+                0xF383, 0x8814, // msr control, r3
+                0xF3EF, 0x8414, // mrs r4, control
+                0xF3EF, 0x8503, // mrs r5, xpsr
+                0xE7FE,         // b .
+            ]),
+            (0x380, &[
+                0xF3EF, 0x8009, // PendSV: mrs r0, psp
+                0x3810,         // subs r0, #16
+                0xC0F0,         // stmia r0!, {r4-r7}
+                0x3810,         // subs r0, #16
+                0x4905,         // ldr r1, [pc, #20]
+                0x680A,         // ldr r2, [r1]
+                0x684B,         // ldr r3, [r1, #4]
+                0x6010,         // str r0, [r2]
+                0x600B,         // str r3, [r1]
+                0x604A,         // str r2, [r1, #4]
+                0x6818,         // ldr r0, [r3]
+                0xC8F0,         // ldmia r0!, {r4-r7}
+                0xF380, 0x8809, // msr psp, r0
+                0x4770,         // bx lr
+                0x0800, 0x0000, // .word 0x800 (current and next task control blocks)
+            ]),
+            (0x3C0, &[
+                0x4803,         // ldr r0, [pc, #12]
+                0xF380, 0x8809, // msr psp, r0
+                0x2003,         // movs r0, #3
+                0xF380, 0x8814, // msr control, r0
+                0x3401,         // task A: adds r4, #1
+                0xE7FD,         //         b task A
+                0x0E00, 0x0000, // .word 0xE00 (task A stack)
+                0x3410,         // task B: adds r4, #16
+                0xE7FD,         //         b task B
+            ]),
+        ];
+
+        for &(address, halfwords) in code.iter() {
+            for (i, halfword) in halfwords.iter().enumerate() {
+                processor.mem.write_u16(address + 2 * i, *halfword);
+            }
+        }
+
+        processor.reset();
+        processor
+    }
+
+    #[test]
+    fn interrupt_preemption_and_tail_chaining() {
+        let mut processor = processor_with_interrupts();
+
+        for _ in 0..8 {
+            assert!(processor.step());
+        }
+
+        assert!(processor.nvic().is_enabled(16) && processor.nvic().is_enabled(17));
+        assert!(!processor.nvic().is_enabled(18));
+        assert_eq!(processor.nvic().priority(16), 0x80, "firmware configures the NVIC through its registers");
+        assert_eq!(processor.nvic().priority(17), 0x40);
+        assert_eq!(processor.reg[Register::PC], 0x110);
+
+        processor.set_irq_line(2, true);
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x110, "disabled interrupts are not taken");
+
+        processor.set_irq_line(0, true);
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x200);
+        assert_eq!(processor.reg[Register::IPSR], 16);
+        assert_eq!(processor.reg[Register::MSP], 0xFE0);
+        assert_eq!(processor.mem.read_u32(0xFE0 + 0x18), 0x110, "the interrupted instruction is the return address");
+
+        assert!(processor.step());
+        processor.set_irq_line(1, true);
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x220, "a higher priority interrupt preempts");
+        assert_eq!(processor.reg[Register::LR], EXC_RETURN_HANDLER);
+        assert_eq!(processor.nvic().nested_activation(), 2);
+
+        processor.set_irq_line(1, false);
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x202);
+        assert_eq!(processor.reg[Register::IPSR], 16);
+        assert_eq!(processor.reg.read(5), 2);
+
+        // The line of IRQ0 is still asserted, so it is pending again when its handler returns
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x200, "a pending interrupt is tail-chained");
+        assert_eq!(processor.reg[Register::IPSR], 16);
+        assert_eq!(processor.reg[Register::MSP], 0xFE0, "tail-chaining leaves the frame on the stack");
+        assert_eq!(processor.reg[Register::LR], EXC_RETURN_THREAD_MSP);
+
+        processor.set_irq_line(0, false);
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x110);
+        assert_eq!(processor.reg[Register::IPSR], 0);
+        assert_eq!(processor.reg[Register::MSP], 0x1000);
+        assert_eq!(processor.nvic().nested_activation(), 0);
+    }
+
+    #[test]
+    fn interrupt_priority_grouping_and_late_arrival() {
+        let mut processor = processor_with_interrupts();
+
+        for _ in 0..8 {
+            assert!(processor.step());
+        }
+
+        // With PRIGROUP 7 every priority bit is subpriority, so no interrupt preempts another
+        processor.write_memory(0xE000_ED0C, DataSize::Word, 0x05FA_0700).unwrap();
+        assert_eq!(processor.read_memory(0xE000_ED0C, DataSize::Word).unwrap(), 0xFA05_0700);
+
+        processor.set_irq_line(0, true);
+        processor.set_irq_line(0, false);
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::IPSR], 16);
+
+        processor.set_irq_line(1, true);
+        processor.set_irq_line(1, false);
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x202, "an interrupt of the same group priority does not preempt");
+        assert_eq!(processor.read_memory(0xE000_ED04, DataSize::Word).unwrap() & 0x1F_F1FF, 17 << 12 | 16);
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x220, "the pending interrupt is tail-chained");
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x110);
+
+        // SysTick is taken with IRQ1 pending, which has the higher priority when the vector is fetched
+        processor.write_memory(0xE000_ED23, DataSize::Byte, 0xF0).unwrap();
+        processor.write_memory(0xE000_E200, DataSize::Word, 1 << 1).unwrap();
+        take(&mut processor, Exception::SysTick);
+        assert_eq!(processor.reg[Register::PC], 0x220, "a late arriving exception is taken first");
+        assert!(processor.nvic().is_pending(Exception::SysTick.number()));
+
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x240);
+        assert_eq!(processor.reg[Register::IPSR], 15);
+    }
+
+    #[test]
+    fn system_reset_request() {
+        let mut processor = processor_with_interrupts();
+
+        for _ in 0..8 {
+            assert!(processor.step());
+        }
+
+        let code = [
+            0x4802,         // ldr r0, [pc, #8]
+            0x4903,         // ldr r1, [pc, #12]
+            0x6001,         // str r1, [r0]
+            0xE7FE,         // b .
+            0xBF00, 0xBF00,
+            0xED0C, 0xE000, // .word 0xE000ED0C (AIRCR)
+            0x0004, 0x05FA, // .word 0x05FA0004 (VECTKEY | SYSRESETREQ)
+        ];
+        for (i, &halfword) in code.iter().enumerate() {
+            processor.mem.write_u16(0x400 + 2 * i, halfword);
+        }
+
+        processor.reg[Register::PC] = 0x400;
+        processor.reg[Register::MSP] = 0x800;
+        assert!(processor.nvic().is_enabled(16));
+
+        assert!(processor.step());
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x100, "the reset handler is entered from the vector table");
+        assert_eq!(processor.reg[Register::MSP], 0x1000);
+        assert_eq!(processor.reg.read(0), 0);
+        assert!(!processor.nvic().is_enabled(16), "the NVIC takes its reset state");
+    }
+
+    #[test]
+    fn systick_exception() {
+        let mut processor = processor_with_interrupts();
+
+        for _ in 0..8 {
+            assert!(processor.step());
+        }
+
+        processor.write_memory(0xE000_E014, DataSize::Word, 19).unwrap();
+        processor.write_memory(0xE000_E018, DataSize::Word, 0).unwrap();
+        processor.write_memory(0xE000_E010, DataSize::Word, 0b111).unwrap();
+
+        // b . takes two cycles, one for the branch and one to refill the pipeline
+        let mut trace = Vec::new();
+        for _ in 0..10 {
+            assert!(processor.step());
+            trace.push(processor.read_memory(0xE000_E018, DataSize::Word).unwrap());
+        }
+
+        assert_eq!(trace, [18, 16, 14, 12, 10, 8, 6, 4, 2, 0]);
+        assert_eq!(processor.read_memory(0xE000_E010, DataSize::Word).unwrap(), 1 << 16 | 0b111);
+        assert_eq!(processor.read_memory(0xE000_E010, DataSize::Word).unwrap(), 0b111, "reading SYST_CSR clears COUNTFLAG");
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x240, "SysTick is pended when the counter reaches zero");
+        assert_eq!(processor.reg[Register::IPSR], 15);
+        assert_eq!(processor.read_memory(0xE000_E018, DataSize::Word).unwrap(), 8, "exception entry takes 12 cycles");
+
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x110);
+        assert_eq!(processor.reg.read(6), 3);
+        assert_eq!(processor.read_memory(0xE000_E018, DataSize::Word).unwrap(), 5);
+
+        // ldmia r0!, {r4-r7} takes a cycle per register after the first
+        processor.reg.write(0, 0x800);
+        processor.reg[Register::PC] = 0x398;
+        assert!(processor.step());
+        assert_eq!(processor.read_memory(0xE000_E018, DataSize::Word).unwrap(), 0);
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x240, "the timer fires every RELOAD + 1 cycles");
+    }
+
+    /// Executes the instruction at `pc` with the given SHCSR and CCR, returning the processor once it has faulted
+    fn fault_at(pc: u32, shcsr: u32, ccr: u32, registers: &[(u8, u32)]) -> Processor {
+        let mut processor = processor_with_interrupts();
+        processor.write_memory(0xE000_ED24, DataSize::Word, shcsr).unwrap();
+        processor.write_memory(0xE000_ED14, DataSize::Word, ccr).unwrap();
+        processor.reg[Register::PC] = pc;
+
+        for &(n, value) in registers.iter() {
+            processor.reg.write(n, value);
+        }
+
+        processor.step();
+        processor
+    }
+
+    #[test]
+    fn fault_exceptions() {
+        const MEMFAULTENA: u32 = 1 << 16;
+        const BUSFAULTENA: u32 = 1 << 17;
+        const USGFAULTENA: u32 = 1 << 18;
+        const ALL: u32 = MEMFAULTENA | BUSFAULTENA | USGFAULTENA;
+
+        let status = |processor: &mut Processor| {
+            let cfsr = processor.read_memory(0xE000_ED28, DataSize::Word).unwrap();
+            let hfsr = processor.read_memory(0xE000_ED2C, DataSize::Word).unwrap();
+            (processor.reg[Register::PC], cfsr, hfsr)
+        };
+
+        let mut processor = fault_at(0x300, 0, 0, &[]);
+        assert_eq!(status(&mut processor), (0x260, 1 << 16, 1 << 30), "a disabled UsageFault escalates to HardFault");
+        assert_eq!(processor.mem.read_u32(0xFE0 + 0x18), 0x300, "the faulting instruction is the return address");
+
+        let mut processor = fault_at(0x300, USGFAULTENA, 0, &[]);
+        assert_eq!(status(&mut processor), (0x2C0, 1 << 16, 0), "UNDEFINSTR");
+        assert_eq!(processor.read_memory(0xE000_ED24, DataSize::Word).unwrap(), USGFAULTENA | 1 << 3, "UsageFault is active");
+
+        let mut processor = fault_at(0x302, ALL, 0, &[(1, 0x4000_0001)]);
+        assert_eq!(status(&mut processor), (0x2A0, 1 << 9 | 1 << 15, 0), "PRECISERR with BFARVALID");
+        assert_eq!(processor.read_memory(0xE000_ED38, DataSize::Word).unwrap(), 0x4000_0001);
+        processor.write_memory(0xE000_ED29, DataSize::Byte, 0xFF).unwrap();
+        assert_eq!(status(&mut processor).1, 0, "the fault status bits are cleared by writing 1");
+
+        let mut processor = fault_at(0x304, ALL, 0, &[(2, 0x41)]);
+        assert_eq!(status(&mut processor), (0x2C0, 1 << 24, 0), "UNALIGNED, load multiple requires word alignment");
+        assert_eq!(processor.reg.read(2), 0x41);
+
+        let processor = fault_at(0x306, ALL, 0, &[(1, 7)]);
+        assert_eq!((processor.reg[Register::PC], processor.reg.read(0)), (0x30A, 0), "division by zero gives zero");
+
+        let mut processor = fault_at(0x306, ALL, 1 << 4, &[(1, 7)]);
+        assert_eq!(status(&mut processor), (0x2C0, 1 << 25, 0), "DIVBYZERO with CCR.DIV_0_TRP");
+
+        let processor = fault_at(0x306, ALL, 0, &[(1, (-7i32) as u32), (2, 2)]);
+        assert_eq!(processor.reg.read(0), (-3i32) as u32, "signed division rounds towards zero");
+
+        let mut processor = fault_at(0x30A, ALL, 0, &[]);
+        assert_eq!(status(&mut processor), (0x2C0, 1 << 19, 0), "NOCP");
+
+        let mut processor = fault_at(0x30E, ALL, 0, &[(1, 0xE000_0001)]);
+        assert!(processor.step());
+        assert_eq!(status(&mut processor), (0x280, 1 << 0, 0), "IACCVIOL, the System region is Execute Never");
+        assert_eq!(processor.mem.read_u32(0xFE0 + 0x18), 0xE000_0000);
+
+        let mut processor = fault_at(0x30E, ALL, 0, &[(1, 0x2000_0001)]);
+        assert!(processor.step());
+        assert_eq!(status(&mut processor), (0x2A0, 1 << 8, 0), "IBUSERR");
+
+        let processor = fault_at(0x310, ALL, 0, &[(4, 0x42)]);
+        assert_eq!(processor.reg[Register::PC], 0x312, "unaligned word accesses are permitted by default");
+
+        let mut processor = fault_at(0x310, ALL, 1 << 3, &[(4, 0x42)]);
+        assert_eq!(status(&mut processor), (0x2C0, 1 << 24, 0), "UNALIGNED with CCR.UNALIGN_TRP");
+
+        // UsageFault cannot preempt itself, so a fault within its handler escalates
+        let mut processor = fault_at(0x300, ALL, 0, &[]);
+        processor.reg[Register::PC] = 0x300;
+        assert!(processor.step());
+        assert_eq!(status(&mut processor), (0x260, 1 << 16, 1 << 30));
+        assert_eq!(processor.reg[Register::IPSR], 3);
+
+        processor.reg[Register::PC] = 0x300;
+        assert!(!processor.step(), "a fault within the HardFault handler locks up the processor");
+        assert!(processor.locked_up);
+    }
+
+    #[test]
+    fn fault_within_it_block() {
+        let mut processor = processor_with_interrupts();
+        processor.reg[Register::MSP] = 0x800;
+        processor.reg.set_flags(ConditionFlags { n: false, z: true, c: false, v: false });
+        processor.reg.write(1, 0x2000_0000);
+
+        // it eq; ldreq r0, [r1], and it eq; ldreq.w r0, [r1] with its second halfword beyond the end of memory
+        for &(address, halfword) in [(0x400, 0xBF08), (0x402, 0x6808), (0xFFC, 0xBF08), (0xFFE, 0xF8D1)].iter() {
+            processor.mem.write_u16(address, halfword);
+        }
+
+        for &(pc, stacked_pc) in [(0x400, 0x402), (0xFFC, 0xFFE)].iter() {
+            processor.reg[Register::PC] = pc;
+            assert!(processor.step());
+
+            let itstate = processor.reg.itstate();
+            assert!(itstate.position().in_it_block());
+            assert!(processor.step());
+            assert_eq!(processor.reg[Register::PC], 0x260);
+
+            let frame = processor.reg[Register::MSP];
+            let xpsr = processor.mem.read_u32(frame as usize + 0x1C);
+            assert_eq!(processor.mem.read_u32(frame as usize + 0x18), stacked_pc);
+            assert_eq!(ItState::from_epsr(xpsr), itstate, "the faulting instruction is stacked with its ITSTATE");
+            assert_eq!(xpsr, 0x4100_0800);
+
+            processor.reg[Register::IPSR] = 0;
+            processor.scs.nvic.deactivate(Exception::HardFault.number());
+            processor.reg[Register::MSP] = 0x800;
+        }
+    }
+
+    #[test]
+    fn unimplemented_instructions_stop() {
+        let mut processor = processor_with_interrupts();
+
+        // isb sy; nop; add.w r0, r1, r2
+        for (i, &halfword) in [0xF3BF, 0x8F6F, 0xBF00, 0xEB01, 0x0002].iter().enumerate() {
+            processor.mem.write_u16(0x400 + 2 * i, halfword);
+        }
+
+        processor.reg[Register::PC] = 0x400;
+        processor.reg.write(1, 1);
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x406, "barriers and hints execute as no operation");
+
+        assert!(!processor.step(), "an unimplemented instruction stops execution");
+        assert_eq!(processor.reg[Register::PC], 0x406);
+        assert_eq!(processor.reg.read(0), 0);
+        assert_eq!(processor.reg[Register::IPSR], 0, "no fault is raised for a defined instruction");
+        assert_eq!(processor.read_memory(0xE000_ED28, DataSize::Word).unwrap(), 0);
+    }
+
+    #[test]
+    fn fault_on_exception_entry() {
+        let mut processor = processor_with_interrupts();
+        processor.configure_nvic(64, 4);
+        processor.reset();
+
+        // The vector table is moved so that it ends at the top of memory, and the vector of IRQ40 cannot be read
+        for number in 0..32 {
+            let vector = processor.mem.read_u32(4 * number);
+            processor.mem.write_u32(0xF80 + 4 * number, vector);
+        }
+
+        processor.write_memory(0xE000_ED08, DataSize::Word, 0xF80).unwrap();
+        processor.write_memory(0xE000_E104, DataSize::Word, 1 << 8).unwrap();
+        processor.write_memory(0xE000_E204, DataSize::Word, 1 << 8).unwrap();
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x260, "a vector which cannot be read raises HardFault");
+        assert_eq!(processor.read_memory(0xE000_ED2C, DataSize::Word).unwrap(), 1 << 1, "VECTTBL");
+
+        // A failed push pends a BusFault, which escalates to HardFault
+        let mut processor = processor_with_interrupts();
+        processor.reg[Register::MSP] = 0x1010;
+        processor.set_irq_line(0, true);
+        processor.write_memory(0xE000_E100, DataSize::Word, 1).unwrap();
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x260, "the derived HardFault is taken in place of the interrupt");
+        assert_eq!(processor.read_memory(0xE000_ED28, DataSize::Word).unwrap(), 1 << 12, "STKERR");
+        assert!(processor.nvic().is_pending(16));
+    }
+
+    #[test]
+    fn supervisor_calls() {
+        let mut processor = processor_with_interrupts();
+        processor.set_svc_handler(1, |args, _| args[0] + args[1]);
+        processor.reg[Register::PC] = 0x320;
+        processor.reg.write(0, 5);
+        processor.reg.write(1, 6);
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x2E0, "svc takes the SVCall exception");
+        assert_eq!(processor.reg[Register::IPSR], 11);
+
+        let stacked_pc = processor.mem.read_u32(0xFE0 + 0x18);
+        assert_eq!(stacked_pc, 0x322, "the return address is the following instruction");
+        assert_eq!(processor.mem.read_u16(stacked_pc as usize - 2) & 0xFF, 42, "the handler finds the SVC number");
+
+        // An SVC within the SVCall handler cannot preempt it, and escalates
+        processor.reg[Register::PC] = 0x320;
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x260);
+        assert_eq!(processor.read_memory(0xE000_ED2C, DataSize::Word).unwrap(), 1 << 30, "FORCED");
+
+        let mut processor = processor_with_interrupts();
+        processor.set_svc_handler(1, |args, _| args[0] + args[1]);
+        processor.reg[Register::PC] = 0x322;
+        processor.reg.write(0, 5);
+        processor.reg.write(1, 6);
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x324, "a host side handler services the call in place");
+        assert_eq!(processor.reg.read(0), 11);
+        assert_eq!(processor.reg[Register::IPSR], 0);
+
+        processor.set_svc_handler(42, |args, mem| {
+            mem.write_u32(args[0] as usize, args[1]);
+            0
+        });
+        processor.reg.write(0, 0x800);
+        processor.reg[Register::PC] = 0x320;
+        assert!(processor.step());
+        assert_eq!(processor.mem.read_u32(0x800), 6, "host side handlers have access to memory");
+        assert_eq!(processor.reg.read(0), 0);
+
+        assert!(processor.remove_svc_handler(1).is_some());
+        processor.reg[Register::PC] = 0x322;
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x2E0, "a removed handler returns the number to SVCall");
+    }
+
+    #[test]
+    fn exception_mask_registers() {
+        let mut processor = processor_with_interrupts();
+
+        for _ in 0..8 {
+            assert!(processor.step());
+        }
+
+        processor.reg[Register::PC] = 0x340;
+        processor.reg.write(0, 0x85);
+        processor.reg.write(1, 0x40);
+        processor.reg.write(3, 0b11);
+        processor.reg[Register::PSP] = 0xE00;
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PRIMASK], 1);
+        processor.write_memory(0xE000_E200, DataSize::Word, 1 << 1).unwrap();
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x344, "PRIMASK masks every configurable priority exception");
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x220, "the pending interrupt is taken once cpsie i executes");
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x344);
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::BASEPRI], 0x80, "BASEPRI holds only the implemented priority bits");
+        processor.write_memory(0xE000_E200, DataSize::Word, 1 << 0).unwrap();
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::BASEPRI], 0x40, "BASEPRI_MAX raises the priority");
+        assert!(processor.step());
+        assert_eq!(processor.reg.read(2), 0x40);
+        assert_eq!(processor.reg[Register::PC], 0x350, "BASEPRI masks exceptions of the same or lower priority");
+        assert!(processor.nvic().is_pending(16));
+
+        processor.write_special_register(18, 0b10, 0x80);
+        assert_eq!(processor.reg[Register::BASEPRI], 0x40, "BASEPRI_MAX cannot lower the priority");
+
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::FAULTMASK], 1);
+        assert_eq!(processor.execution_priority(), -1);
+
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg.read(4), 0b11);
+        assert!(!processor.reg.privileged());
+        assert!(processor.reg.process_stack(), "writing CONTROL.SPSEL in Thread mode switches to the process stack");
+
+        processor.reg.set_flags(ConditionFlags { n: true, z: false, c: true, v: false });
+        assert!(processor.step());
+        assert_eq!(processor.reg.read(5), 0xA000_0000, "the EPSR reads as zero");
+
+        // Unprivileged execution can neither read nor write the masks
+        processor.reg[Register::PC] = 0x340;
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PRIMASK], 0, "cps is ignored when unprivileged");
+        processor.reg[Register::PC] = 0x344;
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::BASEPRI], 0x40);
+        processor.reg[Register::PC] = 0x34C;
+        assert!(processor.step());
+        assert_eq!(processor.reg.read(2), 0);
+
+        // FAULTMASK is cleared by the exception return, after which BASEPRI still masks IRQ0
+        take(&mut processor, Exception::SysTick);
+        assert!(processor.reg.privileged(), "Handler mode is always privileged");
+        assert_eq!(processor.reg[Register::LR], EXC_RETURN_THREAD_PSP);
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::FAULTMASK], 0);
+        assert_eq!(processor.execution_priority(), 0x40);
+        assert!(processor.nvic().is_pending(16));
+
+        processor.write_special_register(17, 0b10, 0x20);
+        assert_eq!(processor.reg[Register::BASEPRI], 0x40, "unprivileged writes are ignored");
+
+        // An SVC with PRIMASK set cannot be taken and escalates
+        let mut processor = processor_with_interrupts();
+        processor.reg[Register::PRIMASK] = 1;
+        processor.reg[Register::PC] = 0x320;
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x260);
+
+        // A fault with FAULTMASK set locks up the processor
+        let mut processor = processor_with_interrupts();
+        processor.reg[Register::FAULTMASK] = 1;
+        processor.reg[Register::PC] = 0x300;
+        assert!(!processor.step());
+    }
+
+    #[test]
+    fn context_switching_on_the_process_stack() {
+        let mut processor = processor_with_interrupts();
+
+        // Task B has yet to run, its stack holds the context PendSV restores with r4 = 0x100
+        processor.mem.write_u32(0x800, 0x810);
+        processor.mem.write_u32(0x804, 0x814);
+        processor.mem.write_u32(0x814, 0xCD0);
+        processor.mem.write_u32(0xCD0, 0x100);
+        processor.mem.write_u32(0xCE0 + 0x18, 0x3D4);
+        processor.mem.write_u32(0xCE0 + 0x1C, 1 << 24);
+
+        processor.reg[Register::PC] = 0x3C0;
+        for _ in 0..4 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.mode(), Mode::Thread);
+        assert!(!processor.privileged());
+        assert_eq!(processor.reg[Register::SP], 0xE00, "CONTROL.SPSEL selects the process stack in Thread mode");
+        assert_eq!(processor.read_memory(0xE000_ED04, DataSize::Word), Err(Fault::PreciseErr { address: 0xE000_ED04 }));
+
+        for _ in 0..3 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg.read(4), 2);
+
+        processor.scs.nvic.set_pending(Exception::PendSv.number());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 0x380);
+        assert_eq!(processor.mode(), Mode::Handler);
+        assert!(processor.privileged());
+        assert_eq!(processor.reg[Register::LR], EXC_RETURN_THREAD_PSP);
+        assert_eq!(processor.reg[Register::PSP], 0xDE0, "the frame is pushed to the process stack");
+        assert_eq!(processor.reg[Register::SP], 0x1000, "handlers run on the main stack");
+
+        for _ in 0..14 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg[Register::PC], 0x3D4, "the exception returns to task B");
+        assert_eq!(processor.mode(), Mode::Thread);
+        assert!(!processor.privileged(), "CONTROL.nPRIV is kept across the exception");
+        assert_eq!(processor.reg[Register::PSP], 0xD00);
+        assert_eq!(processor.reg.read(4), 0x100);
+        assert_eq!(processor.mem.read_u32(0x810), 0xDD0, "the context of task A is saved");
+
+        assert!(processor.step());
+        assert_eq!(processor.reg.read(4), 0x110);
+
+        processor.scs.nvic.set_pending(Exception::PendSv.number());
+        for _ in 0..15 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg[Register::PC], 0x3CE, "the exception returns to task A");
+        assert_eq!(processor.reg[Register::PSP], 0xE00);
+        assert_eq!(processor.reg[Register::MSP], 0x1000);
+        assert_eq!(processor.reg.read(4), 2);
+        assert_eq!(processor.mem.read_u32(0x814), 0xCD0, "the context of task B is saved");
+
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg.read(4), 3);
+    }
+
+    #[test]
+    fn execute_data_processing_program() {
+        let mut processor = processor_with_program(&[
+            0x2005, // movs r0, #5
+            0x2107, // movs r1, #7
+            0x1A42, // subs r2, r0, r1
+            0x4252, // rsbs r2, r2
+            0x4350, // muls r0, r2, r0
+            0x280A, // cmp r0, #10
+            0x0843, // lsrs r3, r0, #1
+            0x41DB, // rors r3, r3
+        ]);
+
+        for _ in 0..3 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg.read(2), (-2i32) as u32);
+        assert_eq!(processor.reg.flags(), ConditionFlags { n: true, z: false, c: false, v: false });
+
+        for _ in 0..3 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg.read(0), 10);
+        assert_eq!(processor.reg.flags(), ConditionFlags { n: false, z: true, c: true, v: false });
+
+        for _ in 0..2 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg.read(3), 5u32.rotate_right(5));
+        assert_eq!(processor.reg.flags(), ConditionFlags { n: false, z: false, c: false, v: false });
+        assert_eq!(processor.reg[Register::PC], 16);
+        assert_eq!(processor.reg.read(15), 20);
+    }
+
+    #[test]
+    fn execute_conditional_branches() {
+        let mut processor = processor_with_program(&[
+            0x2003, // movs r0, #3
+            0x3801, // subs r0, #1
+            0xD1FD, // bne .-2
+            0xD3FF, // bcc .+2 (not taken, subs sets the carry when there is no borrow)
+            0xD200, // bcs .+4
+            0xBF00, // nop
+            0xE7FE, // b .
+        ]);
+
+        let mut trace = Vec::new();
+        for _ in 0..12 {
+            trace.push(processor.reg[Register::PC]);
+            assert!(processor.step());
+        }
+
+        assert_eq!(trace, [0, 2, 4, 2, 4, 2, 4, 6, 8, 12, 12, 12]);
+        assert_eq!(processor.reg.read(0), 0);
+    }
+
+    #[test]
+    fn execute_it_blocks() {
+        let mut processor = processor_with_program(&[
+            0x2000,         // movs r0, #0
+            0x2800,         // cmp r0, #0
+            0xBF0C,         // ite eq
+            0x2101,         // moveq r1, #1
+            0x2102,         // movne r1, #2
+            0xBF1C,         // itt ne
+            0xF103, 0x0301, // addne.w r3, r3, #1
+            0x2103,         // movne r1, #3
+            0x1C0A,         // adds r2, r1, #0
+            0xBF08,         // it eq
+            0xE7FE,         // beq .
+            0xE7FE,         // b .
+        ]);
+
+        processor.step();
+        processor.step();
+        processor.step();
+        assert_eq!(processor.reg[Register::EPSR], 1 << 24 | 0b000011 << 10, "ITSTATE is held in the EPSR");
+
+        processor.step();
+        assert_eq!(processor.reg.read(1), 1);
+        assert!(processor.reg.flags().z, "16 bit instructions within an IT block do not set flags");
+
+        let mut trace = vec![processor.reg[Register::PC]];
+        for _ in 0..8 {
+            assert!(processor.step());
+            trace.push(processor.reg[Register::PC]);
+        }
+
+        assert_eq!(trace, [8, 10, 12, 16, 18, 20, 22, 24, 24]);
+        assert_eq!(processor.reg.read(1), 1);
+        assert_eq!(processor.reg.read(2), 1);
+        assert_eq!(processor.reg.itstate(), ItState::default());
+    }
+
+    #[test]
+    fn execute_load_store_single() {
+        let mut processor = processor_with_program(&[
+            0x4804,         // ldr r0, [pc, #16]
+            0x2140,         // movs r1, #64
+            0x6048,         // str r0, [r1, #4]
+            0x808A,         // strh r2, [r1, #4]
+            0x2206,         // movs r2, #6
+            0x568B,         // ldrsb r3, [r1, r2]
+            0x9301,         // str r3, [sp, #4]
+            0x9C01,         // ldr r4, [sp, #4]
+            0x7B4D,         // ldrb r5, [r1, #13]
+            0xE7FE,         // b .
+            0xBEEF, 0xDEAD, // .word 0xDEADBEEF
+        ]);
+
+        processor.reg[Register::SP] = 0x80;
+        processor.reg[Register::R2] = 0x1234;
+
+        for _ in 0..9 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg.read(0), 0xDEAD_BEEF, "literal loads read from Align(PC, 4) + imm");
+        assert_eq!(processor.mem.read_u32(0x44), 0xDEAD_1234);
+        assert_eq!(processor.reg.read(3), 0xFFFF_FFAD);
+        assert_eq!(processor.mem.read_u32(0x84), 0xFFFF_FFAD);
+        assert_eq!(processor.reg.read(4), 0xFFFF_FFAD);
+        assert_eq!(processor.reg.read(5), 0, "ldrb zero extends");
+    }
+
+    #[test]
+    fn execute_load_store_multiple() {
+        let mut processor = processor_with_program(&[
+            0x2040, // movs r0, #64
+            0x2101, // movs r1, #1
+            0x2202, // movs r2, #2
+            0xC006, // stm r0!, {r1, r2}
+            0x3808, // subs r0, #8
+            0xC809, // ldm r0, {r0, r3}
+            0xB506, // push {r1, r2, lr}
+            0xBD30, // pop {r4, r5, pc}
+            0xE7FE, // b .
+            0xE7FE, // b .
+        ]);
+
+        processor.reg[Register::SP] = 0x100;
+        processor.reg[Register::LR] = 18 | 1;
+
+        for _ in 0..7 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.mem.read_u32(0x40), 1);
+        assert_eq!(processor.mem.read_u32(0x44), 2);
+        assert_eq!(processor.reg.read(0), 1, "the loaded base register is not written back");
+        assert_eq!(processor.reg.read(3), 2);
+        assert_eq!(processor.reg.read(13), 0xF4);
+        assert_eq!(processor.mem.read_u32(0xFC), 18 | 1);
+
+        assert!(processor.step());
+        assert_eq!(processor.reg.read(4), 1);
+        assert_eq!(processor.reg.read(5), 2);
+        assert_eq!(processor.reg.read(13), 0x100);
+        assert_eq!(processor.reg[Register::PC], 18, "popping the PC is an interworking branch");
+        assert!(processor.reg.thumb());
+    }
+
+    #[test]
+    fn execute_prologue_and_epilogue() {
+        let mut program = vec![
+            0x202A,         // movs r0, #42
+            0xF000, 0xF801, // bl func
+            0xE7FE,         // b .
+            0xB580,         // func: push {r7, lr}
+            0xB082,         //       sub sp, #8
+            0xAF00,         //       add r7, sp, #0
+            0x6078,         //       str r0, [r7, #4]
+            0xA204,         //       adr r2, table
+            0x6811,         //       ldr r1, [r2]
+            0x4688,         //       mov r8, r1
+            0x44B8,         //       add r8, r7
+            0x687B,         //       ldr r3, [r7, #4]
+            0x446B,         //       add r3, sp, r3
+            0x4640,         //       mov r0, r8
+            0x46BD,         //       mov sp, r7
+            0xB002,         //       add sp, #8
+            0xBD80,         //       pop {r7, pc}
+            0x1000, 0x0000, // table: .word 0x1000
+            0x3001,         // leaf: adds r0, #1
+            0x46F7,         //       mov pc, lr
+        ];
+        program.resize(0x80, 0);
+
+        let mut processor = processor_with_program(&program);
+        processor.reg[Register::SP] = 0x100;
+        processor.reg.write(7, 0x77);
+
+        for _ in 0..6 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg[Register::SP], 0xF0);
+        assert_eq!(processor.reg.read(7), 0xF0, "the frame pointer is set up from the SP");
+        assert_eq!(processor.mem.read_u32(0xF4), 42);
+
+        for _ in 0..10 {
+            assert!(processor.step());
+        }
+
+        assert_eq!(processor.reg[Register::PC], 6, "the epilogue returns to the caller");
+        assert_eq!(processor.reg[Register::SP], 0x100, "the stack is balanced");
+        assert_eq!(processor.reg.read(7), 0x77);
+        assert_eq!(processor.reg.read(0), 0x10F0);
+        assert_eq!(processor.reg.read(3), 0xF0 + 42);
+
+        processor.reg[Register::PC] = 0x28;
+        processor.reg[Register::LR] = 7;
+        assert!(processor.step());
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::PC], 6, "mov pc, lr branches");
+        assert_eq!(processor.reg.read(0), 0x10F1);
+    }
+
+    #[test]
+    fn execute_calls_and_returns() {
+        let mut processor = processor_with_program(&[
+            0xF000, 0xF804, // bl .+12
+            0xB110,         // cbz r0, .+8
+            0xB900,         // cbnz r0, .+4
+            0xBF00,         // nop
+            0x4790,         // blx r2
+            0x2011,         // movs r0, #17
+            0x4770,         // bx lr
+            0xBF00,         // nop
+        ]);
+
+        processor.reg[Register::R2] = 16;
+        processor.reg[Register::SP] = 0x800;
+
+        let mut trace = Vec::new();
+        for _ in 0..6 {
+            assert!(processor.step());
+            trace.push((processor.reg[Register::PC], processor.reg[Register::LR]));
+        }
+
+        assert_eq!(trace, [(12, 5), (14, 5), (4, 5), (6, 5), (10, 5), (16, 13)]);
+        assert!(!processor.reg.thumb(), "blx to an even address clears the Thumb bit");
+        assert!(processor.step());
+        assert_eq!(processor.reg[Register::IPSR], 3, "executing with EPSR.T clear raises INVSTATE, escalated to HardFault");
+        assert_eq!(processor.mem.read_u32(0x800 - 0x20 + 0x18), 16, "the faulting instruction is the return address");
     }
 }
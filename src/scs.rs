@@ -0,0 +1,376 @@
+// ARMv7-M System Control Space
+
+use crate::exception::{ Exception, Fault };
+use crate::instructions::DataSize;
+use crate::nvic::Nvic;
+use crate::systick::SysTick;
+
+/// The address range of the System Control Space, the memory mapped registers of the core
+pub const SCS_BASE: u32 = 0xE000_E000;
+pub const SCS_END: u32 = 0xE000_EFFF;
+
+const ICSR: u32 = 0xD04;
+const VTOR: u32 = 0xD08;
+const AIRCR: u32 = 0xD0C;
+const CCR: u32 = 0xD14;
+const SHCSR: u32 = 0xD24;
+const CFSR: u32 = 0xD28;
+const HFSR: u32 = 0xD2C;
+const MMFAR: u32 = 0xD34;
+const BFAR: u32 = 0xD38;
+
+const ICSR_NMIPENDSET: u32 = 1 << 31;
+const ICSR_PENDSVSET: u32 = 1 << 28;
+const ICSR_PENDSVCLR: u32 = 1 << 27;
+const ICSR_PENDSTSET: u32 = 1 << 26;
+const ICSR_PENDSTCLR: u32 = 1 << 25;
+const ICSR_ISRPENDING: u32 = 1 << 22;
+const ICSR_RETTOBASE: u32 = 1 << 11;
+
+const VTOR_TBLOFF_MASK: u32 = 0x3FFF_FF80;
+const AIRCR_VECTKEY: u32 = 0x05FA;
+const AIRCR_VECTKEYSTAT: u32 = 0xFA05;
+const AIRCR_SYSRESETREQ: u32 = 1 << 2;
+
+const CCR_UNALIGN_TRP: u32 = 1 << 3;
+const CCR_DIV_0_TRP: u32 = 1 << 4;
+const CCR_STKALIGN: u32 = 1 << 9;
+
+/// The SHCSR bits holding the active, pending and enabled state of the system exceptions
+const SHCSR_ACTIVE: [(u32, Exception); 7] = [
+    (0, Exception::MemManage), (1, Exception::BusFault), (3, Exception::UsageFault), (7, Exception::SvCall),
+    (8, Exception::DebugMonitor), (10, Exception::PendSv), (11, Exception::SysTick),
+];
+const SHCSR_PENDED: [(u32, Exception); 4] = [
+    (12, Exception::UsageFault), (13, Exception::MemManage), (14, Exception::BusFault), (15, Exception::SvCall),
+];
+const SHCSR_ENABLED: [(u32, Exception); 3] = [
+    (16, Exception::MemManage), (17, Exception::BusFault), (18, Exception::UsageFault),
+];
+
+/// BFSR.BFARVALID, where CFSR holds the MMFSR in byte 0, the BFSR in byte 1 and the UFSR in the upper halfword
+const BFSR_BFARVALID: u32 = 1 << 15;
+
+const HFSR_VECTTBL: u32 = 1 << 1;
+const HFSR_FORCED: u32 = 1 << 30;
+
+/// System Control Space
+///
+/// SCS REGISTERS (B3.2.2 pg. 652)
+///
+/// | Address                    | Name          | Description                                                    |
+/// | [0xE000E010 -> 0xE000E01C] | SYST          | SysTick timer, see `SysTick`                                   |
+/// | [0xE000E100 -> 0xE000E5EC] | NVIC          | Nested Vectored Interrupt Controller, see `Nvic`               |
+/// | [0xE000ED04]               | ICSR          | Interrupt Control and State, pends NMI, PendSV and SysTick     |
+/// | [0xE000ED08]               | VTOR          | Vector Table Offset                                            |
+/// | [0xE000ED0C]               | AIRCR         | Application Interrupt and Reset Control, holds PRIGROUP and    |
+/// |                            |               | requests a system reset through SYSRESETREQ                    |
+/// | [0xE000ED14]               | CCR           | Configuration and Control, enables the UNALIGNED and DIVBYZERO |
+/// |                            |               | traps                                                          |
+/// | [0xE000ED18 -> 0xE000ED23] | SHPR1-3       | System Handler Priority, one byte per system exception         |
+/// | [0xE000ED24]               | SHCSR         | System Handler Control and State, enables the fault exceptions |
+/// | [0xE000ED28]               | CFSR          | Configurable Fault Status, the MMFSR, BFSR and UFSR            |
+/// | [0xE000ED2C]               | HFSR          | HardFault Status                                               |
+/// | [0xE000ED34]               | MMFAR         | MemManage Fault Address, valid when MMFSR.MMARVALID is set     |
+/// | [0xE000ED38]               | BFAR          | BusFault Address, valid when BFSR.BFARVALID is set             |
+///
+/// Addresses are given as offsets from the base of the SCS. The priority registers are byte accessible, the other
+/// registers take sub-word writes as a write of the containing word with the remaining bytes zero. The fault status
+/// registers are cleared by writing 1 to their bits. Writes to AIRCR are ignored without the VECTKEY, and its debug
+/// only VECTRESET and VECTCLRACTIVE bits are ignored.
+pub struct Scs {
+    pub nvic: Nvic,
+    pub systick: SysTick,
+    vtor: u32,
+    ccr: u32,
+    cfsr: u32,
+    hfsr: u32,
+    mmfar: u32,
+    bfar: u32,
+    reset_requested: bool,
+}
+
+impl Scs {
+    pub fn new(nvic: Nvic) -> Scs {
+        Scs {
+            nvic,
+            systick: SysTick::new(),
+            vtor: 0,
+            ccr: 0,
+            cfsr: 0,
+            hfsr: 0,
+            mmfar: 0,
+            bfar: 0,
+            reset_requested: false,
+        }
+    }
+
+    /// Whether an address falls within the System Control Space
+    pub fn contains(address: u32) -> bool {
+        (SCS_BASE..=SCS_END).contains(&address)
+    }
+
+    pub fn reset(&mut self) {
+        let nvic = std::mem::take(&mut self.nvic);
+        *self = Scs::new(nvic);
+        self.nvic.reset();
+    }
+
+    /// Whether a system reset has been requested through AIRCR.SYSRESETREQ since the last call, clearing the request
+    pub fn take_reset_request(&mut self) -> bool {
+        std::mem::take(&mut self.reset_requested)
+    }
+
+    /// Advances the timers by a number of clock cycles
+    pub fn tick(&mut self, cycles: u32) {
+        if self.systick.tick(cycles) {
+            self.nvic.set_pending(Exception::SysTick.number());
+        }
+    }
+
+    /// The address of the vector table
+    pub fn vtor(&self) -> u32 {
+        self.vtor
+    }
+
+    /// Whether unaligned halfword and word accesses raise an UNALIGNED UsageFault, CCR.UNALIGN_TRP
+    pub fn unalign_trp(&self) -> bool {
+        self.ccr & CCR_UNALIGN_TRP != 0
+    }
+
+    /// Whether integer division by zero raises a DIVBYZERO UsageFault, CCR.DIV_0_TRP
+    pub fn div_0_trp(&self) -> bool {
+        self.ccr & CCR_DIV_0_TRP != 0
+    }
+
+    /// Records a fault in the fault status and address registers
+    pub fn record_fault(&mut self, fault: Fault) {
+        let status = match fault {
+            Fault::IAccViol => 1 << 0,
+            Fault::IBusErr => 1 << 8,
+            Fault::PreciseErr { address } => {
+                self.bfar = address;
+                1 << 9 | BFSR_BFARVALID
+            },
+            Fault::UnstkErr => 1 << 11,
+            Fault::StkErr => 1 << 12,
+            Fault::UndefInstr => 1 << 16,
+            Fault::InvState => 1 << 17,
+            Fault::InvPc => 1 << 18,
+            Fault::NoCp => 1 << 19,
+            Fault::Unaligned => 1 << 24,
+            Fault::DivByZero => 1 << 25,
+            Fault::VectTbl => {
+                self.hfsr |= HFSR_VECTTBL;
+                0
+            },
+        };
+
+        self.cfsr |= status;
+    }
+
+    /// Records the escalation of a configurable fault to HardFault, HFSR.FORCED
+    pub fn record_forced(&mut self) {
+        self.hfsr |= HFSR_FORCED;
+    }
+
+    /// Reads an item at an offset from the base of the SCS, which may have side effects such as clearing COUNTFLAG
+    pub fn read(&mut self, offset: u32, size: DataSize) -> u32 {
+        let word = self.read_register(offset & !0b11) >> (8 * (offset & 0b11));
+
+        match size {
+            DataSize::Byte => word & 0xFF,
+            DataSize::Halfword => word & 0xFFFF,
+            DataSize::Word => word,
+        }
+    }
+
+    /// Writes an item at an offset from the base of the SCS
+    pub fn write(&mut self, offset: u32, size: DataSize, value: u32) {
+        let bytes = size.bytes() as u32;
+
+        if byte_accessible(offset) {
+            (0..bytes).for_each(|byte| self.write_priority_byte(offset + byte, (value >> (8 * byte)) as u8));
+        } else {
+            let value = if bytes == 4 { value } else { value & ((1 << (8 * bytes)) - 1) };
+            self.write_register(offset & !0b11, value << (8 * (offset & 0b11)));
+        }
+    }
+
+    fn read_register(&mut self, offset: u32) -> u32 {
+        let nvic = &self.nvic;
+
+        match offset {
+            0x010..=0x01C => self.systick.read_register(offset),
+            0x100..=0x5EC => nvic.read_register(offset),
+
+            ICSR => {
+                let pending = |exception: Exception| nvic.is_pending(exception.number());
+                let vectpending = nvic.pending_exception().map_or(0, |exception| exception.number() as u32);
+                let vectactive = nvic.current_exception().map_or(0, |exception| exception.number() as u32);
+                let isrpending = (0..nvic.irqs() as u16).any(|irq| pending(Exception::Interrupt(irq)));
+
+                (if pending(Exception::Nmi) { ICSR_NMIPENDSET } else { 0 })
+                    | (if pending(Exception::PendSv) { ICSR_PENDSVSET } else { 0 })
+                    | (if pending(Exception::SysTick) { ICSR_PENDSTSET } else { 0 })
+                    | (if isrpending { ICSR_ISRPENDING } else { 0 })
+                    | vectpending << 12
+                    | (if nvic.nested_activation() <= 1 { ICSR_RETTOBASE } else { 0 })
+                    | vectactive
+            },
+
+            VTOR => self.vtor,
+            AIRCR => AIRCR_VECTKEYSTAT << 16 | (nvic.prigroup() as u32) << 8,
+            CCR => self.ccr | CCR_STKALIGN,
+
+            SHCSR => {
+                let bits = |table: &[(u32, Exception)], state: &dyn Fn(u16) -> bool| table.iter()
+                    .filter(|(_, exception)| state(exception.number()))
+                    .fold(0, |word, (bit, _)| word | 1 << bit);
+
+                bits(&SHCSR_ACTIVE, &|n| nvic.is_active(n))
+                    | bits(&SHCSR_PENDED, &|n| nvic.is_pending(n))
+                    | bits(&SHCSR_ENABLED, &|n| nvic.is_enabled(n))
+            },
+
+            CFSR => self.cfsr,
+            HFSR => self.hfsr,
+            MMFAR => self.mmfar,
+            BFAR => self.bfar,
+            0xD18..=0xD20 => (0..4).fold(0, |word, byte| word | (self.read_priority_byte(offset + byte) as u32) << (8 * byte)),
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, offset: u32, value: u32) {
+        match offset {
+            0x010..=0x01C => self.systick.write_register(offset, value),
+            0x100..=0x5EC => self.nvic.write_register(offset, value),
+
+            ICSR => {
+                let nvic = &mut self.nvic;
+
+                if value & ICSR_NMIPENDSET != 0 {
+                    nvic.set_pending(Exception::Nmi.number());
+                }
+
+                if value & ICSR_PENDSVSET != 0 {
+                    nvic.set_pending(Exception::PendSv.number());
+                } else if value & ICSR_PENDSVCLR != 0 {
+                    nvic.clear_pending(Exception::PendSv.number());
+                }
+
+                if value & ICSR_PENDSTSET != 0 {
+                    nvic.set_pending(Exception::SysTick.number());
+                } else if value & ICSR_PENDSTCLR != 0 {
+                    nvic.clear_pending(Exception::SysTick.number());
+                }
+            },
+
+            VTOR => self.vtor = value & VTOR_TBLOFF_MASK,
+
+            // Writes without the key are ignored
+            AIRCR if value >> 16 == AIRCR_VECTKEY => {
+                self.nvic.set_prigroup((value >> 8) as u8);
+                self.reset_requested |= value & AIRCR_SYSRESETREQ != 0;
+            },
+
+            CCR => self.ccr = value & (CCR_UNALIGN_TRP | CCR_DIV_0_TRP),
+
+            SHCSR => {
+                let set = |bit: &u32| value & (1 << bit) != 0;
+
+                for (bit, exception) in SHCSR_ACTIVE.iter() {
+                    if set(bit) { self.nvic.activate(exception.number()) } else { self.nvic.deactivate(exception.number()) }
+                }
+
+                for (bit, exception) in SHCSR_PENDED.iter() {
+                    if set(bit) { self.nvic.set_pending(exception.number()) } else { self.nvic.clear_pending(exception.number()) }
+                }
+
+                for (bit, exception) in SHCSR_ENABLED.iter() {
+                    self.nvic.set_enabled(exception.number(), set(bit));
+                }
+            },
+
+            CFSR => self.cfsr &= !value,
+
+            HFSR => self.hfsr &= !value,
+            MMFAR => self.mmfar = value,
+            BFAR => self.bfar = value,
+
+            _ => (),
+        }
+    }
+
+    fn read_priority_byte(&self, offset: u32) -> u8 {
+        match offset {
+            0x400..=0x5EF => self.nvic.read_priority_byte(offset),
+            0xD18..=0xD23 => self.nvic.priority((offset - 0xD18 + 4) as u16).max(0) as u8,
+            _ => 0,
+        }
+    }
+
+    fn write_priority_byte(&mut self, offset: u32, priority: u8) {
+        match offset {
+            0x400..=0x5EF => self.nvic.write_priority_byte(offset, priority),
+            0xD18..=0xD23 => self.nvic.set_priority((offset - 0xD18 + 4) as u16, priority),
+            _ => (),
+        }
+    }
+}
+
+/// Whether an offset is within the byte accessible priority registers
+fn byte_accessible(offset: u32) -> bool {
+    matches!(offset, 0x400..=0x5EF | 0xD18..=0xD23)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scs_registers() {
+        let mut scs = Scs::new(Nvic::new(32, 4));
+
+        scs.write(VTOR, DataSize::Word, 0x2000_0123);
+        assert_eq!(scs.vtor(), 0x2000_0100);
+
+        scs.write(AIRCR, DataSize::Word, 0x0000_0500);
+        assert_eq!(scs.read(AIRCR, DataSize::Word), 0xFA05_0000, "AIRCR writes without VECTKEY are ignored");
+        scs.write(AIRCR, DataSize::Word, 0x05FA_0500);
+        assert_eq!(scs.read(AIRCR, DataSize::Word), 0xFA05_0500);
+        assert_eq!(scs.nvic.prigroup(), 5);
+        assert!(!scs.take_reset_request());
+
+        scs.write(AIRCR, DataSize::Word, 0x0000_0004);
+        assert!(!scs.take_reset_request(), "a reset request needs the VECTKEY");
+        scs.write(AIRCR, DataSize::Word, 0x05FA_0506);
+        assert!(scs.take_reset_request());
+        assert!(!scs.take_reset_request(), "taking the reset request clears it");
+
+        scs.write(0xD22, DataSize::Byte, 0xE0);
+        scs.write(0xD23, DataSize::Byte, 0x4F);
+        assert_eq!(scs.nvic.priority(Exception::PendSv.number()), 0xE0);
+        assert_eq!(scs.read(0xD20, DataSize::Word), 0x40E0_0000);
+        assert_eq!(scs.read(0xD22, DataSize::Halfword), 0x40E0);
+
+        scs.write(0x403, DataSize::Byte, 0x80);
+        assert_eq!(scs.nvic.priority(16 + 3), 0x80);
+
+        scs.write(ICSR, DataSize::Word, ICSR_PENDSVSET | ICSR_PENDSTSET);
+        assert_eq!(scs.read(ICSR, DataSize::Word), ICSR_PENDSVSET | ICSR_PENDSTSET | 15 << 12 | ICSR_RETTOBASE);
+        scs.write(ICSR, DataSize::Word, ICSR_PENDSTCLR);
+        assert_eq!(scs.read(ICSR, DataSize::Word) >> 12 & 0x1FF, 14);
+
+        scs.write(0x100, DataSize::Word, 1 << 3);
+        scs.write(0x200, DataSize::Byte, 1 << 3);
+        scs.nvic.activate(16 + 3);
+        let icsr = scs.read(ICSR, DataSize::Word);
+        assert_eq!(icsr & ICSR_ISRPENDING, ICSR_ISRPENDING);
+        assert_eq!(icsr & 0x1FF, 16 + 3);
+
+        scs.write(0x101, DataSize::Byte, 1);
+        assert!(scs.nvic.is_enabled(16 + 8), "sub-word writes are placed within the containing word");
+    }
+}
@@ -0,0 +1,139 @@
+// ARMv7-M System Timer, SysTick
+
+const CSR_ENABLE: u32 = 1 << 0;
+const CSR_TICKINT: u32 = 1 << 1;
+const CSR_CLKSOURCE: u32 = 1 << 2;
+const CSR_COUNTFLAG: u32 = 1 << 16;
+
+const RELOAD_MASK: u32 = 0x00FF_FFFF;
+
+/// SYST_CALIB, no reference clock is implemented and no calibration value is known
+const CALIB_NOREF: u32 = 1 << 31;
+const CALIB_SKEW: u32 = 1 << 30;
+
+/// SysTick, a 24 bit timer which counts down once per processor clock cycle
+///
+/// SYSTICK REGISTERS (B3.3.2 pg. 676)
+///
+/// | Address      | Name       | Description                                                                    |
+/// | [0xE000E010] | SYST_CSR   | Control and Status, ENABLE, TICKINT, CLKSOURCE and COUNTFLAG                   |
+/// | [0xE000E014] | SYST_RVR   | Reload Value, loaded into the counter when it wraps                            |
+/// | [0xE000E018] | SYST_CVR   | Current Value, any write clears the counter and COUNTFLAG                      |
+/// | [0xE000E01C] | SYST_CALIB | Calibration Value, read only                                                   |
+///
+/// When the counter steps from 1 to 0 COUNTFLAG is set and, if TICKINT is set, the SysTick exception is pended. The
+/// next cycle reloads the counter from SYST_RVR, so the timer fires every RELOAD + 1 cycles. Reading SYST_CSR clears
+/// COUNTFLAG. There is no external reference clock, so CLKSOURCE reads as 1.
+#[derive(Default)]
+pub struct SysTick {
+    enabled: bool,
+    tickint: bool,
+    countflag: bool,
+    reload: u32,
+    current: u32,
+}
+
+impl SysTick {
+    pub fn new() -> SysTick {
+        Self::default()
+    }
+
+    /// Reads a SysTick register, at an offset from 0xE000E000
+    pub fn read_register(&mut self, offset: u32) -> u32 {
+        match offset {
+            0x010 => {
+                let csr = (if self.enabled { CSR_ENABLE } else { 0 })
+                    | (if self.tickint { CSR_TICKINT } else { 0 })
+                    | CSR_CLKSOURCE
+                    | (if self.countflag { CSR_COUNTFLAG } else { 0 });
+
+                self.countflag = false;
+                csr
+            },
+
+            0x014 => self.reload,
+            0x018 => self.current,
+            0x01C => CALIB_NOREF | CALIB_SKEW,
+            _ => 0,
+        }
+    }
+
+    /// Writes a SysTick register, at an offset from 0xE000E000
+    pub fn write_register(&mut self, offset: u32, value: u32) {
+        match offset {
+            0x010 => {
+                self.enabled = value & CSR_ENABLE != 0;
+                self.tickint = value & CSR_TICKINT != 0;
+            },
+
+            0x014 => self.reload = value & RELOAD_MASK,
+
+            0x018 => {
+                self.current = 0;
+                self.countflag = false;
+            },
+
+            _ => (),
+        }
+    }
+
+    /// Counts down by a number of clock cycles, returning whether the SysTick exception is to be pended
+    pub fn tick(&mut self, cycles: u32) -> bool {
+        let mut fired = false;
+
+        if !self.enabled {
+            return false
+        }
+
+        for _ in 0..cycles {
+            if self.current == 0 {
+                self.current = self.reload;
+            } else {
+                self.current -= 1;
+
+                if self.current == 0 {
+                    self.countflag = true;
+                    fired |= self.tickint;
+                }
+            }
+        }
+
+        fired
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn systick_counting() {
+        let mut systick = SysTick::new();
+
+        systick.write_register(0x014, 0xFF00_0003);
+        assert_eq!(systick.read_register(0x014), 3, "the reload value is 24 bits");
+        assert!(!systick.tick(10), "a disabled timer does not count");
+        assert_eq!(systick.read_register(0x018), 0);
+
+        systick.write_register(0x010, CSR_ENABLE);
+        assert!(!systick.tick(1));
+        assert_eq!(systick.read_register(0x018), 3, "the counter is loaded from the reload value");
+        assert!(!systick.tick(3), "without TICKINT the exception is not pended");
+        assert_eq!(systick.read_register(0x010), CSR_ENABLE | CSR_CLKSOURCE | CSR_COUNTFLAG);
+        assert_eq!(systick.read_register(0x010), CSR_ENABLE | CSR_CLKSOURCE, "reading SYST_CSR clears COUNTFLAG");
+
+        systick.write_register(0x010, CSR_ENABLE | CSR_TICKINT);
+        assert!(!systick.tick(3));
+        assert!(systick.tick(1), "the timer fires every RELOAD + 1 cycles");
+        assert!(systick.tick(4));
+
+        systick.tick(2);
+        systick.write_register(0x018, 0x1234);
+        assert_eq!(systick.read_register(0x018), 0, "writing SYST_CVR clears the counter");
+        assert_eq!(systick.read_register(0x010) & CSR_COUNTFLAG, 0);
+
+        systick.write_register(0x014, 0);
+        assert!(!systick.tick(100), "a reload value of zero disables the counter on its next wrap");
+        assert_eq!(systick.read_register(0x01C), CALIB_NOREF | CALIB_SKEW);
+    }
+}